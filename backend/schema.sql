@@ -1,3 +1,6 @@
+-- 任务广场中文关键词搜索使用三元组索引
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
 -- 用户表
 CREATE TABLE IF NOT EXISTS users (
     id UUID PRIMARY KEY,
@@ -8,9 +11,28 @@ CREATE TABLE IF NOT EXISTS users (
     phone VARCHAR(32),
     student_id VARCHAR(32),
     email VARCHAR(128),
-    created_at TIMESTAMPTZ NOT NULL
+    created_at TIMESTAMPTZ NOT NULL,
+    -- 管理员（教师）可以查看匿名互评的评价人
+    is_admin BOOLEAN NOT NULL DEFAULT FALSE,
+    -- 联系方式的可见范围：public 公开，teammates 仅队友，private 仅自己
+    phone_visibility VARCHAR(16) NOT NULL DEFAULT 'teammates'
+        CHECK (phone_visibility IN ('public', 'teammates', 'private')),
+    student_id_visibility VARCHAR(16) NOT NULL DEFAULT 'teammates'
+        CHECK (student_id_visibility IN ('public', 'teammates', 'private')),
+    email_visibility VARCHAR(16) NOT NULL DEFAULT 'public'
+        CHECK (email_visibility IN ('public', 'teammates', 'private'))
 );
 
+-- 已有数据库补上新增的列，下同
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS is_admin BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN IF NOT EXISTS phone_visibility VARCHAR(16) NOT NULL DEFAULT 'teammates'
+        CHECK (phone_visibility IN ('public', 'teammates', 'private')),
+    ADD COLUMN IF NOT EXISTS student_id_visibility VARCHAR(16) NOT NULL DEFAULT 'teammates'
+        CHECK (student_id_visibility IN ('public', 'teammates', 'private')),
+    ADD COLUMN IF NOT EXISTS email_visibility VARCHAR(16) NOT NULL DEFAULT 'public'
+        CHECK (email_visibility IN ('public', 'teammates', 'private'));
+
 -- 任务表
 CREATE TABLE IF NOT EXISTS tasks (
     id UUID PRIMARY KEY,
@@ -19,15 +41,213 @@ CREATE TABLE IF NOT EXISTS tasks (
     creator_id UUID REFERENCES users(id),
     created_at TIMESTAMPTZ NOT NULL,
     team_size INT NOT NULL DEFAULT 1,
-    status VARCHAR(16) DEFAULT '进行中'
+    -- 草稿 / 招募中 / 进行中 / 审核中 / 已结束 / 已归档 / 重新开启
+    status VARCHAR(16) DEFAULT '进行中',
+    -- 计算进度时是否按子任务预估工时加权
+    weight_by_estimate BOOLEAN NOT NULL DEFAULT FALSE,
+    deadline TIMESTAMPTZ,
+    -- 软删除时间，超过保留期后由清理任务彻底删除
+    deleted_at TIMESTAMPTZ,
+    -- 学科分类标签
+    tags TEXT[] NOT NULL DEFAULT '{}',
+    -- 认领职责是否需要发布者审核
+    approval_required BOOLEAN NOT NULL DEFAULT FALSE,
+    -- 匿名互评：非管理员看不到评价人，某成员收到的评价不少于 evaluation_min_ratings 条时才公开
+    evaluation_anonymous BOOLEAN NOT NULL DEFAULT FALSE,
+    evaluation_min_ratings INT NOT NULL DEFAULT 3,
+    -- 所属课程，用于按课程导出成绩
+    course VARCHAR(64),
+    -- 标题和描述的全文索引
+    search_vector TSVECTOR GENERATED ALWAYS AS (
+        to_tsvector('simple', title || ' ' || description)
+    ) STORED
+);
+
+ALTER TABLE tasks
+    ADD COLUMN IF NOT EXISTS weight_by_estimate BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN IF NOT EXISTS deadline TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS approval_required BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN IF NOT EXISTS evaluation_anonymous BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN IF NOT EXISTS evaluation_min_ratings INT NOT NULL DEFAULT 3,
+    ADD COLUMN IF NOT EXISTS course VARCHAR(64),
+    ADD COLUMN IF NOT EXISTS search_vector TSVECTOR GENERATED ALWAYS AS (
+        to_tsvector('simple', title || ' ' || description)
+    ) STORED;
+
+CREATE INDEX IF NOT EXISTS idx_tasks_deadline ON tasks (deadline);
+CREATE INDEX IF NOT EXISTS idx_tasks_created_at ON tasks (created_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_tasks_creator ON tasks (creator_id);
+CREATE INDEX IF NOT EXISTS idx_tasks_course ON tasks (course);
+CREATE INDEX IF NOT EXISTS idx_tasks_tags ON tasks USING GIN (tags);
+CREATE INDEX IF NOT EXISTS idx_tasks_search ON tasks USING GIN (search_vector);
+CREATE INDEX IF NOT EXISTS idx_tasks_text_trgm ON tasks USING GIN ((title || ' ' || description) gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_tasks_deleted_at ON tasks (deleted_at) WHERE deleted_at IS NOT NULL;
+
+-- 里程碑表
+CREATE TABLE IF NOT EXISTS milestones (
+    id UUID PRIMARY KEY,
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    name VARCHAR(128) NOT NULL,
+    description TEXT,
+    target_date TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_milestones_task_target ON milestones (task_id, target_date);
+
+-- 任务状态变更历史
+CREATE TABLE IF NOT EXISTS task_status_history (
+    id UUID PRIMARY KEY,
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    from_status VARCHAR(16),
+    to_status VARCHAR(16) NOT NULL,
+    changed_by UUID REFERENCES users(id),
+    note TEXT,
+    created_at TIMESTAMPTZ NOT NULL
 );
 
+CREATE INDEX IF NOT EXISTS idx_task_status_history_task ON task_status_history (task_id, created_at);
+
 -- 职责表
 CREATE TABLE IF NOT EXISTS task_roles (
     id UUID PRIMARY KEY,
     task_id UUID REFERENCES tasks(id),
     role_name VARCHAR(64) NOT NULL,
-    user_id UUID REFERENCES users(id)
+    -- 名额，一个职责可以由多名成员担任
+    capacity INT NOT NULL DEFAULT 1 CHECK (capacity >= 1),
+    -- 任务开始后未满的职责关闭，不再接受认领
+    closed BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_roles_task ON task_roles (task_id);
+
+-- 职责成员表
+CREATE TABLE IF NOT EXISTS task_role_members (
+    role_id UUID NOT NULL REFERENCES task_roles(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id),
+    joined_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (role_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_role_members_user ON task_role_members (user_id);
+
+-- 旧版职责表每个职责只有一名成员，记在 task_roles.user_id 上：补上名额列，
+-- 把已认领的成员迁入职责成员表（加入时间取任务创建时间），再删除旧列
+ALTER TABLE task_roles
+    ADD COLUMN IF NOT EXISTS capacity INT NOT NULL DEFAULT 1 CHECK (capacity >= 1),
+    ADD COLUMN IF NOT EXISTS closed BOOLEAN NOT NULL DEFAULT FALSE;
+
+DO $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_schema = current_schema() AND table_name = 'task_roles' AND column_name = 'user_id'
+    ) THEN
+        INSERT INTO task_role_members (role_id, user_id, joined_at)
+        SELECT tr.id, tr.user_id, t.created_at
+        FROM task_roles tr
+        JOIN tasks t ON t.id = tr.task_id
+        WHERE tr.user_id IS NOT NULL
+        ON CONFLICT (role_id, user_id) DO NOTHING;
+        ALTER TABLE task_roles DROP COLUMN user_id;
+    END IF;
+END $$;
+
+-- 加入申请表
+CREATE TABLE IF NOT EXISTS join_requests (
+    id UUID PRIMARY KEY,
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    role_id UUID NOT NULL REFERENCES task_roles(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id),
+    message TEXT,
+    -- 待审核 / 已通过 / 已拒绝 / 已撤回
+    status VARCHAR(16) NOT NULL DEFAULT '待审核',
+    created_at TIMESTAMPTZ NOT NULL,
+    decided_by UUID REFERENCES users(id),
+    decided_at TIMESTAMPTZ,
+    decision_note TEXT
+);
+
+-- 同一职责同一用户只能有一条待审核的申请
+CREATE UNIQUE INDEX IF NOT EXISTS idx_join_requests_pending ON join_requests (role_id, user_id) WHERE status = '待审核';
+CREATE INDEX IF NOT EXISTS idx_join_requests_task ON join_requests (task_id, status);
+CREATE INDEX IF NOT EXISTS idx_join_requests_user ON join_requests (user_id, created_at DESC);
+
+-- 邀请链接表：链接本身是签名 token，这里记录用于列出和撤销
+CREATE TABLE IF NOT EXISTS task_invite_links (
+    id UUID PRIMARY KEY,
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    -- 为空表示可加入任务中的任一职责
+    role_id UUID REFERENCES task_roles(id) ON DELETE CASCADE,
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_invite_links_task ON task_invite_links (task_id, created_at DESC);
+
+-- 直接邀请表
+CREATE TABLE IF NOT EXISTS task_invitations (
+    id UUID PRIMARY KEY,
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    role_id UUID NOT NULL REFERENCES task_roles(id) ON DELETE CASCADE,
+    inviter_id UUID NOT NULL REFERENCES users(id),
+    invitee_id UUID NOT NULL REFERENCES users(id),
+    message TEXT,
+    -- 待回复 / 已接受 / 已拒绝
+    status VARCHAR(16) NOT NULL DEFAULT '待回复',
+    created_at TIMESTAMPTZ NOT NULL,
+    responded_at TIMESTAMPTZ
+);
+
+-- 同一职责对同一用户只能有一条待回复的邀请
+CREATE UNIQUE INDEX IF NOT EXISTS idx_task_invitations_pending ON task_invitations (role_id, invitee_id) WHERE status = '待回复';
+CREATE INDEX IF NOT EXISTS idx_task_invitations_invitee ON task_invitations (invitee_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_task_invitations_task ON task_invitations (task_id, created_at DESC);
+
+-- 任务转让表：发布者发起，新发布者接受后生效
+CREATE TABLE IF NOT EXISTS task_ownership_transfers (
+    id UUID PRIMARY KEY,
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    from_user_id UUID NOT NULL REFERENCES users(id),
+    to_user_id UUID NOT NULL REFERENCES users(id),
+    -- 待接受 / 已接受 / 已拒绝 / 已取消
+    status VARCHAR(16) NOT NULL DEFAULT '待接受',
+    created_at TIMESTAMPTZ NOT NULL,
+    responded_at TIMESTAMPTZ
+);
+
+-- 每个任务同时只能有一条待接受的转让
+CREATE UNIQUE INDEX IF NOT EXISTS idx_task_ownership_transfers_pending ON task_ownership_transfers (task_id) WHERE status = '待接受';
+CREATE INDEX IF NOT EXISTS idx_task_ownership_transfers_to ON task_ownership_transfers (to_user_id, created_at DESC);
+
+-- 成员变动记录：加入 / 退出 / 移除，职责删除后仍保留职责名称
+CREATE TABLE IF NOT EXISTS task_membership_events (
+    id UUID PRIMARY KEY,
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id),
+    role_id UUID REFERENCES task_roles(id) ON DELETE SET NULL,
+    role_name VARCHAR(255) NOT NULL,
+    event VARCHAR(16) NOT NULL,
+    -- 操作人：本人认领或退出时为本人，审核通过或移除时为发布者
+    actor_id UUID REFERENCES users(id),
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_membership_events_task ON task_membership_events (task_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_task_membership_events_user ON task_membership_events (user_id);
+
+-- 为记录表创建之前已加入的成员补一条加入记录
+INSERT INTO task_membership_events (id, task_id, user_id, role_id, role_name, event, actor_id, created_at)
+SELECT gen_random_uuid(), tr.task_id, m.user_id, m.role_id, tr.role_name, '加入', m.user_id, m.joined_at
+FROM task_role_members m
+JOIN task_roles tr ON tr.id = m.role_id
+WHERE NOT EXISTS (
+    SELECT 1 FROM task_membership_events e WHERE e.role_id = m.role_id AND e.user_id = m.user_id
 );
 
 -- 进度表
@@ -36,17 +256,82 @@ CREATE TABLE IF NOT EXISTS progress (
     task_id UUID REFERENCES tasks(id),
     content TEXT NOT NULL,
     percent INT NOT NULL,
-    created_at TIMESTAMPTZ NOT NULL
+    created_at TIMESTAMPTZ NOT NULL,
+    -- manual 为手动填写，auto 为根据子任务自动生成的快照
+    source VARCHAR(16) NOT NULL DEFAULT 'manual',
+    -- 填写人，自动快照为空
+    user_id UUID REFERENCES users(id),
+    -- 内容中 @ 到的任务成员及其位置
+    mentions JSONB NOT NULL DEFAULT '[]'
+);
+
+ALTER TABLE progress
+    ADD COLUMN IF NOT EXISTS source VARCHAR(16) NOT NULL DEFAULT 'manual',
+    ADD COLUMN IF NOT EXISTS user_id UUID REFERENCES users(id),
+    ADD COLUMN IF NOT EXISTS mentions JSONB NOT NULL DEFAULT '[]';
+
+CREATE INDEX IF NOT EXISTS idx_progress_task_created ON progress (task_id, created_at);
+
+-- 互评标准：每个任务可自定义，未配置时在第一次评价时写入默认标准
+CREATE TABLE IF NOT EXISTS evaluation_criteria (
+    id UUID PRIMARY KEY,
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    name VARCHAR(64) NOT NULL,
+    description TEXT,
+    min_score INT NOT NULL,
+    max_score INT NOT NULL,
+    position INT NOT NULL,
+    CHECK (min_score < max_score),
+    UNIQUE (task_id, name)
 );
 
--- 评价表
+-- 旧版评价表（username / content / rate）无法对应到评价人和被评价人，改名保留为 evaluations_legacy，
+-- 再按新结构建表。主键索引一并改名，避免与新表的主键索引重名
+DO $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_schema = current_schema() AND table_name = 'evaluations' AND column_name = 'rate'
+    ) THEN
+        ALTER TABLE evaluations RENAME TO evaluations_legacy;
+        ALTER INDEX evaluations_pkey RENAME TO evaluations_legacy_pkey;
+    END IF;
+END $$;
+
+-- 旧版评价表的 task_id 外键没有级联删除，会使清理软删除任务失败，改为随任务删除
+DO $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM pg_constraint
+        WHERE conrelid = to_regclass('evaluations_legacy') AND conname = 'evaluations_task_id_fkey'
+    ) THEN
+        ALTER TABLE evaluations_legacy
+            DROP CONSTRAINT evaluations_task_id_fkey,
+            ADD CONSTRAINT evaluations_legacy_task_id_fkey
+                FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE;
+    END IF;
+END $$;
+
+-- 评价表：任务结束后成员互评，每个评价人对每名队友一条
 CREATE TABLE IF NOT EXISTS evaluations (
     id UUID PRIMARY KEY,
-    task_id UUID REFERENCES tasks(id),
-    username VARCHAR(64) NOT NULL,
-    content TEXT NOT NULL,
-    rate INT NOT NULL,
-    created_at TIMESTAMPTZ NOT NULL
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    rater_id UUID NOT NULL REFERENCES users(id),
+    target_id UUID NOT NULL REFERENCES users(id),
+    comment TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    UNIQUE (task_id, rater_id, target_id),
+    CHECK (rater_id <> target_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_evaluations_target ON evaluations (task_id, target_id);
+
+-- 每条评价在各项标准上的得分
+CREATE TABLE IF NOT EXISTS evaluation_scores (
+    evaluation_id UUID NOT NULL REFERENCES evaluations(id) ON DELETE CASCADE,
+    criterion_id UUID NOT NULL REFERENCES evaluation_criteria(id) ON DELETE CASCADE,
+    score INT NOT NULL,
+    PRIMARY KEY (evaluation_id, criterion_id)
 );
 
 -- 消息表
@@ -57,6 +342,28 @@ CREATE TABLE IF NOT EXISTS messages (
     created_at TIMESTAMPTZ NOT NULL
 );
 
+-- 周期性子任务模板
+CREATE TABLE IF NOT EXISTS sub_task_templates (
+    id UUID PRIMARY KEY,
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    title VARCHAR(255) NOT NULL,
+    description TEXT,
+    assignee_id UUID REFERENCES users(id),
+    priority SMALLINT NOT NULL DEFAULT 1 CHECK (priority BETWEEN 0 AND 3),
+    labels TEXT[] NOT NULL DEFAULT '{}',
+    -- daily / weekly / custom
+    frequency VARCHAR(16) NOT NULL,
+    interval_days INT NOT NULL CHECK (interval_days > 0),
+    -- 生成的子任务 DDL = 生成时间 + due_offset_hours
+    due_offset_hours INT NOT NULL DEFAULT 0,
+    next_run_at TIMESTAMPTZ NOT NULL,
+    until_at TIMESTAMPTZ,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_sub_task_templates_next_run ON sub_task_templates (next_run_at) WHERE active;
+
 -- 子任务表
 CREATE TABLE IF NOT EXISTS sub_tasks (
     id UUID PRIMARY KEY,
@@ -66,5 +373,215 @@ CREATE TABLE IF NOT EXISTS sub_tasks (
     assignee_id UUID REFERENCES users(id),
     status VARCHAR(16) NOT NULL DEFAULT '未开始',
     created_at TIMESTAMPTZ NOT NULL,
-    due_date TIMESTAMPTZ
-); 
\ No newline at end of file
+    due_date TIMESTAMPTZ,
+    -- 优先级：0 低，1 中，2 高，3 紧急
+    priority SMALLINT NOT NULL DEFAULT 1 CHECK (priority BETWEEN 0 AND 3),
+    labels TEXT[] NOT NULL DEFAULT '{}',
+    -- 由周期模板生成时记录来源与对应的周期时间点，同一时间点只会生成一次
+    template_id UUID REFERENCES sub_task_templates(id) ON DELETE SET NULL,
+    occurrence_at TIMESTAMPTZ,
+    estimate_hours REAL CHECK (estimate_hours >= 0),
+    completed_at TIMESTAMPTZ,
+    milestone_id UUID REFERENCES milestones(id) ON DELETE SET NULL,
+    -- 描述中 @ 到的任务成员及其位置
+    mentions JSONB NOT NULL DEFAULT '[]',
+    UNIQUE (template_id, occurrence_at)
+);
+
+ALTER TABLE sub_tasks
+    ADD COLUMN IF NOT EXISTS priority SMALLINT NOT NULL DEFAULT 1 CHECK (priority BETWEEN 0 AND 3),
+    ADD COLUMN IF NOT EXISTS labels TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS template_id UUID REFERENCES sub_task_templates(id) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS occurrence_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS estimate_hours REAL CHECK (estimate_hours >= 0),
+    ADD COLUMN IF NOT EXISTS completed_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS milestone_id UUID REFERENCES milestones(id) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS mentions JSONB NOT NULL DEFAULT '[]';
+
+-- 与建表时 UNIQUE (template_id, occurrence_at) 生成的索引同名，新建的数据库会跳过
+CREATE UNIQUE INDEX IF NOT EXISTS sub_tasks_template_id_occurrence_at_key ON sub_tasks (template_id, occurrence_at);
+
+-- 子任务筛选与排序索引
+CREATE INDEX IF NOT EXISTS idx_sub_tasks_task_status ON sub_tasks (task_id, status);
+CREATE INDEX IF NOT EXISTS idx_sub_tasks_task_assignee ON sub_tasks (task_id, assignee_id);
+CREATE INDEX IF NOT EXISTS idx_sub_tasks_task_priority ON sub_tasks (task_id, priority);
+CREATE INDEX IF NOT EXISTS idx_sub_tasks_task_due_date ON sub_tasks (task_id, due_date);
+CREATE INDEX IF NOT EXISTS idx_sub_tasks_labels ON sub_tasks USING GIN (labels);
+CREATE INDEX IF NOT EXISTS idx_sub_tasks_milestone ON sub_tasks (milestone_id); 
+
+-- 子任务检查项
+CREATE TABLE IF NOT EXISTS sub_task_checklist_items (
+    id UUID PRIMARY KEY,
+    sub_task_id UUID NOT NULL REFERENCES sub_tasks(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    done BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_checklist_items_sub_task ON sub_task_checklist_items (sub_task_id);
+
+-- 任务计算进度：已完成子任务计满分，未完成子任务按检查项完成比例计分；
+-- weight_by_estimate 为真时按预估工时加权（未填工时按 1 计）
+CREATE OR REPLACE VIEW task_computed_progress AS
+SELECT
+    t.id AS task_id,
+    COALESCE(ROUND(100 * SUM(w.weight * w.done_ratio) / NULLIF(SUM(w.weight), 0)), 0)::INT AS percent
+FROM tasks t
+LEFT JOIN LATERAL (
+    SELECT
+        CASE WHEN t.weight_by_estimate THEN COALESCE(st.estimate_hours, 1)::FLOAT8 ELSE 1 END AS weight,
+        CASE
+            WHEN st.status = '已完成' THEN 1
+            ELSE COALESCE((
+                SELECT AVG(CASE WHEN c.done THEN 1 ELSE 0 END)::FLOAT8
+                FROM sub_task_checklist_items c
+                WHERE c.sub_task_id = st.id
+            ), 0)
+        END AS done_ratio
+    FROM sub_tasks st
+    WHERE st.task_id = t.id
+) w ON TRUE
+GROUP BY t.id;
+
+-- 工时记录
+CREATE TABLE IF NOT EXISTS time_logs (
+    id UUID PRIMARY KEY,
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    sub_task_id UUID REFERENCES sub_tasks(id) ON DELETE SET NULL,
+    user_id UUID NOT NULL REFERENCES users(id),
+    hours REAL NOT NULL CHECK (hours > 0),
+    note TEXT,
+    -- 工作发生的时间
+    logged_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_time_logs_task_logged ON time_logs (task_id, logged_at);
+
+-- 贡献度权重，未配置时使用默认权重
+CREATE TABLE IF NOT EXISTS task_contribution_weights (
+    task_id UUID PRIMARY KEY REFERENCES tasks(id) ON DELETE CASCADE,
+    sub_tasks FLOAT8 NOT NULL CHECK (sub_tasks >= 0),
+    hours FLOAT8 NOT NULL CHECK (hours >= 0),
+    evaluation FLOAT8 NOT NULL CHECK (evaluation >= 0),
+    progress FLOAT8 NOT NULL CHECK (progress >= 0),
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+-- 任务附件
+CREATE TABLE IF NOT EXISTS task_attachments (
+    id UUID PRIMARY KEY,
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    file_name VARCHAR(255) NOT NULL,
+    url VARCHAR(255) NOT NULL,
+    content_type VARCHAR(128) NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    uploaded_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_attachments_task ON task_attachments (task_id, created_at);
+
+-- 任务模板：保存职责、子任务、里程碑结构，日期为相对偏移
+CREATE TABLE IF NOT EXISTS task_templates (
+    id UUID PRIMARY KEY,
+    creator_id UUID NOT NULL REFERENCES users(id),
+    name VARCHAR(128) NOT NULL,
+    source_task_id UUID REFERENCES tasks(id) ON DELETE SET NULL,
+    structure JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_templates_creator ON task_templates (creator_id, created_at DESC);
+
+-- 站内通知表
+CREATE TABLE IF NOT EXISTS notifications (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind VARCHAR(32) NOT NULL,
+    content TEXT NOT NULL,
+    task_id UUID REFERENCES tasks(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL,
+    read_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_notifications_user ON notifications (user_id, created_at DESC);
+
+-- 私信会话，每对用户一个会话，user_a < user_b
+CREATE TABLE IF NOT EXISTS dm_threads (
+    id UUID PRIMARY KEY,
+    user_a UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    user_b UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL,
+    last_message_at TIMESTAMPTZ NOT NULL,
+    UNIQUE (user_a, user_b),
+    CHECK (user_a < user_b)
+);
+
+CREATE INDEX IF NOT EXISTS idx_dm_threads_user_a ON dm_threads (user_a, last_message_at DESC);
+CREATE INDEX IF NOT EXISTS idx_dm_threads_user_b ON dm_threads (user_b, last_message_at DESC);
+
+-- 私信
+CREATE TABLE IF NOT EXISTS direct_messages (
+    id UUID PRIMARY KEY,
+    thread_id UUID NOT NULL REFERENCES dm_threads(id) ON DELETE CASCADE,
+    sender_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_direct_messages_thread ON direct_messages (thread_id, created_at DESC, id DESC);
+
+-- 每个用户在会话中的已读位置，用于计算未读数
+CREATE TABLE IF NOT EXISTS dm_thread_reads (
+    thread_id UUID NOT NULL REFERENCES dm_threads(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    last_read_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (thread_id, user_id)
+);
+
+-- 屏蔽关系：blocked_id 不能再给 blocker_id 发私信
+CREATE TABLE IF NOT EXISTS user_blocks (
+    blocker_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    blocked_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (blocker_id, blocked_id),
+    CHECK (blocker_id <> blocked_id)
+);
+
+-- 任务聊天室消息，parent_id 不为空的是对某条消息的回复
+CREATE TABLE IF NOT EXISTS task_messages (
+    id UUID PRIMARY KEY,
+    task_id UUID NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    sender_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    parent_id UUID REFERENCES task_messages(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL,
+    edited_at TIMESTAMPTZ,
+    -- 软删除，保留记录以免回复失去上下文
+    deleted_at TIMESTAMPTZ,
+    -- 内容中 @ 到的任务成员及其位置
+    mentions JSONB NOT NULL DEFAULT '[]'
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_messages_task ON task_messages (task_id, created_at DESC, id DESC) WHERE parent_id IS NULL;
+CREATE INDEX IF NOT EXISTS idx_task_messages_parent ON task_messages (parent_id, created_at);
+
+-- 消息的编辑历史，记录每次编辑前的内容
+CREATE TABLE IF NOT EXISTS task_message_edits (
+    id UUID PRIMARY KEY,
+    message_id UUID NOT NULL REFERENCES task_messages(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    edited_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_message_edits_message ON task_message_edits (message_id, edited_at);
+
+-- 消息的表情回应，每人对同一表情只计一次
+CREATE TABLE IF NOT EXISTS task_message_reactions (
+    message_id UUID NOT NULL REFERENCES task_messages(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    emoji VARCHAR(32) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (message_id, user_id, emoji)
+);
@@ -1,4 +1,4 @@
-use actix_web::{HttpResponse, web};
+use actix_web::{HttpRequest, HttpResponse, web};
 use bcrypt::{DEFAULT_COST, hash, verify};
 use chrono::{Duration, Utc};
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
@@ -72,3 +72,26 @@ pub async fn login(pool: web::Data<PgPool>, form: web::Json<LoginInput>) -> Http
     }
     HttpResponse::Ok().json(json!({"success": false, "message": "用户名或密码错误"}))
 }
+
+// 从 Authorization: Bearer <token> 中取出登录用户，未登录或 token 无效时返回 None
+pub fn request_user_id(req: &HttpRequest) -> Option<Uuid> {
+    let token = req
+        .headers()
+        .get("Authorization")?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+    decode_user_id(token)
+}
+
+// 校验登录 token 并取出用户 id
+pub fn decode_user_id(token: &str) -> Option<Uuid> {
+    let secret = env::var("JWT_SECRET").ok()?;
+    let data = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(secret.as_ref()),
+        &Validation::default(),
+    )
+    .ok()?;
+    Uuid::parse_str(&data.claims.sub).ok()
+}
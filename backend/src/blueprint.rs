@@ -0,0 +1,271 @@
+use crate::progress::sync_task_progress;
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+// 任务结构快照，用于任务模板和复制任务。
+// 只记录职责、子任务、里程碑等结构，不包含成员、分配、完成情况和历史。
+// 所有日期保存为相对原任务创建当天零点（UTC）的偏移（秒），实例化时按新的开始时间平移。
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TaskBlueprint {
+    pub title: String,
+    pub description: String,
+    pub team_size: i32,
+    pub tags: Vec<String>,
+    pub weight_by_estimate: bool,
+    pub approval_required: bool,
+    pub deadline_offset: Option<i64>,
+    pub roles: Vec<RoleBlueprint>,
+    pub milestones: Vec<MilestoneBlueprint>,
+    pub sub_tasks: Vec<SubTaskBlueprint>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoleBlueprint {
+    pub name: String,
+    pub capacity: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MilestoneBlueprint {
+    pub name: String,
+    pub description: Option<String>,
+    pub target_offset: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubTaskBlueprint {
+    pub title: String,
+    pub description: Option<String>,
+    pub priority: i16,
+    pub labels: Vec<String>,
+    pub estimate_hours: Option<f32>,
+    pub due_offset: Option<i64>,
+    // milestones 中的下标
+    pub milestone: Option<usize>,
+    pub checklist: Vec<String>,
+}
+
+pub struct CapturedTask {
+    pub creator_id: Option<Uuid>,
+    // 偏移的起点，以它作为开始时间实例化会得到与原任务相同的日期
+    pub anchor: DateTime<Utc>,
+    pub blueprint: TaskBlueprint,
+}
+
+// 某一时间当天零点（UTC），即偏移的起点
+pub fn day_start(t: DateTime<Utc>) -> DateTime<Utc> {
+    t.date_naive().and_time(NaiveTime::MIN).and_utc()
+}
+
+// 读取任务当前的结构。周期模板生成的子任务不算标准结构，不会被记录。
+pub async fn capture(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+) -> Result<Option<CapturedTask>, sqlx::Error> {
+    let task = match sqlx::query!(
+        r#"
+        SELECT title, description, creator_id, created_at, team_size, tags, weight_by_estimate, approval_required, deadline
+        FROM tasks WHERE id = $1 AND deleted_at IS NULL
+        "#,
+        task_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    {
+        Some(task) => task,
+        None => return Ok(None),
+    };
+    let anchor = day_start(task.created_at);
+    let offset = |d: DateTime<Utc>| (d - anchor).num_seconds();
+
+    let roles = sqlx::query_as!(
+        RoleBlueprint,
+        "SELECT role_name as name, capacity FROM task_roles WHERE task_id = $1",
+        task_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let milestone_rows = sqlx::query!(
+        "SELECT id, name, description, target_date FROM milestones WHERE task_id = $1 ORDER BY target_date ASC",
+        task_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let sub_task_rows = sqlx::query!(
+        r#"
+        SELECT id, title, description, priority, labels, estimate_hours, due_date, milestone_id
+        FROM sub_tasks
+        WHERE task_id = $1 AND template_id IS NULL AND occurrence_at IS NULL
+        ORDER BY created_at ASC
+        "#,
+        task_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let sub_task_ids: Vec<Uuid> = sub_task_rows.iter().map(|st| st.id).collect();
+    let checklist_rows = sqlx::query!(
+        "SELECT sub_task_id, content FROM sub_task_checklist_items WHERE sub_task_id = ANY($1) ORDER BY created_at ASC",
+        &sub_task_ids
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let sub_tasks = sub_task_rows
+        .into_iter()
+        .map(|st| SubTaskBlueprint {
+            title: st.title,
+            description: st.description,
+            priority: st.priority,
+            labels: st.labels,
+            estimate_hours: st.estimate_hours,
+            due_offset: st.due_date.map(offset),
+            milestone: st
+                .milestone_id
+                .and_then(|id| milestone_rows.iter().position(|m| m.id == id)),
+            checklist: checklist_rows
+                .iter()
+                .filter(|c| c.sub_task_id == st.id)
+                .map(|c| c.content.clone())
+                .collect(),
+        })
+        .collect();
+
+    let milestones = milestone_rows
+        .into_iter()
+        .map(|m| MilestoneBlueprint {
+            name: m.name,
+            description: m.description,
+            target_offset: offset(m.target_date),
+        })
+        .collect();
+
+    Ok(Some(CapturedTask {
+        creator_id: task.creator_id,
+        anchor,
+        blueprint: TaskBlueprint {
+            title: task.title,
+            description: task.description,
+            team_size: task.team_size,
+            tags: task.tags,
+            weight_by_estimate: task.weight_by_estimate,
+            approval_required: task.approval_required,
+            deadline_offset: task.deadline.map(offset),
+            roles,
+            milestones,
+            sub_tasks,
+        },
+    }))
+}
+
+// 按快照创建新任务，所有日期以 start 为起点重新计算。返回新任务 id。
+// 需要在事务中调用，失败时由调用方回滚。
+pub async fn instantiate(
+    conn: &mut PgConnection,
+    blueprint: &TaskBlueprint,
+    creator_id: Uuid,
+    title: &str,
+    start: DateTime<Utc>,
+    status: &str,
+) -> Result<Uuid, sqlx::Error> {
+    let task_id = Uuid::new_v4();
+    let now = Utc::now();
+    let date = |offset: i64| start + Duration::seconds(offset);
+
+    sqlx::query!(
+        r#"
+        INSERT INTO tasks (id, title, description, creator_id, created_at, team_size, deadline, status, tags, weight_by_estimate, approval_required)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        "#,
+        task_id,
+        title,
+        blueprint.description,
+        creator_id,
+        now,
+        blueprint.team_size,
+        blueprint.deadline_offset.map(date),
+        status,
+        &blueprint.tags,
+        blueprint.weight_by_estimate,
+        blueprint.approval_required
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        "INSERT INTO task_status_history (id, task_id, from_status, to_status, changed_by, created_at) VALUES ($1, $2, NULL, $3, $4, $5)",
+        Uuid::new_v4(), task_id, status, creator_id, now
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    for role in &blueprint.roles {
+        sqlx::query!(
+            "INSERT INTO task_roles (id, task_id, role_name, capacity) VALUES ($1, $2, $3, $4)",
+            Uuid::new_v4(),
+            task_id,
+            role.name,
+            role.capacity
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    let mut milestone_ids = Vec::with_capacity(blueprint.milestones.len());
+    for m in &blueprint.milestones {
+        let id = Uuid::new_v4();
+        sqlx::query!(
+            "INSERT INTO milestones (id, task_id, name, description, target_date, created_at) VALUES ($1, $2, $3, $4, $5, $6)",
+            id,
+            task_id,
+            m.name,
+            m.description,
+            date(m.target_offset),
+            now
+        )
+        .execute(&mut *conn)
+        .await?;
+        milestone_ids.push(id);
+    }
+
+    // 子任务和清单都按创建时间排序，逐条错开一微秒保持原顺序
+    for (n, st) in blueprint.sub_tasks.iter().enumerate() {
+        let sub_task_id = Uuid::new_v4();
+        sqlx::query!(
+            r#"
+            INSERT INTO sub_tasks (id, task_id, title, description, created_at, due_date, status, priority, labels, estimate_hours, milestone_id)
+            VALUES ($1, $2, $3, $4, $5, $6, '未开始', $7, $8, $9, $10)
+            "#,
+            sub_task_id,
+            task_id,
+            st.title,
+            st.description,
+            now + Duration::microseconds(n as i64),
+            st.due_offset.map(date),
+            st.priority,
+            &st.labels,
+            st.estimate_hours,
+            st.milestone.and_then(|i| milestone_ids.get(i).copied())
+        )
+        .execute(&mut *conn)
+        .await?;
+        for (i, content) in st.checklist.iter().enumerate() {
+            sqlx::query!(
+                "INSERT INTO sub_task_checklist_items (id, sub_task_id, content, done, created_at) VALUES ($1, $2, $3, FALSE, $4)",
+                Uuid::new_v4(),
+                sub_task_id,
+                content,
+                now + Duration::microseconds(i as i64)
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+    }
+
+    sync_task_progress(conn, task_id).await?;
+    Ok(task_id)
+}
@@ -0,0 +1,290 @@
+use crate::evaluation;
+use crate::membership;
+use crate::privacy;
+use chrono::Utc;
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use std::collections::HashMap;
+use std::fmt::Write;
+use uuid::Uuid;
+
+// 未配置权重时的默认值
+pub const DEFAULT_WEIGHTS: ContributionWeights = ContributionWeights {
+    sub_tasks: 40.0,
+    hours: 20.0,
+    evaluation: 30.0,
+    progress: 10.0,
+};
+
+// 单项权重上限
+pub const MAX_WEIGHT: f64 = 100.0;
+
+// 各项指标在贡献度中的权重，只看相对大小
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ContributionWeights {
+    // 完成的子任务数
+    pub sub_tasks: f64,
+    // 登记的工时
+    pub hours: f64,
+    // 互评综合分
+    pub evaluation: f64,
+    // 填写的进度记录数
+    pub progress: f64,
+}
+
+impl ContributionWeights {
+    pub fn validate(&self) -> Option<String> {
+        let weights = [self.sub_tasks, self.hours, self.evaluation, self.progress];
+        if weights
+            .iter()
+            .any(|w| !w.is_finite() || !(0.0..=MAX_WEIGHT).contains(w))
+        {
+            return Some(format!("权重需在 0 到 {} 之间", MAX_WEIGHT));
+        }
+        if weights.iter().sum::<f64>() <= 0.0 {
+            return Some("至少一项权重大于 0".to_string());
+        }
+        None
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemberContribution {
+    pub task_id: Uuid,
+    pub task_title: String,
+    pub user_id: Uuid,
+    pub name: Option<String>,
+    pub username: String,
+    pub student_id: Option<String>,
+    pub completed_sub_tasks: i64,
+    pub hours: f64,
+    // 互评综合分（百分制），没有收到评价时为空
+    pub evaluation: Option<f64>,
+    pub progress_entries: i64,
+    // 贡献度（百分制）
+    pub score: f64,
+}
+
+// 任务的贡献度权重，configured 为 false 时是默认权重
+pub async fn load_weights(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+) -> Result<(ContributionWeights, bool), sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT sub_tasks, hours, evaluation, progress
+        FROM task_contribution_weights WHERE task_id = $1
+        "#,
+        task_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    Ok(match row {
+        Some(r) => (
+            ContributionWeights {
+                sub_tasks: r.sub_tasks,
+                hours: r.hours,
+                evaluation: r.evaluation,
+                progress: r.progress,
+            },
+            true,
+        ),
+        None => (DEFAULT_WEIGHTS, false),
+    })
+}
+
+pub async fn save_weights(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    weights: &ContributionWeights,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO task_contribution_weights (task_id, sub_tasks, hours, evaluation, progress, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (task_id) DO UPDATE SET
+            sub_tasks = EXCLUDED.sub_tasks, hours = EXCLUDED.hours,
+            evaluation = EXCLUDED.evaluation, progress = EXCLUDED.progress,
+            updated_at = EXCLUDED.updated_at
+        "#,
+        task_id,
+        weights.sub_tasks,
+        weights.hours,
+        weights.evaluation,
+        weights.progress,
+        Utc::now()
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+// 计算任务每名成员（含中途退出或被移除的成员）的贡献度。
+// 子任务、工时、进度记录按与队内最高者的比值计分，互评按综合分计分；
+// 全队都没有数据的指标不参与计算，某成员没有收到互评时其互评权重也不计入。
+// 学号按查看人 viewer 和隐私设置过滤；匿名互评中非管理员看不到评价数未达门槛的成员的互评分，
+// 该成员按没有收到互评计算
+pub async fn compute(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    viewer: Uuid,
+) -> Result<Option<Vec<MemberContribution>>, sqlx::Error> {
+    let Some(task_title) = sqlx::query_scalar!(
+        "SELECT title FROM tasks WHERE id = $1 AND deleted_at IS NULL",
+        task_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    else {
+        return Ok(None);
+    };
+    let (weights, _) = load_weights(conn, task_id).await?;
+
+    let members = sqlx::query!(
+        r#"
+        SELECT u.id, u.name, u.username,
+            (SELECT COUNT(*) FROM sub_tasks st
+                WHERE st.task_id = $1 AND st.assignee_id = u.id AND st.status = '已完成') as "completed!",
+            (SELECT COALESCE(SUM(l.hours), 0)::FLOAT8 FROM time_logs l
+                WHERE l.task_id = $1 AND l.user_id = u.id) as "hours!",
+            (SELECT COUNT(*) FROM progress p
+                WHERE p.task_id = $1 AND p.user_id = u.id) as "progress_entries!"
+        FROM users u
+        WHERE u.id IN (SELECT user_id FROM task_membership_events WHERE task_id = $1 AND event = $2)
+        ORDER BY u.username
+        "#,
+        task_id,
+        membership::EVENT_JOINED
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let mut summaries = evaluation::summarize(conn, task_id).await?;
+    if let Some(visibility) = evaluation::visibility(conn, task_id, Some(viewer)).await?
+        && visibility.restricted
+    {
+        evaluation::apply_k_anonymity(&mut summaries, visibility.min_ratings);
+    }
+    let ratings: HashMap<Uuid, f64> = summaries
+        .into_iter()
+        .filter_map(|s| s.overall.map(|o| (s.user_id, o)))
+        .collect();
+    let user_ids: Vec<Uuid> = members.iter().map(|m| m.id).collect();
+    let contacts = privacy::visible_contacts(conn, Some(viewer), &user_ids).await?;
+
+    let max_completed = members.iter().map(|m| m.completed).max().unwrap_or(0) as f64;
+    let max_hours = members.iter().map(|m| m.hours).fold(0.0, f64::max);
+    let max_progress = members
+        .iter()
+        .map(|m| m.progress_entries)
+        .max()
+        .unwrap_or(0) as f64;
+
+    let list = members
+        .into_iter()
+        .map(|m| {
+            let evaluation = ratings.get(&m.id).copied();
+            // (权重, 0 到 1 的得分)
+            let parts = [
+                (max_completed > 0.0)
+                    .then(|| (weights.sub_tasks, m.completed as f64 / max_completed)),
+                (max_hours > 0.0).then(|| (weights.hours, m.hours / max_hours)),
+                evaluation.map(|e| (weights.evaluation, e / 100.0)),
+                (max_progress > 0.0)
+                    .then(|| (weights.progress, m.progress_entries as f64 / max_progress)),
+            ];
+            let total_weight: f64 = parts.iter().flatten().map(|(w, _)| w).sum();
+            let score = if total_weight > 0.0 {
+                100.0 * parts.iter().flatten().map(|(w, s)| w * s).sum::<f64>() / total_weight
+            } else {
+                0.0
+            };
+            MemberContribution {
+                task_id,
+                task_title: task_title.clone(),
+                user_id: m.id,
+                name: m.name,
+                username: m.username,
+                student_id: contacts
+                    .get(&m.id)
+                    .and_then(|(_, contact)| contact.student_id.clone()),
+                completed_sub_tasks: m.completed,
+                hours: m.hours,
+                evaluation,
+                progress_entries: m.progress_entries,
+                score: (score * 10.0).round() / 10.0,
+            }
+        })
+        .collect();
+    Ok(Some(list))
+}
+
+const EXPORT_HEADERS: [&str; 9] = [
+    "任务",
+    "姓名",
+    "用户名",
+    "学号",
+    "完成子任务",
+    "工时",
+    "互评综合分",
+    "进度记录",
+    "贡献度",
+];
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// 带 BOM，Excel 打开时中文不乱码
+pub fn to_csv(rows: &[MemberContribution]) -> String {
+    let mut out = String::from("\u{feff}");
+    let _ = writeln!(out, "{}", EXPORT_HEADERS.join(","));
+    for r in rows {
+        let fields = [
+            csv_field(&r.task_title),
+            csv_field(r.name.as_deref().unwrap_or("")),
+            csv_field(&r.username),
+            csv_field(r.student_id.as_deref().unwrap_or("")),
+            r.completed_sub_tasks.to_string(),
+            format!("{:.1}", r.hours),
+            r.evaluation
+                .map(|e| format!("{:.1}", e))
+                .unwrap_or_default(),
+            r.progress_entries.to_string(),
+            format!("{:.1}", r.score),
+        ];
+        let _ = writeln!(out, "{}", fields.join(","));
+    }
+    out
+}
+
+pub fn to_xlsx(rows: &[MemberContribution]) -> Result<Vec<u8>, XlsxError> {
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("贡献度")?;
+    let bold = Format::new().set_bold();
+    let decimal = Format::new().set_num_format("0.0");
+    for (col, header) in EXPORT_HEADERS.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *header, &bold)?;
+    }
+    for (i, r) in rows.iter().enumerate() {
+        let row = i as u32 + 1;
+        sheet.write_string(row, 0, &r.task_title)?;
+        sheet.write_string(row, 1, r.name.as_deref().unwrap_or(""))?;
+        sheet.write_string(row, 2, &r.username)?;
+        sheet.write_string(row, 3, r.student_id.as_deref().unwrap_or(""))?;
+        sheet.write_number(row, 4, r.completed_sub_tasks as f64)?;
+        sheet.write_number_with_format(row, 5, r.hours, &decimal)?;
+        if let Some(e) = r.evaluation {
+            sheet.write_number_with_format(row, 6, e, &decimal)?;
+        }
+        sheet.write_number(row, 7, r.progress_entries as f64)?;
+        sheet.write_number_with_format(row, 8, r.score, &decimal)?;
+    }
+    sheet.autofit();
+    workbook.save_to_buffer()
+}
@@ -0,0 +1,176 @@
+use crate::models::DirectMessage;
+use chrono::Utc;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+// 单条私信的最大字数
+pub const MAX_CONTENT_CHARS: usize = 2000;
+
+pub enum DirectMessageError {
+    SelfMessage,
+    EmptyContent,
+    TooLong,
+    UserNotFound,
+    // 对方屏蔽了发送人
+    Blocked,
+    // 发送人屏蔽了对方
+    BlockedByMe,
+    Db(sqlx::Error),
+}
+
+impl From<sqlx::Error> for DirectMessageError {
+    fn from(e: sqlx::Error) -> Self {
+        DirectMessageError::Db(e)
+    }
+}
+
+impl DirectMessageError {
+    pub fn message(&self) -> String {
+        match self {
+            DirectMessageError::SelfMessage => "不能给自己发私信".to_string(),
+            DirectMessageError::EmptyContent => "私信内容不能为空".to_string(),
+            DirectMessageError::TooLong => format!("私信不能超过{}字", MAX_CONTENT_CHARS),
+            DirectMessageError::UserNotFound => "用户不存在".to_string(),
+            DirectMessageError::Blocked => "对方已屏蔽你，无法发送私信".to_string(),
+            DirectMessageError::BlockedByMe => "你已屏蔽对方，解除屏蔽后才能发送私信".to_string(),
+            DirectMessageError::Db(e) => e.to_string(),
+        }
+    }
+}
+
+pub async fn is_blocked(
+    conn: &mut PgConnection,
+    blocker_id: Uuid,
+    blocked_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM user_blocks WHERE blocker_id = $1 AND blocked_id = $2) as "blocked!""#,
+        blocker_id,
+        blocked_id
+    )
+    .fetch_one(&mut *conn)
+    .await
+}
+
+// 发送私信：两人之间没有会话时创建会话，发送人的已读位置同步到这条消息
+pub async fn send(
+    conn: &mut PgConnection,
+    sender_id: Uuid,
+    recipient_id: Uuid,
+    content: &str,
+) -> Result<DirectMessage, DirectMessageError> {
+    if sender_id == recipient_id {
+        return Err(DirectMessageError::SelfMessage);
+    }
+    let content = content.trim();
+    if content.is_empty() {
+        return Err(DirectMessageError::EmptyContent);
+    }
+    if content.chars().count() > MAX_CONTENT_CHARS {
+        return Err(DirectMessageError::TooLong);
+    }
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM users WHERE id = $1) as "exists!""#,
+        recipient_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if !exists {
+        return Err(DirectMessageError::UserNotFound);
+    }
+    if is_blocked(conn, recipient_id, sender_id).await? {
+        return Err(DirectMessageError::Blocked);
+    }
+    if is_blocked(conn, sender_id, recipient_id).await? {
+        return Err(DirectMessageError::BlockedByMe);
+    }
+
+    let now = Utc::now();
+    let thread_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO dm_threads (id, user_a, user_b, created_at, last_message_at)
+        VALUES ($1, LEAST($2::UUID, $3::UUID), GREATEST($2::UUID, $3::UUID), $4, $4)
+        ON CONFLICT (user_a, user_b) DO UPDATE SET last_message_at = EXCLUDED.last_message_at
+        RETURNING id
+        "#,
+        Uuid::new_v4(),
+        sender_id,
+        recipient_id,
+        now
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    let message = sqlx::query_as!(
+        DirectMessage,
+        r#"
+        INSERT INTO direct_messages (id, thread_id, sender_id, content, created_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, thread_id, sender_id, content, created_at
+        "#,
+        Uuid::new_v4(),
+        thread_id,
+        sender_id,
+        content,
+        now
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    mark_read(conn, thread_id, sender_id).await?;
+    Ok(message)
+}
+
+// 会话中另一方的用户 id，user_id 不是会话参与人时返回 None
+pub async fn thread_peer(
+    conn: &mut PgConnection,
+    thread_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT CASE WHEN user_a = $2 THEN user_b ELSE user_a END as "peer!"
+        FROM dm_threads WHERE id = $1 AND $2 IN (user_a, user_b)
+        "#,
+        thread_id,
+        user_id
+    )
+    .fetch_optional(&mut *conn)
+    .await
+}
+
+pub async fn mark_read(
+    conn: &mut PgConnection,
+    thread_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO dm_thread_reads (thread_id, user_id, last_read_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (thread_id, user_id) DO UPDATE
+            SET last_read_at = GREATEST(dm_thread_reads.last_read_at, EXCLUDED.last_read_at)
+        "#,
+        thread_id,
+        user_id,
+        Utc::now()
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+// 用户所有会话中未读私信的总数
+pub async fn unread_count(conn: &mut PgConnection, user_id: Uuid) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "count!"
+        FROM direct_messages d
+        JOIN dm_threads th ON th.id = d.thread_id
+        LEFT JOIN dm_thread_reads r ON r.thread_id = th.id AND r.user_id = $1
+        WHERE $1 IN (th.user_a, th.user_b) AND d.sender_id <> $1
+            AND d.created_at > COALESCE(r.last_read_at, '-infinity')
+        "#,
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await
+}
@@ -0,0 +1,411 @@
+use crate::lifecycle;
+use crate::membership;
+use crate::models::EvaluationCriterion;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// 未配置评分标准时使用的默认标准：名称、说明
+pub const DEFAULT_CRITERIA: [(&str, &str); 3] = [
+    ("沟通协作", "是否积极沟通、及时回应队友"),
+    ("任务贡献", "承担的工作量与对任务推进的作用"),
+    ("完成质量", "交付成果的质量与可靠程度"),
+];
+pub const DEFAULT_MIN_SCORE: i32 = 1;
+pub const DEFAULT_MAX_SCORE: i32 = 5;
+
+// 自定义标准的限制
+pub const MAX_CRITERIA: usize = 10;
+pub const SCORE_LIMIT: std::ops::RangeInclusive<i32> = 0..=100;
+
+// 匿名互评时公开汇总所需的最少评价数
+pub const MIN_RATINGS_RANGE: std::ops::RangeInclusive<i32> = 2..=20;
+
+pub enum EvaluationError {
+    TaskNotFound,
+    NotFinished,
+    NotMember,
+    SelfEvaluation,
+    AlreadyEvaluated,
+    MissingScore(String),
+    UnknownCriterion(String),
+    OutOfRange { name: String, min: i32, max: i32 },
+    Db(sqlx::Error),
+}
+
+impl From<sqlx::Error> for EvaluationError {
+    fn from(e: sqlx::Error) -> Self {
+        EvaluationError::Db(e)
+    }
+}
+
+impl EvaluationError {
+    pub fn message(&self) -> String {
+        match self {
+            EvaluationError::TaskNotFound => "任务不存在".to_string(),
+            EvaluationError::NotFinished => "任务结束后才能互评".to_string(),
+            EvaluationError::NotMember => "只有任务成员之间可以互评".to_string(),
+            EvaluationError::SelfEvaluation => "不能评价自己".to_string(),
+            EvaluationError::AlreadyEvaluated => "你已经评价过该成员".to_string(),
+            EvaluationError::MissingScore(name) => format!("缺少「{}」的评分", name),
+            EvaluationError::UnknownCriterion(name) => format!("评分标准「{}」不存在", name),
+            EvaluationError::OutOfRange { name, min, max } => {
+                format!("「{}」的评分需在{}到{}之间", name, min, max)
+            }
+            EvaluationError::Db(e) => e.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CriterionInput {
+    pub name: String,
+    pub description: Option<String>,
+    pub min_score: Option<i32>,
+    pub max_score: Option<i32>,
+}
+
+// 校验自定义评分标准，返回错误信息
+pub fn validate_rubric(criteria: &[CriterionInput]) -> Option<String> {
+    if criteria.is_empty() || criteria.len() > MAX_CRITERIA {
+        return Some(format!("评分标准需为1到{}项", MAX_CRITERIA));
+    }
+    let mut names = Vec::with_capacity(criteria.len());
+    for c in criteria {
+        let name = c.name.trim();
+        if name.is_empty() {
+            return Some("评分标准名称不能为空".to_string());
+        }
+        if names.contains(&name) {
+            return Some(format!("评分标准「{}」重复", name));
+        }
+        names.push(name);
+        let min = c.min_score.unwrap_or(DEFAULT_MIN_SCORE);
+        let max = c.max_score.unwrap_or(DEFAULT_MAX_SCORE);
+        if !SCORE_LIMIT.contains(&min) || !SCORE_LIMIT.contains(&max) || min >= max {
+            return Some(format!(
+                "「{}」的分值范围需在{}到{}之间且最低分小于最高分",
+                name,
+                SCORE_LIMIT.start(),
+                SCORE_LIMIT.end()
+            ));
+        }
+    }
+    None
+}
+
+// 任务当前的评分标准；未配置时返回默认标准（id 为新生成，尚未写入）
+pub async fn load_rubric(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+) -> Result<(Vec<EvaluationCriterion>, bool), sqlx::Error> {
+    let criteria = sqlx::query_as!(
+        EvaluationCriterion,
+        "SELECT * FROM evaluation_criteria WHERE task_id = $1 ORDER BY position ASC",
+        task_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    if !criteria.is_empty() {
+        return Ok((criteria, true));
+    }
+    let defaults = DEFAULT_CRITERIA
+        .iter()
+        .enumerate()
+        .map(|(i, (name, description))| EvaluationCriterion {
+            id: Uuid::new_v4(),
+            task_id,
+            name: name.to_string(),
+            description: Some(description.to_string()),
+            min_score: DEFAULT_MIN_SCORE,
+            max_score: DEFAULT_MAX_SCORE,
+            position: i as i32,
+        })
+        .collect();
+    Ok((defaults, false))
+}
+
+// 写入评分标准。调用方需先删除旧标准或确认尚未配置
+pub async fn save_rubric(
+    conn: &mut PgConnection,
+    criteria: &[EvaluationCriterion],
+) -> Result<(), sqlx::Error> {
+    for c in criteria {
+        sqlx::query!(
+            r#"
+            INSERT INTO evaluation_criteria (id, task_id, name, description, min_score, max_score, position)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            c.id,
+            c.task_id,
+            c.name,
+            c.description,
+            c.min_score,
+            c.max_score,
+            c.position
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+// 校验并写入一条互评，scores 以评分标准名称为键。需要在事务中调用。
+// 第一次有人评价时把默认标准写入任务，之后的评价和统计都引用同一组标准。
+pub async fn submit(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    rater_id: Uuid,
+    target_id: Uuid,
+    scores: &HashMap<String, i32>,
+    comment: Option<&str>,
+) -> Result<Uuid, EvaluationError> {
+    // 锁住任务行，默认标准只会被写入一次
+    let status = sqlx::query_scalar!(
+        "SELECT status FROM tasks WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+        task_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(EvaluationError::TaskNotFound)?;
+    if status.as_deref() != Some(lifecycle::FINISHED) {
+        return Err(EvaluationError::NotFinished);
+    }
+    if rater_id == target_id {
+        return Err(EvaluationError::SelfEvaluation);
+    }
+    let roster = membership::team_roster(conn, task_id).await?;
+    if !roster.contains(&rater_id) || !roster.contains(&target_id) {
+        return Err(EvaluationError::NotMember);
+    }
+    let evaluated = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM evaluations WHERE task_id = $1 AND rater_id = $2 AND target_id = $3) as "exists!""#,
+        task_id,
+        rater_id,
+        target_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if evaluated {
+        return Err(EvaluationError::AlreadyEvaluated);
+    }
+
+    let (criteria, configured) = load_rubric(conn, task_id).await?;
+    if let Some(name) = scores
+        .keys()
+        .find(|name| !criteria.iter().any(|c| &c.name == *name))
+    {
+        return Err(EvaluationError::UnknownCriterion(name.clone()));
+    }
+    for c in &criteria {
+        let score = *scores
+            .get(&c.name)
+            .ok_or_else(|| EvaluationError::MissingScore(c.name.clone()))?;
+        if score < c.min_score || score > c.max_score {
+            return Err(EvaluationError::OutOfRange {
+                name: c.name.clone(),
+                min: c.min_score,
+                max: c.max_score,
+            });
+        }
+    }
+    if !configured {
+        save_rubric(conn, &criteria).await?;
+    }
+
+    let id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO evaluations (id, task_id, rater_id, target_id, comment, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        id,
+        task_id,
+        rater_id,
+        target_id,
+        comment,
+        Utc::now()
+    )
+    .execute(&mut *conn)
+    .await?;
+    for c in &criteria {
+        sqlx::query!(
+            "INSERT INTO evaluation_scores (evaluation_id, criterion_id, score) VALUES ($1, $2, $3)",
+            id,
+            c.id,
+            scores[&c.name]
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(id)
+}
+
+#[derive(Debug, Serialize)]
+pub struct CriterionAverage {
+    pub criterion_id: Uuid,
+    pub name: String,
+    pub min_score: i32,
+    pub max_score: i32,
+    pub average: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemberEvaluationSummary {
+    pub user_id: Uuid,
+    pub name: Option<String>,
+    pub username: String,
+    pub rating_count: i64,
+    // 匿名互评中评价数不足时为 false，此时不返回分数
+    pub revealed: bool,
+    // 各项标准平均分换算成百分制后的平均值，没有评价时为空
+    pub overall: Option<f64>,
+    pub criteria: Vec<CriterionAverage>,
+}
+
+// 按成员汇总互评结果，包含尚未收到评价的成员
+pub async fn summarize(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+) -> Result<Vec<MemberEvaluationSummary>, sqlx::Error> {
+    let (criteria, _) = load_rubric(conn, task_id).await?;
+    let members = sqlx::query!(
+        r#"
+        SELECT u.id, u.name, u.username,
+            (SELECT COUNT(*) FROM evaluations e WHERE e.task_id = $1 AND e.target_id = u.id) as "rating_count!"
+        FROM users u
+        WHERE u.id IN (SELECT user_id FROM task_membership_events WHERE task_id = $1 AND event = $2)
+        ORDER BY u.username
+        "#,
+        task_id,
+        membership::EVENT_JOINED
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let averages = sqlx::query!(
+        r#"
+        SELECT e.target_id, s.criterion_id, AVG(s.score)::FLOAT8 as "average!"
+        FROM evaluations e
+        JOIN evaluation_scores s ON s.evaluation_id = e.id
+        WHERE e.task_id = $1
+        GROUP BY e.target_id, s.criterion_id
+        "#,
+        task_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(members
+        .into_iter()
+        .map(|m| {
+            let criteria: Vec<CriterionAverage> = criteria
+                .iter()
+                .map(|c| CriterionAverage {
+                    criterion_id: c.id,
+                    name: c.name.clone(),
+                    min_score: c.min_score,
+                    max_score: c.max_score,
+                    average: averages
+                        .iter()
+                        .find(|a| a.target_id == m.id && a.criterion_id == c.id)
+                        .map(|a| a.average),
+                })
+                .collect();
+            let normalized: Vec<f64> = criteria
+                .iter()
+                .filter_map(|c| {
+                    c.average.map(|avg| {
+                        (avg - c.min_score as f64) / (c.max_score - c.min_score) as f64 * 100.0
+                    })
+                })
+                .collect();
+            MemberEvaluationSummary {
+                user_id: m.id,
+                name: m.name,
+                username: m.username,
+                rating_count: m.rating_count,
+                revealed: true,
+                overall: (!normalized.is_empty())
+                    .then(|| normalized.iter().sum::<f64>() / normalized.len() as f64),
+                criteria,
+            }
+        })
+        .collect())
+}
+
+// 查看互评时的可见范围。匿名互评中非管理员受限：看不到评价人和评价时间，
+// 收到的评价少于 min_ratings 条的成员不公开分数和评语
+pub struct Visibility {
+    pub restricted: bool,
+    pub min_ratings: i64,
+}
+
+// viewer 为登录用户，未登录按非管理员处理。任务不存在时返回 None
+pub async fn visibility(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    viewer: Option<Uuid>,
+) -> Result<Option<Visibility>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT evaluation_anonymous, evaluation_min_ratings,
+            COALESCE((SELECT is_admin FROM users WHERE id = $2), FALSE) as "is_admin!"
+        FROM tasks WHERE id = $1 AND deleted_at IS NULL
+        "#,
+        task_id,
+        viewer
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    Ok(row.map(|r| Visibility {
+        restricted: r.evaluation_anonymous && !r.is_admin,
+        min_ratings: r.evaluation_min_ratings as i64,
+    }))
+}
+
+// 隐藏评价数不足的成员的分数
+pub fn apply_k_anonymity(summaries: &mut [MemberEvaluationSummary], min_ratings: i64) {
+    for s in summaries
+        .iter_mut()
+        .filter(|s| s.rating_count < min_ratings)
+    {
+        s.revealed = false;
+        s.overall = None;
+        for c in &mut s.criteria {
+            c.average = None;
+        }
+    }
+}
+
+// 用户在所有任务中收到的互评：每条评价先按各项标准换算成百分制再取平均。
+// 匿名互评中评价数未达到公开门槛的任务不计入。返回 (平均分, 评价数)
+pub async fn user_rating(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<(Option<f64>, i64), sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT AVG(n.normalized) as average, COUNT(*) as "rating_count!"
+        FROM (
+            SELECT e.id,
+                AVG((s.score - c.min_score)::FLOAT8 / (c.max_score - c.min_score) * 100) as normalized
+            FROM evaluations e
+            JOIN tasks t ON t.id = e.task_id
+            JOIN evaluation_scores s ON s.evaluation_id = e.id
+            JOIN evaluation_criteria c ON c.id = s.criterion_id
+            WHERE e.target_id = $1 AND t.deleted_at IS NULL
+                AND (NOT t.evaluation_anonymous OR (
+                    SELECT COUNT(*) FROM evaluations x
+                    WHERE x.task_id = e.task_id AND x.target_id = e.target_id
+                ) >= t.evaluation_min_ratings)
+            GROUP BY e.id
+        ) n
+        "#,
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok((row.average, row.rating_count))
+}
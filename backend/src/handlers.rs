@@ -1,16 +1,39 @@
 use crate::RoleInfo;
-use crate::auth::Claims;
-use crate::models::{Evaluation, Message, MyRole, Progress, SubTask, Task, TaskRole, User};
+use crate::auth::{self, Claims};
+use crate::blueprint;
+use crate::contribution;
+use crate::direct_messages::{self, DirectMessageError};
+use crate::evaluation::{self, EvaluationError};
+use crate::models::{
+    ChecklistItem, DirectMessage, EvaluationCriterion, Milestone, MyRole, Notification, Progress,
+    RoleMember, SubTask, SubTaskTemplate, Task, TaskAttachment, TaskRole, TaskStatusHistory,
+    TaskTemplate, TimeLog,
+};
+use crate::invites;
+use crate::lifecycle::{self, TransitionError};
+use crate::membership::{self, JoinError, RoleSlot};
+use crate::mentions::{self, Mention};
+use crate::notifications;
+use crate::privacy;
+use crate::progress::sync_task_progress;
+use crate::report::build_weekly_report;
+use crate::reputation;
+use crate::scheduler::TASK_RETENTION_DAYS;
+use crate::task_chat::{self, ChatError, ChatMessage};
+use crate::ws;
+use actix::Addr;
 use actix_multipart::Multipart;
 use actix_web::{HttpResponse, web};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use futures_util::{StreamExt, TryStreamExt};
 use jsonwebtoken::{DecodingKey, Validation, decode};
 use sanitize_filename::sanitize;
 use serde::Deserialize;
 use serde::Serialize;
 use serde_json::json;
-use sqlx::PgPool;
+use sqlx::types::Json;
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
+use std::collections::HashMap;
 use std::env;
 use std::io::Write;
 use uuid::Uuid;
@@ -26,6 +49,10 @@ pub struct TaskDetails {
     pub status: String,
     pub creator_name: Option<String>,
     pub creator_username: String,
+    pub progress: i32,
+    pub deadline: Option<DateTime<Utc>>,
+    pub tags: Vec<String>,
+    pub approval_required: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -34,10 +61,149 @@ pub struct TaskInput {
     pub description: String,
     pub creator_id: Uuid,
     pub team_size: i32,
-    pub roles: Vec<String>,
+    pub roles: Vec<RoleInput>,
+    pub deadline: Option<DateTime<Utc>>,
+    // 草稿 / 招募中 / 进行中，默认进行中
+    pub status: Option<String>,
+    // 学科分类，如 编程、设计、竞赛、论文
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // 认领职责需要发布者审核
+    #[serde(default)]
+    pub approval_required: bool,
+    // 所属课程
+    pub course: Option<String>,
+}
+
+// 职责可以只写名称（名额为 1），也可以写成 {"name": "开发", "capacity": 3}
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum RoleInput {
+    Name(String),
+    WithCapacity { name: String, capacity: i32 },
+}
+
+impl RoleInput {
+    fn name(&self) -> &str {
+        match self {
+            RoleInput::Name(name) | RoleInput::WithCapacity { name, .. } => name,
+        }
+    }
+
+    fn capacity(&self) -> i32 {
+        match self {
+            RoleInput::Name(_) => 1,
+            RoleInput::WithCapacity { capacity, .. } => *capacity,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TaskListQuery {
+    // deadline 按截止日期由近到远，默认按创建时间倒序
+    pub sort: Option<String>,
+    // 标题和描述的关键词搜索
+    pub q: Option<String>,
+    pub creator_id: Option<Uuid>,
+    pub tag: Option<String>,
+    // true 只看还有空缺名额的任务，false 只看已招满的
+    pub has_open_roles: Option<bool>,
+    pub min_team_size: Option<i32>,
+    pub max_team_size: Option<i32>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    // 上一页返回的 next_cursor
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskPage {
+    pub items: Vec<TaskDetails>,
+    pub next_cursor: Option<String>,
+}
+
+const TASK_PAGE_DEFAULT_LIMIT: i64 = 20;
+const TASK_PAGE_MAX_LIMIT: i64 = 100;
+
+// 游标记录上一页最后一条的排序键，格式为以 "_" 分隔的字段：
+// 按创建时间：<created_at 微秒>_<id>
+// 按截止日期：<第一页的查询时间 微秒>_<是否已过期 0/1>_<deadline 微秒，无截止日期为 inf>_<id>。
+// 是否过期按第一页的查询时间判断，翻页期间有任务到期也不会换到另一段
+enum TaskCursor {
+    Created(DateTime<Utc>, Uuid),
+    Deadline(DateTime<Utc>, bool, Option<DateTime<Utc>>, Uuid),
+}
+
+impl TaskCursor {
+    fn encode(&self) -> String {
+        match self {
+            TaskCursor::Created(created_at, id) => {
+                format!("{}_{}", created_at.timestamp_micros(), id)
+            }
+            TaskCursor::Deadline(as_of, overdue, deadline, id) => format!(
+                "{}_{}_{}_{}",
+                as_of.timestamp_micros(),
+                *overdue as u8,
+                deadline.map_or("inf".to_string(), |d| d.timestamp_micros().to_string()),
+                id
+            ),
+        }
+    }
+
+    fn decode(s: &str, by_deadline: bool) -> Option<Self> {
+        let micros = |v: &str| v.parse().ok().and_then(DateTime::<Utc>::from_timestamp_micros);
+        let parts: Vec<&str> = s.split('_').collect();
+        match (by_deadline, parts.as_slice()) {
+            (false, [created_at, id]) => Some(TaskCursor::Created(
+                micros(created_at)?,
+                Uuid::parse_str(id).ok()?,
+            )),
+            (true, [as_of, overdue, deadline, id]) => Some(TaskCursor::Deadline(
+                micros(as_of)?,
+                match *overdue {
+                    "0" => false,
+                    "1" => true,
+                    _ => return None,
+                },
+                match *deadline {
+                    "inf" => None,
+                    d => Some(micros(d)?),
+                },
+                Uuid::parse_str(id).ok()?,
+            )),
+            _ => None,
+        }
+    }
+}
+
+// ILIKE 模式中转义 % _ \\
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+fn invalid_initial_status(status: &str) -> Option<HttpResponse> {
+    if lifecycle::INITIAL_STATUSES.contains(&status) {
+        None
+    } else {
+        Some(
+            HttpResponse::Ok()
+                .json(json!({"success": false, "message": "新任务只能是草稿、招募中或进行中"})),
+        )
+    }
 }
 
 pub async fn create_task(pool: web::Data<PgPool>, form: web::Json<TaskInput>) -> HttpResponse {
+    let status = form.status.as_deref().unwrap_or(lifecycle::IN_PROGRESS);
+    if let Some(resp) = invalid_initial_status(status) {
+        return resp;
+    }
+    if form.team_size < 1 || form.roles.iter().any(|r| r.capacity() < 1) {
+        return HttpResponse::Ok()
+            .json(json!({"success": false, "message": "队伍人数和职责名额至少为 1"}));
+    }
     let id = Uuid::new_v4();
     let now = Utc::now();
     let mut tx = match pool.begin().await {
@@ -48,8 +214,18 @@ pub async fn create_task(pool: web::Data<PgPool>, form: web::Json<TaskInput>) ->
         }
     };
     let res = sqlx::query!(
-        "INSERT INTO tasks (id, title, description, creator_id, created_at, team_size) VALUES ($1, $2, $3, $4, $5, $6)",
-        id, form.title, form.description, form.creator_id, now, form.team_size
+        "INSERT INTO tasks (id, title, description, creator_id, created_at, team_size, deadline, status, tags, approval_required, course) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+        id, form.title, form.description, form.creator_id, now, form.team_size, form.deadline, status, &normalize_labels(&form.tags), form.approval_required, normalize_course(form.course.as_deref())
+    )
+    .execute(&mut *tx)
+    .await;
+    if let Err(e) = res {
+        tx.rollback().await.ok();
+        return HttpResponse::Ok().json(json!({"success": false, "message": format!("{}", e)}));
+    }
+    let res = sqlx::query!(
+        "INSERT INTO task_status_history (id, task_id, from_status, to_status, changed_by, created_at) VALUES ($1, $2, NULL, $3, $4, $5)",
+        Uuid::new_v4(), id, status, form.creator_id, now
     )
     .execute(&mut *tx)
     .await;
@@ -58,14 +234,14 @@ pub async fn create_task(pool: web::Data<PgPool>, form: web::Json<TaskInput>) ->
         return HttpResponse::Ok().json(json!({"success": false, "message": format!("{}", e)}));
     }
     // 插入职责
-    for role_name in &form.roles {
+    for role in &form.roles {
         let role_id = Uuid::new_v4();
         let res = sqlx::query!(
-            "INSERT INTO task_roles (id, task_id, role_name, user_id) VALUES ($1, $2, $3, $4)",
+            "INSERT INTO task_roles (id, task_id, role_name, capacity) VALUES ($1, $2, $3, $4)",
             role_id,
             id,
-            role_name,
-            Option::<Uuid>::None
+            role.name(),
+            role.capacity()
         )
         .execute(&mut *tx)
         .await;
@@ -74,26 +250,168 @@ pub async fn create_task(pool: web::Data<PgPool>, form: web::Json<TaskInput>) ->
             return HttpResponse::Ok().json(json!({"success": false, "message": format!("{}", e)}));
         }
     }
-    tx.commit().await.ok();
+    if let Err(e) = tx.commit().await {
+        return HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()}));
+    }
     HttpResponse::Ok().json(json!({"success": true, "id": id}))
 }
 
-pub async fn list_tasks(pool: web::Data<PgPool>) -> HttpResponse {
-    let query = r#"
+pub async fn list_tasks(
+    pool: web::Data<PgPool>,
+    query: web::Query<TaskListQuery>,
+) -> HttpResponse {
+    let by_deadline = match query.sort.as_deref() {
+        None => false,
+        Some("deadline") => true,
+        Some(_) => {
+            return HttpResponse::BadRequest()
+                .json(json!({"success": false, "message": "sort 只支持 deadline"}));
+        }
+    };
+    let limit = query
+        .limit
+        .unwrap_or(TASK_PAGE_DEFAULT_LIMIT)
+        .clamp(1, TASK_PAGE_MAX_LIMIT);
+    let cursor = match query.cursor.as_deref() {
+        None | Some("") => None,
+        Some(c) => match TaskCursor::decode(c, by_deadline) {
+            Some(cursor) => Some(cursor),
+            None => {
+                return HttpResponse::BadRequest()
+                    .json(json!({"success": false, "message": "cursor 无效"}));
+            }
+        },
+    };
+    // 排序、游标和是否过期都用同一个时间点：第一页取当前时间，之后沿用游标中记录的时间，翻页期间顺序不变
+    let now = match cursor {
+        Some(TaskCursor::Deadline(as_of, ..)) => as_of,
+        _ => Utc::now(),
+    };
+
+    let mut qb = QueryBuilder::<Postgres>::new(
+        r#"
         SELECT
             t.id, t.title, t.description, t.creator_id, t.created_at, t.team_size, t.status,
-            u.name as creator_name, u.username as creator_username
+            u.name as creator_name, u.username as creator_username,
+            COALESCE(p.percent, 0) as progress, t.deadline, t.tags, t.approval_required
         FROM tasks t
         INNER JOIN users u ON t.creator_id = u.id
-        WHERE t.status = '进行中'
-        ORDER BY t.created_at DESC
-    "#;
-    let rows = sqlx::query_as::<_, TaskDetails>(query)
+        LEFT JOIN task_computed_progress p ON p.task_id = t.id
+        WHERE t.deleted_at IS NULL AND t.status = ANY("#,
+    );
+    qb.push_bind(&lifecycle::LISTED_STATUSES[..]).push(")");
+
+    if let Some(q) = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+        // 英文等有空格分词的内容走全文索引；中文没有分词，用 pg_trgm 索引支持的子串匹配兜底
+        qb.push(" AND (t.search_vector @@ plainto_tsquery('simple', ")
+            .push_bind(q.to_string())
+            .push(") OR (t.title || ' ' || t.description) ILIKE ")
+            .push_bind(format!("%{}%", escape_like(q)))
+            .push(")");
+    }
+    if let Some(creator_id) = query.creator_id {
+        qb.push(" AND t.creator_id = ").push_bind(creator_id);
+    }
+    if let Some(tag) = query.tag.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+        // 走 tags 上的 GIN 索引
+        qb.push(" AND t.tags @> ARRAY[")
+            .push_bind(tag.to_string())
+            .push("]::TEXT[]");
+    }
+    if let Some(has_open_roles) = query.has_open_roles {
+        // 有未满且未关闭的职责，并且队伍人数未满
+        qb.push(if has_open_roles { " AND (" } else { " AND NOT (" }).push(
+            r#"
+            EXISTS (
+                SELECT 1 FROM task_roles r
+                WHERE r.task_id = t.id AND NOT r.closed
+                    AND (SELECT COUNT(*) FROM task_role_members m WHERE m.role_id = r.id) < r.capacity
+            )
+            AND (
+                SELECT COUNT(DISTINCT m.user_id) FROM task_role_members m
+                JOIN task_roles r ON r.id = m.role_id
+                WHERE r.task_id = t.id
+            ) < t.team_size)"#,
+        );
+    }
+    if let Some(min) = query.min_team_size {
+        qb.push(" AND t.team_size >= ").push_bind(min);
+    }
+    if let Some(max) = query.max_team_size {
+        qb.push(" AND t.team_size <= ").push_bind(max);
+    }
+    if let Some(after) = query.created_after {
+        qb.push(" AND t.created_at >= ").push_bind(after);
+    }
+    if let Some(before) = query.created_before {
+        qb.push(" AND t.created_at < ").push_bind(before);
+    }
+
+    // 已过期的排在最后，再按截止日期由近到远，没有截止日期的排在未过期任务之后
+    let overdue_key = "COALESCE(t.deadline < ";
+    match cursor {
+        Some(TaskCursor::Created(created_at, id)) => {
+            qb.push(" AND (t.created_at, t.id) < (")
+                .push_bind(created_at)
+                .push(", ")
+                .push_bind(id)
+                .push(")");
+        }
+        Some(TaskCursor::Deadline(_, overdue, deadline, id)) => {
+            qb.push(" AND (")
+                .push(overdue_key)
+                .push_bind(now)
+                .push(", FALSE), COALESCE(t.deadline, 'infinity'), t.id) > (")
+                .push_bind(overdue)
+                .push(", ");
+            match deadline {
+                Some(deadline) => qb.push_bind(deadline),
+                None => qb.push("'infinity'::TIMESTAMPTZ"),
+            };
+            qb.push(", ").push_bind(id).push(")");
+        }
+        None => {}
+    }
+
+    if by_deadline {
+        qb.push(" ORDER BY ")
+            .push(overdue_key)
+            .push_bind(now)
+            .push(", FALSE), COALESCE(t.deadline, 'infinity'), t.id");
+    } else {
+        qb.push(" ORDER BY t.created_at DESC, t.id DESC");
+    }
+    // 多取一条判断是否还有下一页
+    qb.push(" LIMIT ").push_bind(limit + 1);
+
+    let rows = qb
+        .build_query_as::<TaskDetails>()
         .fetch_all(pool.get_ref())
         .await;
 
     match rows {
-        Ok(tasks) => HttpResponse::Ok().json(tasks),
+        Ok(mut items) => {
+            let next_cursor = if items.len() as i64 > limit {
+                items.truncate(limit as usize);
+                items.last().map(|t| {
+                    if by_deadline {
+                        TaskCursor::Deadline(
+                            now,
+                            t.deadline.is_some_and(|d| d < now),
+                            t.deadline,
+                            t.id,
+                        )
+                    } else {
+                        TaskCursor::Created(t.created_at, t.id)
+                    }
+                    .encode()
+                })
+            } else {
+                None
+            };
+            HttpResponse::Ok().json(TaskPage { items, next_cursor })
+        }
         Err(e) => {
             eprintln!("Failed to fetch tasks: {:?}", e);
             HttpResponse::InternalServerError().finish()
@@ -101,70 +419,212 @@ pub async fn list_tasks(pool: web::Data<PgPool>) -> HttpResponse {
     }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct ProgressInput {
-    pub task_id: Uuid,
-    pub content: String,
-    pub percent: i32,
+#[derive(Debug, Serialize)]
+pub struct TaskFullDetails {
+    #[serde(flatten)]
+    pub task: TaskDetails,
+    pub roles: Vec<RoleInfo>,
+    pub member_count: usize,
+    // 未关闭且未满的职责数
+    pub open_roles: usize,
+    // 还能加入的人数，同时受职责名额和队伍人数限制
+    pub open_seats: usize,
+    pub sub_task_total: i64,
+    pub sub_task_completed: i64,
+    pub latest_progress: Option<Progress>,
 }
 
-pub async fn add_progress(pool: web::Data<PgPool>, form: web::Json<ProgressInput>) -> HttpResponse {
-    let id = Uuid::new_v4();
-    let now = Utc::now();
-    let res = sqlx::query!(
-        "INSERT INTO progress (id, task_id, content, percent, created_at) VALUES ($1, $2, $3, $4, $5)",
-        id, form.task_id, form.content, form.percent, now
+// 单个任务详情，不限状态（已结束、已归档的任务也能查看）
+pub async fn get_task(
+    pool: web::Data<PgPool>,
+    req: actix_web::HttpRequest,
+    task_id: web::Path<Uuid>,
+) -> HttpResponse {
+    let task_id = task_id.into_inner();
+    let task = sqlx::query_as::<_, TaskDetails>(
+        r#"
+        SELECT
+            t.id, t.title, t.description, t.creator_id, t.created_at, t.team_size, t.status,
+            u.name as creator_name, u.username as creator_username,
+            COALESCE(p.percent, 0) as progress, t.deadline, t.tags, t.approval_required
+        FROM tasks t
+        INNER JOIN users u ON t.creator_id = u.id
+        LEFT JOIN task_computed_progress p ON p.task_id = t.id
+        WHERE t.id = $1 AND t.deleted_at IS NULL
+        "#,
     )
-    .execute(pool.get_ref())
+    .bind(task_id)
+    .fetch_optional(pool.get_ref())
     .await;
-    match res {
-        Ok(_) => HttpResponse::Ok().json(json!({"success": true, "id": id})),
-        Err(e) => HttpResponse::Ok().json(json!({"success": false, "message": format!("{}", e)})),
-    }
+    let task = match task {
+        Ok(Some(task)) => task,
+        Ok(None) => return HttpResponse::NotFound().json("Task not found"),
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+
+    let roles = load_task_roles(pool.get_ref(), task_id, auth::request_user_id(&req));
+    let sub_tasks = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "total!", COUNT(*) FILTER (WHERE status = '已完成') as "completed!"
+        FROM sub_tasks WHERE task_id = $1
+        "#,
+        task_id
+    )
+    .fetch_one(pool.get_ref());
+    let latest_progress = sqlx::query_as::<_, Progress>(
+        "SELECT * FROM progress WHERE task_id = $1 ORDER BY created_at DESC LIMIT 1",
+    )
+    .bind(task_id)
+    .fetch_optional(pool.get_ref());
+
+    let (roles, sub_tasks, latest_progress) =
+        match futures_util::try_join!(roles, sub_tasks, latest_progress) {
+            Ok(result) => result,
+            Err(e) => {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+        };
+
+    let mut members: Vec<Uuid> = roles
+        .iter()
+        .flat_map(|r| r.members.iter().map(|m| m.user_id))
+        .collect();
+    members.sort();
+    members.dedup();
+    let open: Vec<usize> = roles
+        .iter()
+        .filter(|r| !r.closed)
+        .map(|r| (r.capacity as usize).saturating_sub(r.members.len()))
+        .filter(|&seats| seats > 0)
+        .collect();
+    let team_left = (task.team_size as usize).saturating_sub(members.len());
+
+    HttpResponse::Ok().json(TaskFullDetails {
+        task,
+        member_count: members.len(),
+        open_roles: open.len(),
+        open_seats: open.iter().sum::<usize>().min(team_left),
+        roles,
+        sub_task_total: sub_tasks.total,
+        sub_task_completed: sub_tasks.completed,
+        latest_progress,
+    })
 }
 
-pub async fn list_progress(pool: web::Data<PgPool>, task_id: web::Path<Uuid>) -> HttpResponse {
-    let rows = sqlx::query_as::<_, Progress>(
-        "SELECT * FROM progress WHERE task_id = $1 ORDER BY created_at DESC",
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TagFacet {
+    pub tag: String,
+    pub count: i64,
+}
+
+// 各标签下任务广场中的任务数
+pub async fn list_task_tags(pool: web::Data<PgPool>) -> HttpResponse {
+    let rows = sqlx::query_as::<_, TagFacet>(
+        r#"
+        SELECT tag, COUNT(*) as count
+        FROM tasks t, unnest(t.tags) AS tag
+        WHERE t.status = ANY($1) AND t.deleted_at IS NULL
+        GROUP BY tag
+        ORDER BY count DESC, tag ASC
+        "#,
     )
-    .bind(*task_id)
+    .bind(&lifecycle::LISTED_STATUSES[..])
     .fetch_all(pool.get_ref())
     .await;
+
     match rows {
-        Ok(list) => HttpResponse::Ok().json(list),
-        Err(_) => HttpResponse::InternalServerError().finish(),
+        Ok(facets) => HttpResponse::Ok().json(facets),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
     }
 }
 
 #[derive(Debug, Deserialize)]
-pub struct EvaluationInput {
+pub struct ProgressInput {
     pub task_id: Uuid,
-    pub username: String,
     pub content: String,
-    pub rate: i32,
+    pub percent: i32,
 }
 
-pub async fn add_evaluation(
+// 填写进度，内容中 @ 到的任务成员会收到通知。进度计入贡献度，只有任务当前成员可以填写
+pub async fn add_progress(
+    req: actix_web::HttpRequest,
     pool: web::Data<PgPool>,
-    form: web::Json<EvaluationInput>,
+    form: web::Json<ProgressInput>,
 ) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
     let id = Uuid::new_v4();
     let now = Utc::now();
-    let res = sqlx::query!(
-        "INSERT INTO evaluations (id, task_id, username, content, rate, created_at) VALUES ($1, $2, $3, $4, $5, $6)",
-        id, form.task_id, form.username, form.content, form.rate, now
-    )
-    .execute(pool.get_ref())
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::Ok().json(json!({"success": false, "message": format!("{}", e)}));
+        }
+    };
+    let res: Result<Option<Vec<Mention>>, sqlx::Error> = async {
+        if !membership::current_team(&mut tx, form.task_id)
+            .await?
+            .contains(&user_id)
+        {
+            return Ok(None);
+        }
+        let mentions = mentions::resolve(&mut tx, form.task_id, &form.content).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO progress (id, task_id, content, percent, created_at, user_id, mentions)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            id,
+            form.task_id,
+            form.content,
+            form.percent,
+            now,
+            user_id,
+            Json(&mentions) as _
+        )
+        .execute(&mut *tx)
+        .await?;
+        mentions::notify_mentioned(
+            &mut tx,
+            form.task_id,
+            Some(user_id),
+            &mentions,
+            &[],
+            "进度记录",
+        )
+        .await?;
+        Ok(Some(mentions))
+    }
     .await;
     match res {
-        Ok(_) => HttpResponse::Ok().json(json!({"success": true, "id": id})),
-        Err(e) => HttpResponse::Ok().json(json!({"success": false, "message": format!("{}", e)})),
+        Ok(Some(mentions)) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true, "id": id, "mentions": mentions}))
+        }
+        Ok(None) => {
+            tx.rollback().await.ok();
+            HttpResponse::Ok()
+                .json(json!({"success": false, "message": "只有任务成员可以填写进度"}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::Ok().json(json!({"success": false, "message": format!("{}", e)}))
+        }
     }
 }
 
-pub async fn list_evaluations(pool: web::Data<PgPool>, task_id: web::Path<Uuid>) -> HttpResponse {
-    let rows = sqlx::query_as::<_, Evaluation>(
-        "SELECT * FROM evaluations WHERE task_id = $1 ORDER BY created_at DESC",
+pub async fn list_progress(pool: web::Data<PgPool>, task_id: web::Path<Uuid>) -> HttpResponse {
+    let rows = sqlx::query_as::<_, Progress>(
+        "SELECT * FROM progress WHERE task_id = $1 ORDER BY created_at DESC",
     )
     .bind(*task_id)
     .fetch_all(pool.get_ref())
@@ -175,52 +635,68 @@ pub async fn list_evaluations(pool: web::Data<PgPool>, task_id: web::Path<Uuid>)
     }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct MessageInput {
-    pub username: String,
-    pub content: String,
-}
-
-pub async fn add_message(pool: web::Data<PgPool>, form: web::Json<MessageInput>) -> HttpResponse {
-    let id = Uuid::new_v4();
-    let now = Utc::now();
-    let res = sqlx::query!(
-        "INSERT INTO messages (id, username, content, created_at) VALUES ($1, $2, $3, $4)",
-        id,
-        form.username,
-        form.content,
-        now
+// 查询任务的职责及各职责的成员，成员联系方式按查看人 viewer 和隐私设置过滤
+async fn load_task_roles(
+    pool: &PgPool,
+    task_id: Uuid,
+    viewer: Option<Uuid>,
+) -> Result<Vec<RoleInfo>, sqlx::Error> {
+    let roles = sqlx::query!(
+        "SELECT id, role_name, capacity, closed FROM task_roles WHERE task_id = $1",
+        task_id
     )
-    .execute(pool.get_ref())
-    .await;
-    match res {
-        Ok(_) => HttpResponse::Ok().json(json!({"success": true, "id": id})),
-        Err(e) => HttpResponse::Ok().json(json!({"success": false, "message": format!("{}", e)})),
-    }
-}
-
-pub async fn list_messages(pool: web::Data<PgPool>) -> HttpResponse {
-    let rows = sqlx::query_as::<_, Message>("SELECT * FROM messages ORDER BY created_at ASC")
-        .fetch_all(pool.get_ref())
-        .await;
-    match rows {
-        Ok(list) => HttpResponse::Ok().json(list),
-        Err(_) => HttpResponse::InternalServerError().finish(),
-    }
-}
-
-pub async fn get_task_roles(pool: web::Data<PgPool>, task_id: web::Path<Uuid>) -> HttpResponse {
-    let rows = sqlx::query_as::<_, RoleInfo>(
+    .fetch_all(pool)
+    .await?;
+    let members = sqlx::query!(
         r#"
-        SELECT tr.id as role_id, tr.role_name, tr.user_id, u.name, u.username, u.phone, u.student_id, u.email
-        FROM task_roles tr
-        LEFT JOIN users u ON tr.user_id = u.id
+        SELECT m.role_id, m.user_id, u.name, u.username, m.joined_at
+        FROM task_role_members m
+        JOIN task_roles tr ON tr.id = m.role_id
+        JOIN users u ON u.id = m.user_id
         WHERE tr.task_id = $1
+        ORDER BY m.joined_at ASC
         "#,
+        task_id
     )
-    .bind(*task_id)
-    .fetch_all(pool.get_ref())
-    .await;
+    .fetch_all(pool)
+    .await?;
+    let user_ids: Vec<Uuid> = members.iter().map(|m| m.user_id).collect();
+    let contacts =
+        privacy::visible_contacts(&mut *pool.acquire().await?, viewer, &user_ids).await?;
+
+    let mut list: Vec<RoleInfo> = roles
+        .into_iter()
+        .map(|r| RoleInfo {
+            role_id: r.id,
+            role_name: r.role_name,
+            capacity: r.capacity,
+            closed: r.closed,
+            members: Vec::new(),
+        })
+        .collect();
+    for m in members {
+        if let Some(role) = list.iter_mut().find(|r| r.role_id == m.role_id) {
+            role.members.push(RoleMember {
+                user_id: m.user_id,
+                name: m.name,
+                username: m.username,
+                contact: contacts
+                    .get(&m.user_id)
+                    .map(|(_, contact)| contact.clone())
+                    .unwrap_or_default(),
+                joined_at: m.joined_at,
+            });
+        }
+    }
+    Ok(list)
+}
+
+pub async fn get_task_roles(
+    pool: web::Data<PgPool>,
+    req: actix_web::HttpRequest,
+    task_id: web::Path<Uuid>,
+) -> HttpResponse {
+    let rows = load_task_roles(pool.get_ref(), *task_id, auth::request_user_id(&req)).await;
 
     match rows {
         Ok(roles) => HttpResponse::Ok().json(roles),
@@ -228,100 +704,347 @@ pub async fn get_task_roles(pool: web::Data<PgPool>, task_id: web::Path<Uuid>) -
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RemoveMemberQuery {
+    // 被移除的成员
+    pub user_id: Uuid,
+    pub reason: Option<String>,
+}
+
+// 发布者移除成员：在同一事务中释放其在该任务下的全部职责和子任务分配，记录移除并通知该成员
 pub async fn remove_member_from_task_role(
+    req: actix_web::HttpRequest,
     pool: web::Data<PgPool>,
     path: web::Path<Uuid>,
+    query: web::Query<RemoveMemberQuery>,
 ) -> HttpResponse {
+    let Some(operator_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
     let role_id = path.into_inner();
-    // 先查出该 role_id 对应的 task_id 和 user_id
-    let row = sqlx::query!(
-        "SELECT task_id, user_id FROM task_roles WHERE id = $1",
-        role_id
-    )
-    .fetch_one(pool.get_ref())
-    .await;
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
 
-    if let Ok(r) = row {
-        if let Some(user_id) = r.user_id {
-            // 批量释放该成员在该任务下的所有职责
-            let res = sqlx::query!(
-                "UPDATE task_roles SET user_id = NULL WHERE task_id = $1 AND user_id = $2",
-                r.task_id,
-                user_id
-            )
-            .execute(pool.get_ref())
-            .await;
-
-            // 将该成员在该任务下的所有子任务 assignee_id 置为 NULL
-            let _ = sqlx::query!(
-                "UPDATE sub_tasks SET assignee_id = NULL WHERE task_id = $1 AND assignee_id = $2",
-                r.task_id,
-                user_id
+    let reason = query
+        .reason
+        .as_deref()
+        .map(str::trim)
+        .filter(|r| !r.is_empty());
+    let res: Result<Result<(), HttpResponse>, sqlx::Error> = async {
+        let task = match sqlx::query!(
+            r#"
+            SELECT t.id, t.title, t.creator_id
+            FROM task_roles tr JOIN tasks t ON t.id = tr.task_id
+            WHERE tr.id = $1
+            FOR UPDATE OF t
+            "#,
+            role_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        {
+            Some(task) => task,
+            None => return Ok(Err(HttpResponse::NotFound().json("Role not found"))),
+        };
+        if task.creator_id != Some(operator_id) {
+            return Ok(Err(HttpResponse::Forbidden().json(
+                json!({"success": false, "message": "只有任务发布者可以移除成员"}),
+            )));
+        }
+        let released = membership::release_member(
+            &mut tx,
+            task.id,
+            query.user_id,
+            membership::EVENT_REMOVED,
+            operator_id,
+            reason,
+        )
+        .await?;
+        if released == 0 {
+            return Ok(Err(
+                HttpResponse::Ok().json(json!({"success": false, "message": "该用户不是任务成员"}))
+            ));
+        }
+        if query.user_id != operator_id {
+            let mut content = format!("你已被移出任务「{}」", task.title);
+            if let Some(reason) = reason {
+                content.push_str(&format!("：{}", reason));
+            }
+            notifications::notify(
+                &mut tx,
+                query.user_id,
+                notifications::MEMBER_REMOVED,
+                &content,
+                Some(task.id),
             )
-            .execute(pool.get_ref())
-            .await;
-
-            return match res {
-                Ok(_) => HttpResponse::Ok().json(json!({"success": true})),
-                Err(e) => HttpResponse::InternalServerError()
-                    .json(json!({"success": false, "message": e.to_string()})),
-            };
+            .await?;
         }
+        Ok(Ok(()))
     }
-    HttpResponse::Ok().json(json!({"success": true}))
-}
-
-#[derive(Debug, Deserialize)]
-pub struct ClaimRoleInput {
-    pub role_id: Uuid,
-    pub user_id: Uuid,
-}
-
-pub async fn claim_role(pool: web::Data<PgPool>, form: web::Json<ClaimRoleInput>) -> HttpResponse {
-    let res = sqlx::query!(
-        "UPDATE task_roles SET user_id = $1 WHERE id = $2 AND user_id IS NULL",
-        form.user_id,
-        form.role_id
-    )
-    .execute(pool.get_ref())
     .await;
     match res {
-        Ok(r) if r.rows_affected() == 1 => HttpResponse::Ok().json(json!({"success": true})),
-        _ => HttpResponse::Ok().json(json!({"success": false, "message": "认领失败或已被认领"})),
+        Ok(Ok(())) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true}))
+        }
+        Ok(Err(resp)) => {
+            tx.rollback().await.ok();
+            resp
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}))
+        }
     }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct UpdateProfileInput {
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct MembershipEvent {
+    pub id: Uuid,
     pub user_id: Uuid,
-    pub name: String,
-    pub phone: Option<String>,
-    pub student_id: Option<String>,
-    pub email: Option<String>,
+    pub name: Option<String>,
+    pub username: String,
+    pub role_id: Option<Uuid>,
+    pub role_name: String,
+    pub event: String,
+    pub actor_id: Option<Uuid>,
+    pub actor_name: Option<String>,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
 }
 
-pub async fn get_user_info(pool: web::Data<PgPool>, user_id: web::Path<Uuid>) -> HttpResponse {
-    let row = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-        .bind(*user_id)
-        .fetch_one(pool.get_ref())
-        .await;
-    match row {
-        Ok(user) => HttpResponse::Ok().json(user),
-        Err(_) => HttpResponse::NotFound().finish(),
-    }
+#[derive(Debug, Deserialize)]
+pub struct MembershipHistoryQuery {
+    // 只看某个成员
+    pub user_id: Option<Uuid>,
 }
 
-pub async fn update_user_profile(
+// 任务成员变动记录，按时间先后排列
+pub async fn get_membership_history(
+    pool: web::Data<PgPool>,
+    task_id: web::Path<Uuid>,
+    query: web::Query<MembershipHistoryQuery>,
+) -> HttpResponse {
+    let rows = sqlx::query_as::<_, MembershipEvent>(
+        r#"
+        SELECT e.id, e.user_id, u.name, u.username, e.role_id, e.role_name, e.event,
+            e.actor_id, COALESCE(NULLIF(a.name, ''), a.username) as actor_name,
+            e.reason, e.created_at
+        FROM task_membership_events e
+        JOIN users u ON u.id = e.user_id
+        LEFT JOIN users a ON a.id = e.actor_id
+        WHERE e.task_id = $1 AND ($2::UUID IS NULL OR e.user_id = $2)
+        ORDER BY e.created_at ASC, e.id
+        "#,
+    )
+    .bind(*task_id)
+    .bind(query.user_id)
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match rows {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClaimRoleInput {
+    pub role_id: Uuid,
+    pub user_id: Uuid,
+    // 需要审核的任务中附在申请上的留言
+    pub message: Option<String>,
+}
+
+pub async fn claim_role(pool: web::Data<PgPool>, form: web::Json<ClaimRoleInput>) -> HttpResponse {
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    // 关闭的职责、草稿以及已结束的任务不可认领；职责或队伍满员时拒绝。
+    // 需要审核的任务先创建申请，由发布者通过后再加入；发布者本人无需审核。
+    let res = match membership::check_join(&mut tx, form.role_id, form.user_id).await {
+        Ok(slot) if slot.approval_required && slot.creator_id != Some(form.user_id) => {
+            apply_and_notify(
+                &mut tx,
+                &slot,
+                form.role_id,
+                form.user_id,
+                form.message.as_deref(),
+            )
+            .await
+            .map(Some)
+        }
+        Ok(_) => membership::add_member(&mut tx, form.role_id, form.user_id, form.user_id)
+            .await
+            .map(|_| None)
+            .map_err(JoinError::from),
+        Err(e) => Err(e),
+    };
+    match res {
+        Ok(None) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true}))
+        }
+        Ok(Some(request_id)) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok()
+                .json(json!({"success": true, "pending": true, "request_id": request_id}))
+        }
+        Err(JoinError::Db(e)) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::Ok().json(json!({"success": false, "message": e.message()}))
+        }
+    }
+}
+
+// 创建加入申请并通知发布者
+async fn apply_and_notify(
+    conn: &mut PgConnection,
+    slot: &RoleSlot,
+    role_id: Uuid,
+    user_id: Uuid,
+    message: Option<&str>,
+) -> Result<Uuid, JoinError> {
+    let request_id = membership::apply_for_role(conn, slot, role_id, user_id, message).await?;
+    if let Some(creator_id) = slot.creator_id {
+        let applicant = user_display_name(conn, user_id).await?;
+        notifications::notify(
+            conn,
+            creator_id,
+            notifications::JOIN_REQUEST,
+            &format!("{} 申请加入职责「{}」", applicant, slot.role_name),
+            Some(slot.task_id),
+        )
+        .await?;
+    }
+    Ok(request_id)
+}
+
+// 通知中使用的用户名称，未设置姓名时使用用户名
+async fn user_display_name(conn: &mut PgConnection, user_id: Uuid) -> Result<String, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT COALESCE(NULLIF(name, ''), username) as "name!" FROM users WHERE id = $1"#,
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateProfileInput {
+    pub name: String,
+    pub phone: Option<String>,
+    pub student_id: Option<String>,
+    pub email: Option<String>,
+    // public / teammates / private，不传则保持不变
+    pub phone_visibility: Option<String>,
+    pub student_id_visibility: Option<String>,
+    pub email_visibility: Option<String>,
+}
+
+// 本人（按 Authorization 识别）看到完整资料和隐私设置，其他人只能看到隐私设置允许的联系方式
+pub async fn get_user_info(
+    pool: web::Data<PgPool>,
+    req: actix_web::HttpRequest,
+    user_id: web::Path<Uuid>,
+) -> HttpResponse {
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    match privacy::user_view(&mut conn, auth::request_user_id(&req), *user_id).await {
+        Ok(Some(privacy::UserView::Myself(user))) => HttpResponse::Ok().json(user),
+        Ok(Some(privacy::UserView::Other(user))) => HttpResponse::Ok().json(user),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+// 公开资料，供发布者审核申请时查看申请人的履历
+pub async fn get_public_profile(pool: web::Data<PgPool>, user_id: web::Path<Uuid>) -> HttpResponse {
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    match reputation::public_profile(&mut conn, user_id.into_inner()).await {
+        Ok(Some(profile)) => HttpResponse::Ok().json(profile),
+        Ok(None) => HttpResponse::NotFound().json("User not found"),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+// 修改本人资料和隐私设置，用户按 Authorization 识别
+pub async fn update_user_profile(
+    req: actix_web::HttpRequest,
     pool: web::Data<PgPool>,
     form: web::Json<UpdateProfileInput>,
 ) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let visibilities = [
+        &form.phone_visibility,
+        &form.student_id_visibility,
+        &form.email_visibility,
+    ];
+    if visibilities
+        .iter()
+        .flat_map(|v| v.as_deref())
+        .any(|v| !privacy::VISIBILITIES.contains(&v))
+    {
+        return HttpResponse::BadRequest().json(
+            json!({"success": false, "message": "可见范围只能是 public、teammates 或 private"}),
+        );
+    }
     let res = sqlx::query!(
-        "UPDATE users SET name = $1, phone = $2, student_id = $3, email = $4 WHERE id = $5",
+        r#"
+        UPDATE users SET name = $1, phone = $2, student_id = $3, email = $4,
+            phone_visibility = COALESCE($5, phone_visibility),
+            student_id_visibility = COALESCE($6, student_id_visibility),
+            email_visibility = COALESCE($7, email_visibility)
+        WHERE id = $8
+        "#,
         form.name,
         form.phone,
         form.student_id,
         form.email,
-        form.user_id
+        form.phone_visibility,
+        form.student_id_visibility,
+        form.email_visibility,
+        user_id
     )
     .execute(pool.get_ref())
     .await;
@@ -344,9 +1067,10 @@ pub async fn get_my_roles(pool: web::Data<PgPool>, user_id: web::Path<Uuid>) ->
 
     let rows = sqlx::query_as::<_, QueryResult>(
         r#"SELECT t.id as task_id, t.title, t.description, tr.role_name, t.status
-            FROM task_roles tr
+            FROM task_role_members m
+            JOIN task_roles tr ON tr.id = m.role_id
             JOIN tasks t ON tr.task_id = t.id
-            WHERE tr.user_id = $1
+            WHERE m.user_id = $1 AND t.deleted_at IS NULL
             ORDER BY t.status ASC, t.created_at DESC"#,
     )
     .bind(*user_id)
@@ -377,25 +1101,33 @@ pub struct TaskWithMembers {
     pub id: Uuid,
     pub title: String,
     pub status: String,
+    pub progress: i32,
+    pub tags: Vec<String>,
     pub members: Vec<MemberRole>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct MemberRole {
+    pub user_id: Uuid,
+    pub role_id: Uuid,
     pub name: String,
     pub username: String,
     pub role_name: String,
-    pub phone: Option<String>,
-    pub student_id: Option<String>,
-    pub email: Option<String>,
+    // 按查看人和隐私设置过滤
+    #[serde(flatten)]
+    pub contact: privacy::Contact,
 }
 
 pub async fn get_my_published_tasks(
     pool: web::Data<PgPool>,
+    req: actix_web::HttpRequest,
     user_id: web::Path<Uuid>,
 ) -> HttpResponse {
+    let viewer = auth::request_user_id(&req);
     // 获取自己发布的所有任务
-    let tasks = sqlx::query_as::<_, Task>("SELECT * FROM tasks WHERE creator_id = $1")
+    let tasks = sqlx::query_as::<_, Task>(
+        "SELECT * FROM tasks WHERE creator_id = $1 AND deleted_at IS NULL",
+    )
         .bind(*user_id)
         .fetch_all(pool.get_ref())
         .await;
@@ -405,29 +1137,54 @@ pub async fn get_my_published_tasks(
             for task in task_list {
                 // 获取成员
                 let members = sqlx::query!(
-                    r#"SELECT u.name, u.username, tr.role_name, u.phone, u.student_id, u.email
-                        FROM task_roles tr
-                        LEFT JOIN users u ON tr.user_id = u.id
-                        WHERE tr.task_id = $1"#,
+                    r#"SELECT m.user_id, m.role_id, u.name, u.username, tr.role_name
+                        FROM task_role_members m
+                        JOIN task_roles tr ON tr.id = m.role_id
+                        JOIN users u ON u.id = m.user_id
+                        WHERE tr.task_id = $1
+                        ORDER BY m.joined_at ASC"#,
                     task.id
                 )
                 .fetch_all(pool.get_ref())
                 .await
-                .unwrap_or_default()
-                .into_iter()
-                .map(|r| MemberRole {
-                    name: r.name.unwrap_or_default(),
-                    username: r.username,
-                    role_name: r.role_name,
-                    phone: r.phone,
-                    student_id: r.student_id,
-                    email: r.email,
-                })
-                .collect();
+                .unwrap_or_default();
+                let user_ids: Vec<Uuid> = members.iter().map(|r| r.user_id).collect();
+                let contacts = match pool.acquire().await {
+                    Ok(mut conn) => privacy::visible_contacts(&mut conn, viewer, &user_ids)
+                        .await
+                        .unwrap_or_default(),
+                    Err(_) => HashMap::new(),
+                };
+                let members = members
+                    .into_iter()
+                    .map(|r| MemberRole {
+                        contact: contacts
+                            .get(&r.user_id)
+                            .map(|(_, contact)| contact.clone())
+                            .unwrap_or_default(),
+                        user_id: r.user_id,
+                        role_id: r.role_id,
+                        name: r.name.unwrap_or_default(),
+                        username: r.username,
+                        role_name: r.role_name,
+                    })
+                    .collect();
+                let progress = sqlx::query_scalar!(
+                    "SELECT percent FROM task_computed_progress WHERE task_id = $1",
+                    task.id
+                )
+                .fetch_optional(pool.get_ref())
+                .await
+                .ok()
+                .flatten()
+                .flatten()
+                .unwrap_or(0);
                 result.push(TaskWithMembers {
                     id: task.id,
                     title: task.title,
                     status: task.status,
+                    progress,
+                    tags: task.tags,
                     members,
                 });
             }
@@ -444,19 +1201,219 @@ pub struct FinishTaskInput {
     pub task_id: Uuid,
 }
 
+// 结束任务，操作人按 Authorization 识别，需为任务发布者
 pub async fn finish_task(
+    req: actix_web::HttpRequest,
     pool: web::Data<PgPool>,
     form: web::Json<FinishTaskInput>,
 ) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(_) => return HttpResponse::Ok().json(json!({"success": false, "message": "结束失败"})),
+    };
+    match lifecycle::transition_task(&mut tx, form.task_id, lifecycle::FINISHED, user_id, None).await {
+        Ok(_) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::Ok().json(json!({"success": false, "message": format!("结束失败：{}", e.message())}))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TaskStatusInput {
+    pub status: String,
+    pub note: Option<String>,
+}
+
+pub async fn change_task_status(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    form: web::Json<TaskStatusInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    if !lifecycle::ALL_STATUSES.contains(&form.status.as_str()) {
+        return HttpResponse::BadRequest()
+            .json(json!({"success": false, "message": "无效的任务状态"}));
+    }
+    let task_id = path.into_inner();
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": format!("{}", e)}));
+        }
+    };
+    let res = lifecycle::transition_task(
+        &mut tx,
+        task_id,
+        &form.status,
+        user_id,
+        form.note.as_deref(),
+    )
+    .await;
+    match res {
+        Ok(from) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true, "from": from, "status": form.status}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            let message = e.message();
+            match e {
+                TransitionError::NotFound => HttpResponse::NotFound()
+                    .json(json!({"success": false, "message": message})),
+                TransitionError::Forbidden => HttpResponse::Forbidden()
+                    .json(json!({"success": false, "message": message})),
+                TransitionError::Invalid { .. } => HttpResponse::Conflict()
+                    .json(json!({"success": false, "message": message})),
+                TransitionError::Db(_) => HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": message})),
+            }
+        }
+    }
+}
+
+pub async fn list_task_status_history(
+    pool: web::Data<PgPool>,
+    task_id: web::Path<Uuid>,
+) -> HttpResponse {
+    let rows = sqlx::query_as::<_, TaskStatusHistory>(
+        "SELECT * FROM task_status_history WHERE task_id = $1 ORDER BY created_at ASC",
+    )
+    .bind(*task_id)
+    .fetch_all(pool.get_ref())
+    .await;
+    match rows {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TaskOwnerInput {
+    pub user_id: Uuid,
+}
+
+// 软删除任务，仅发布者可操作
+pub async fn delete_task(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let task_id = path.into_inner();
+    let res = sqlx::query!(
+        "UPDATE tasks SET deleted_at = NOW() WHERE id = $1 AND creator_id = $2 AND deleted_at IS NULL",
+        task_id,
+        user_id
+    )
+    .execute(pool.get_ref())
+    .await;
+    match res {
+        Ok(r) if r.rows_affected() == 1 => HttpResponse::Ok().json(json!({
+            "success": true,
+            "restore_before": Utc::now() + chrono::Duration::days(TASK_RETENTION_DAYS),
+        })),
+        Ok(_) => HttpResponse::Ok()
+            .json(json!({"success": false, "message": "任务不存在或无权删除"})),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": format!("{}", e)})),
+    }
+}
+
+// 在保留期内恢复软删除的任务，仅发布者可操作
+pub async fn restore_task(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let task_id = path.into_inner();
+    let cutoff = Utc::now() - chrono::Duration::days(TASK_RETENTION_DAYS);
     let res = sqlx::query!(
-        "UPDATE tasks SET status = '已结束' WHERE id = $1",
-        form.task_id
+        r#"
+        UPDATE tasks SET deleted_at = NULL
+        WHERE id = $1 AND creator_id = $2 AND deleted_at IS NOT NULL AND deleted_at >= $3
+        "#,
+        task_id,
+        user_id,
+        cutoff
     )
     .execute(pool.get_ref())
     .await;
     match res {
         Ok(r) if r.rows_affected() == 1 => HttpResponse::Ok().json(json!({"success": true})),
-        _ => HttpResponse::Ok().json(json!({"success": false, "message": "结束失败"})),
+        Ok(_) => HttpResponse::Ok()
+            .json(json!({"success": false, "message": "任务不存在、已超过保留期或无权恢复"})),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": format!("{}", e)})),
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeletedTask {
+    pub id: Uuid,
+    pub title: String,
+    pub status: String,
+    pub deleted_at: DateTime<Utc>,
+    // 超过该时间后任务会被彻底删除
+    pub purge_at: DateTime<Utc>,
+}
+
+// 获取自己删除且仍可恢复的任务
+pub async fn get_my_deleted_tasks(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, title, status, deleted_at as "deleted_at!"
+        FROM tasks
+        WHERE creator_id = $1 AND deleted_at IS NOT NULL
+        ORDER BY deleted_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool.get_ref())
+    .await;
+    match rows {
+        Ok(rows) => {
+            let list: Vec<DeletedTask> = rows
+                .into_iter()
+                .map(|r| DeletedTask {
+                    id: r.id,
+                    title: r.title,
+                    status: r.status.unwrap_or_default(),
+                    deleted_at: r.deleted_at,
+                    purge_at: r.deleted_at + chrono::Duration::days(TASK_RETENTION_DAYS),
+                })
+                .collect();
+            HttpResponse::Ok().json(list)
+        }
+        Err(_) => HttpResponse::InternalServerError().finish(),
     }
 }
 
@@ -465,30 +1422,72 @@ pub struct TaskUpdateInput {
     pub task_id: Uuid,
     pub title: String,
     pub description: String,
+    pub weight_by_estimate: Option<bool>,
+    // 不传则保持不变，传 null 清除截止日期
+    #[serde(default, deserialize_with = "present")]
+    pub deadline: Option<Option<DateTime<Utc>>>,
+    pub tags: Option<Vec<String>>,
+    pub approval_required: Option<bool>,
+    // 传空字符串表示清除课程
+    pub course: Option<String>,
 }
 
 pub async fn update_task(
     pool: web::Data<PgPool>,
     form: web::Json<TaskUpdateInput>,
 ) -> HttpResponse {
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": format!("{}", e)}));
+        }
+    };
+    let tags = form.tags.as_deref().map(normalize_labels);
     let res = sqlx::query!(
-        "UPDATE tasks SET title = $1, description = $2 WHERE id = $3",
+        "UPDATE tasks SET title = $1, description = $2, weight_by_estimate = COALESCE($3, weight_by_estimate), deadline = CASE WHEN $10 THEN $4 ELSE deadline END, tags = COALESCE($5, tags), approval_required = COALESCE($6, approval_required), course = CASE WHEN $7 THEN $8 ELSE course END WHERE id = $9",
         form.title,
         form.description,
-        form.task_id
+        form.weight_by_estimate,
+        form.deadline.flatten(),
+        tags.as_deref(),
+        form.approval_required,
+        form.course.is_some(),
+        normalize_course(form.course.as_deref()),
+        form.task_id,
+        form.deadline.is_some()
     )
-    .execute(pool.get_ref())
+    .execute(&mut *tx)
     .await;
 
     match res {
-        Ok(result) if result.rows_affected() == 1 => {
-            HttpResponse::Ok().json(json!({"success": true}))
+        Ok(result) if result.rows_affected() == 1 => {}
+        Ok(_) => {
+            tx.rollback().await.ok();
+            return HttpResponse::Ok()
+                .json(json!({"success": false, "message": "未找到任务或信息无变化"}));
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": format!("{}", e)}));
         }
+    }
+
+    // 加权方式可能改变，重新计算进度
+    match sync_task_progress(&mut tx, form.task_id).await {
         Ok(_) => {
-            HttpResponse::Ok().json(json!({"success": false, "message": "未找到任务或信息无变化"}))
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": format!("{}", e)}))
         }
-        Err(e) => HttpResponse::InternalServerError()
-            .json(json!({"success": false, "message": format!("{}", e)})),
     }
 }
 
@@ -576,19 +1575,60 @@ pub async fn upload_avatar(
 
 // --- Sub-task Handlers ---
 
+// 子任务优先级：0 低，1 中，2 高，3 紧急
+const SUB_TASK_PRIORITY_RANGE: std::ops::RangeInclusive<i16> = 0..=3;
+const SUB_TASK_DEFAULT_PRIORITY: i16 = 1;
+const SUB_TASK_STATUSES: [&str; 3] = ["未开始", "进行中", "已完成"];
+
 #[derive(Debug, Deserialize)]
 pub struct SubTaskInput {
     pub title: String,
     pub description: Option<String>,
     pub due_date: Option<DateTime<Utc>>,
+    pub priority: Option<i16>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    pub estimate_hours: Option<f32>,
+    pub milestone_id: Option<Uuid>,
+    // 操作人，描述中 @ 到自己时不通知
+    pub user_id: Option<Uuid>,
 }
 
+// 修改子任务只改传了的字段：不传的字段保持不变，
+// 可为空的字段（描述、截止日期、负责人、预估工时、里程碑）传 null 表示清空
 #[derive(Debug, Deserialize)]
 pub struct SubTaskUpdateInput {
-    pub title: String,
-    pub description: Option<String>,
-    pub due_date: Option<DateTime<Utc>>,
+    pub title: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    pub description: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub due_date: Option<Option<DateTime<Utc>>>,
+    #[serde(default, deserialize_with = "present")]
+    pub assignee_id: Option<Option<Uuid>>,
+    pub priority: Option<i16>,
+    pub labels: Option<Vec<String>>,
+    pub status: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    pub estimate_hours: Option<Option<f32>>,
+    #[serde(default, deserialize_with = "present")]
+    pub milestone_id: Option<Option<Uuid>>,
+    // 操作人，描述中 @ 到自己时不通知
+    pub user_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubTaskQuery {
+    pub status: Option<String>,
     pub assignee_id: Option<Uuid>,
+    pub label: Option<String>,
+    pub priority: Option<i16>,
+    pub due_before: Option<DateTime<Utc>>,
+    pub overdue: Option<bool>,
+    pub milestone_id: Option<Uuid>,
+    // due_date | priority，默认按创建时间
+    pub sort: Option<String>,
+    // asc | desc
+    pub order: Option<String>,
 }
 
 #[derive(Debug, Serialize, sqlx::FromRow)]
@@ -600,42 +1640,169 @@ pub struct SubTaskDetails {
     due_date: Option<DateTime<Utc>>,
     assignee_id: Option<Uuid>,
     assignee_name: Option<String>,
+    priority: i16,
+    labels: Vec<String>,
+    template_id: Option<Uuid>,
+    estimate_hours: Option<f32>,
+    completed_at: Option<DateTime<Utc>>,
+    checklist_total: i64,
+    checklist_done: i64,
+    milestone_id: Option<Uuid>,
+    mentions: Json<Vec<Mention>>,
 }
 
-pub async fn create_sub_task(
-    pool: web::Data<PgPool>,
-    path: web::Path<Uuid>,
-    form: web::Json<SubTaskInput>,
+// 去掉空白标签与重复标签，保留输入顺序
+fn normalize_labels(labels: &[String]) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for label in labels {
+        let label = label.trim();
+        if !label.is_empty() && !result.iter().any(|l| l == label) {
+            result.push(label.to_string());
+        }
+    }
+    result
+}
+
+// 区分可清空字段的"未传"和"传 null"：未传为 None，传 null 为 Some(None)。
+// 配合 #[serde(default, deserialize_with = "present")] 使用
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+// 课程名去掉首尾空白，空字符串视为未设置
+fn normalize_course(course: Option<&str>) -> Option<String> {
+    course
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(str::to_string)
+}
+
+fn invalid_priority(priority: Option<i16>) -> Option<HttpResponse> {
+    match priority {
+        Some(p) if !SUB_TASK_PRIORITY_RANGE.contains(&p) => Some(
+            HttpResponse::BadRequest()
+                .json(json!({ "success": false, "message": "优先级必须在 0 到 3 之间" })),
+        ),
+        _ => None,
+    }
+}
+
+fn invalid_estimate(estimate_hours: Option<f32>) -> Option<HttpResponse> {
+    match estimate_hours {
+        Some(h) if !h.is_finite() || h < 0.0 => Some(
+            HttpResponse::BadRequest()
+                .json(json!({ "success": false, "message": "预估工时不能为负数" })),
+        ),
+        _ => None,
+    }
+}
+
+pub async fn create_sub_task(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    form: web::Json<SubTaskInput>,
 ) -> HttpResponse {
+    if let Some(resp) = invalid_priority(form.priority).or(invalid_estimate(form.estimate_hours)) {
+        return resp;
+    }
     let task_id = path.into_inner();
     let sub_task_id = Uuid::new_v4();
     let now = Utc::now();
+    let priority = form.priority.unwrap_or(SUB_TASK_DEFAULT_PRIORITY);
+    let labels = normalize_labels(&form.labels);
 
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    };
+    let mentions = match &form.description {
+        Some(description) => match mentions::resolve(&mut tx, task_id, description).await {
+            Ok(mentions) => mentions,
+            Err(e) => {
+                tx.rollback().await.ok();
+                return HttpResponse::InternalServerError()
+                    .json(json!({ "success": false, "message": e.to_string() }));
+            }
+        },
+        None => Vec::new(),
+    };
+    // 里程碑必须属于同一任务
     let res = sqlx::query!(
         r#"
-        INSERT INTO sub_tasks (id, task_id, title, description, created_at, due_date, status)
-        VALUES ($1, $2, $3, $4, $5, $6, '未开始')
+        INSERT INTO sub_tasks (id, task_id, title, description, created_at, due_date, status, priority, labels, estimate_hours, milestone_id, mentions)
+        SELECT $1, $2, $3, $4, $5, $6, '未开始', $7, $8, $9, $10, $11
+        WHERE $10::UUID IS NULL OR EXISTS (SELECT 1 FROM milestones WHERE id = $10 AND task_id = $2)
         "#,
         sub_task_id,
         task_id,
         form.title,
         form.description,
         now,
-        form.due_date
+        form.due_date,
+        priority,
+        &labels,
+        form.estimate_hours,
+        form.milestone_id,
+        Json(&mentions) as _
     )
-    .execute(pool.get_ref())
+    .execute(&mut *tx)
     .await;
+    match res {
+        Ok(r) if r.rows_affected() == 1 => {}
+        Ok(_) => {
+            tx.rollback().await.ok();
+            return HttpResponse::BadRequest()
+                .json(json!({ "success": false, "message": "里程碑不存在" }));
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    }
 
+    let place = format!("子任务「{}」", form.title);
+    let res = async {
+        mentions::notify_mentioned(&mut tx, task_id, form.user_id, &mentions, &[], &place).await?;
+        sync_task_progress(&mut tx, task_id).await
+    }
+    .await;
     match res {
-        Ok(_) => HttpResponse::Created().json(json!({ "success": true, "id": sub_task_id })),
-        Err(e) => HttpResponse::InternalServerError()
-            .json(json!({ "success": false, "message": e.to_string() })),
+        Ok(_) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({ "success": false, "message": e.to_string() }));
+            }
+            HttpResponse::Created()
+                .json(json!({ "success": true, "id": sub_task_id, "mentions": mentions }))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }))
+        }
     }
 }
 
-pub async fn list_sub_tasks(pool: web::Data<PgPool>, path: web::Path<Uuid>) -> HttpResponse {
+pub async fn list_sub_tasks(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    query: web::Query<SubTaskQuery>,
+) -> HttpResponse {
     let task_id = path.into_inner();
-    let query = r#"
+    if let Some(resp) = invalid_priority(query.priority) {
+        return resp;
+    }
+
+    let mut qb = QueryBuilder::<Postgres>::new(
+        r#"
         SELECT
             st.id,
             st.title,
@@ -643,15 +1810,77 @@ pub async fn list_sub_tasks(pool: web::Data<PgPool>, path: web::Path<Uuid>) -> H
             st.status,
             st.due_date,
             st.assignee_id,
-            COALESCE(u.name, u.username, NULL) as assignee_name
+            COALESCE(u.name, u.username, NULL) as assignee_name,
+            st.priority,
+            st.labels,
+            st.template_id,
+            st.estimate_hours,
+            st.completed_at,
+            (SELECT COUNT(*) FROM sub_task_checklist_items c WHERE c.sub_task_id = st.id) as checklist_total,
+            (SELECT COUNT(*) FROM sub_task_checklist_items c WHERE c.sub_task_id = st.id AND c.done) as checklist_done,
+            st.milestone_id,
+            st.mentions
         FROM sub_tasks st
         LEFT JOIN users u ON st.assignee_id = u.id
-        WHERE st.task_id = $1
-        ORDER BY st.created_at ASC
-    "#;
+        WHERE st.task_id = "#,
+    );
+    qb.push_bind(task_id);
+    if let Some(status) = &query.status {
+        qb.push(" AND st.status = ").push_bind(status.clone());
+    }
+    if let Some(assignee_id) = query.assignee_id {
+        qb.push(" AND st.assignee_id = ").push_bind(assignee_id);
+    }
+    if let Some(label) = &query.label {
+        // 走 labels 上的 GIN 索引
+        qb.push(" AND st.labels @> ARRAY[")
+            .push_bind(label.trim().to_string())
+            .push("]::TEXT[]");
+    }
+    if let Some(priority) = query.priority {
+        qb.push(" AND st.priority = ").push_bind(priority);
+    }
+    if let Some(milestone_id) = query.milestone_id {
+        qb.push(" AND st.milestone_id = ").push_bind(milestone_id);
+    }
+    if let Some(due_before) = query.due_before {
+        qb.push(" AND st.due_date < ").push_bind(due_before);
+    }
+    if query.overdue == Some(true) {
+        qb.push(" AND st.due_date < NOW() AND st.status <> '已完成'");
+    }
+
+    let direction = match query.order.as_deref() {
+        Some("desc") => "DESC",
+        Some("asc") => "ASC",
+        // 优先级默认从高到低，其余默认升序
+        _ if query.sort.as_deref() == Some("priority") => "DESC",
+        _ => "ASC",
+    };
+    match query.sort.as_deref() {
+        Some("due_date") => {
+            qb.push(format!(
+                " ORDER BY st.due_date {} NULLS LAST, st.created_at ASC",
+                direction
+            ));
+        }
+        Some("priority") => {
+            qb.push(format!(
+                " ORDER BY st.priority {}, st.due_date ASC NULLS LAST, st.created_at ASC",
+                direction
+            ));
+        }
+        None => {
+            qb.push(format!(" ORDER BY st.created_at {}", direction));
+        }
+        Some(_) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "success": false, "message": "sort 只支持 due_date 或 priority" }));
+        }
+    }
 
-    let rows = sqlx::query_as::<_, SubTaskDetails>(query)
-        .bind(task_id)
+    let rows = qb
+        .build_query_as::<SubTaskDetails>()
         .fetch_all(pool.get_ref())
         .await;
 
@@ -667,26 +1896,152 @@ pub async fn update_sub_task(
     path: web::Path<(Uuid, Uuid)>,
     form: web::Json<SubTaskUpdateInput>,
 ) -> HttpResponse {
-    let (_task_id, sub_task_id) = path.into_inner();
-    let res = sqlx::query!(
+    if let Some(resp) =
+        invalid_priority(form.priority).or(invalid_estimate(form.estimate_hours.flatten()))
+    {
+        return resp;
+    }
+    if form
+        .status
+        .as_deref()
+        .is_some_and(|status| !SUB_TASK_STATUSES.contains(&status))
+    {
+        return HttpResponse::BadRequest()
+            .json(json!({ "success": false, "message": "无效的子任务状态" }));
+    }
+    let (task_id, sub_task_id) = path.into_inner();
+    let labels = form.labels.as_deref().map(normalize_labels);
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    };
+    // 描述修改后新 @ 到的成员才会收到通知
+    let mentions = async {
+        let previous = sqlx::query_scalar!(
+            r#"SELECT mentions as "mentions: Json<Vec<Mention>>" FROM sub_tasks WHERE id = $1 AND task_id = $2"#,
+            sub_task_id,
+            task_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let milestone_ok = match form.milestone_id {
+            Some(Some(milestone_id)) => sqlx::query_scalar!(
+                r#"SELECT EXISTS (SELECT 1 FROM milestones WHERE id = $1 AND task_id = $2) as "exists!""#,
+                milestone_id,
+                task_id
+            )
+            .fetch_one(&mut *tx)
+            .await?,
+            _ => true,
+        };
+        // 没有传描述时沿用原来的提及
+        let current = match &form.description {
+            Some(Some(description)) => mentions::resolve(&mut tx, task_id, description).await?,
+            Some(None) => Vec::new(),
+            None => previous
+                .as_ref()
+                .map(|p| p.0.clone())
+                .unwrap_or_default(),
+        };
+        Ok::<_, sqlx::Error>((previous, milestone_ok, current))
+    }
+    .await;
+    let (previous, mentions) = match mentions {
+        Ok((Some(previous), true, current)) => (previous.0, current),
+        Ok((None, _, _)) => {
+            tx.rollback().await.ok();
+            return HttpResponse::NotFound().json("Sub-task not found");
+        }
+        Ok((Some(_), false, _)) => {
+            tx.rollback().await.ok();
+            return HttpResponse::BadRequest()
+                .json(json!({ "success": false, "message": "里程碑不属于该任务" }));
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    };
+    // 状态变为已完成时记录完成时间，离开已完成时清空
+    let res = sqlx::query_scalar!(
         r#"
         UPDATE sub_tasks
-        SET title = $1, description = $2, due_date = $3, assignee_id = $4
-        WHERE id = $5
+        SET title = COALESCE($1, title),
+            description = CASE WHEN $14 THEN $2 ELSE description END,
+            due_date = CASE WHEN $15 THEN $3 ELSE due_date END,
+            assignee_id = CASE WHEN $16 THEN $4 ELSE assignee_id END,
+            priority = COALESCE($5, priority), labels = COALESCE($6, labels),
+            estimate_hours = CASE WHEN $17 THEN $7 ELSE estimate_hours END,
+            completed_at = CASE
+                WHEN $8::VARCHAR IS NULL OR $8 = status THEN completed_at
+                WHEN $8 = '已完成' THEN NOW()
+                ELSE NULL
+            END,
+            status = COALESCE($8, status),
+            milestone_id = CASE WHEN $13 THEN $11 ELSE milestone_id END,
+            mentions = $12
+        WHERE id = $9 AND task_id = $10
+        RETURNING title
         "#,
         form.title,
-        form.description,
-        form.due_date,
-        form.assignee_id,
-        sub_task_id
+        form.description.clone().flatten(),
+        form.due_date.flatten(),
+        form.assignee_id.flatten(),
+        form.priority,
+        labels.as_deref(),
+        form.estimate_hours.flatten(),
+        form.status,
+        sub_task_id,
+        task_id,
+        form.milestone_id.flatten(),
+        Json(&mentions) as _,
+        form.milestone_id.is_some(),
+        form.description.is_some(),
+        form.due_date.is_some(),
+        form.assignee_id.is_some(),
+        form.estimate_hours.is_some()
     )
-    .execute(pool.get_ref())
+    .fetch_optional(&mut *tx)
     .await;
+    let title = match res {
+        Ok(Some(title)) => title,
+        Ok(None) => {
+            tx.rollback().await.ok();
+            return HttpResponse::NotFound().json("Sub-task not found");
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    };
 
+    let place = format!("子任务「{}」", title);
+    let res = async {
+        mentions::notify_mentioned(&mut tx, task_id, form.user_id, &mentions, &previous, &place)
+            .await?;
+        sync_task_progress(&mut tx, task_id).await
+    }
+    .await;
     match res {
-        Ok(_) => HttpResponse::Ok().json(json!({"success": true})),
-        Err(e) => HttpResponse::InternalServerError()
-            .json(json!({ "success": false, "message": e.to_string() })),
+        Ok(percent) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok()
+                .json(json!({"success": true, "progress": percent, "mentions": mentions}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }))
+        }
     }
 }
 
@@ -694,17 +2049,4353 @@ pub async fn delete_sub_task(
     pool: web::Data<PgPool>,
     path: web::Path<(Uuid, Uuid)>,
 ) -> HttpResponse {
-    let (_task_id, sub_task_id) = path.into_inner();
-    let res = sqlx::query!("DELETE FROM sub_tasks WHERE id = $1", sub_task_id)
-        .execute(pool.get_ref())
-        .await;
+    let (task_id, sub_task_id) = path.into_inner();
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    };
+    let res = sqlx::query!(
+        "DELETE FROM sub_tasks WHERE id = $1 AND task_id = $2",
+        sub_task_id,
+        task_id
+    )
+    .execute(&mut *tx)
+    .await;
 
     match res {
-        Ok(result) if result.rows_affected() == 1 => {
+        Ok(result) if result.rows_affected() == 1 => {}
+        Ok(_) => {
+            tx.rollback().await.ok();
+            return HttpResponse::NotFound().json("Sub-task not found");
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    }
+
+    match sync_task_progress(&mut tx, task_id).await {
+        Ok(_) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({ "success": false, "message": e.to_string() }));
+            }
             HttpResponse::Ok().json(json!({ "success": true }))
         }
-        Ok(_) => HttpResponse::NotFound().json("Sub-task not found"),
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }))
+        }
+    }
+}
+
+// --- Sub-task Checklist Handlers ---
+
+#[derive(Debug, Deserialize)]
+pub struct ChecklistItemInput {
+    pub content: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChecklistItemUpdateInput {
+    pub content: Option<String>,
+    pub done: Option<bool>,
+}
+
+pub async fn list_checklist_items(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> HttpResponse {
+    let (task_id, sub_task_id) = path.into_inner();
+    let rows = sqlx::query_as::<_, ChecklistItem>(
+        r#"
+        SELECT c.* FROM sub_task_checklist_items c
+        JOIN sub_tasks st ON c.sub_task_id = st.id
+        WHERE c.sub_task_id = $1 AND st.task_id = $2
+        ORDER BY c.created_at ASC
+        "#,
+    )
+    .bind(sub_task_id)
+    .bind(task_id)
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match rows {
+        Ok(items) => HttpResponse::Ok().json(items),
         Err(e) => HttpResponse::InternalServerError()
             .json(json!({ "success": false, "message": e.to_string() })),
     }
 }
+
+pub async fn create_checklist_item(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    form: web::Json<ChecklistItemInput>,
+) -> HttpResponse {
+    let (task_id, sub_task_id) = path.into_inner();
+    let item_id = Uuid::new_v4();
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    };
+    let res = sqlx::query!(
+        r#"
+        INSERT INTO sub_task_checklist_items (id, sub_task_id, content, created_at)
+        SELECT $1, id, $2, $3 FROM sub_tasks WHERE id = $4 AND task_id = $5
+        "#,
+        item_id,
+        form.content,
+        Utc::now(),
+        sub_task_id,
+        task_id
+    )
+    .execute(&mut *tx)
+    .await;
+    match res {
+        Ok(r) if r.rows_affected() == 1 => {}
+        Ok(_) => {
+            tx.rollback().await.ok();
+            return HttpResponse::NotFound().json("Sub-task not found");
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    }
+
+    match sync_task_progress(&mut tx, task_id).await {
+        Ok(_) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({ "success": false, "message": e.to_string() }));
+            }
+            HttpResponse::Created().json(json!({ "success": true, "id": item_id }))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }))
+        }
+    }
+}
+
+pub async fn update_checklist_item(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid, Uuid)>,
+    form: web::Json<ChecklistItemUpdateInput>,
+) -> HttpResponse {
+    let (task_id, sub_task_id, item_id) = path.into_inner();
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    };
+    let res = sqlx::query!(
+        r#"
+        UPDATE sub_task_checklist_items c
+        SET content = COALESCE($1, c.content), done = COALESCE($2, c.done)
+        FROM sub_tasks st
+        WHERE c.id = $3 AND c.sub_task_id = $4 AND st.id = c.sub_task_id AND st.task_id = $5
+        "#,
+        form.content,
+        form.done,
+        item_id,
+        sub_task_id,
+        task_id
+    )
+    .execute(&mut *tx)
+    .await;
+    match res {
+        Ok(r) if r.rows_affected() == 1 => {}
+        Ok(_) => {
+            tx.rollback().await.ok();
+            return HttpResponse::NotFound().json("Checklist item not found");
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    }
+
+    match sync_task_progress(&mut tx, task_id).await {
+        Ok(percent) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({ "success": false, "message": e.to_string() }));
+            }
+            HttpResponse::Ok().json(json!({ "success": true, "progress": percent }))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }))
+        }
+    }
+}
+
+pub async fn delete_checklist_item(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid, Uuid)>,
+) -> HttpResponse {
+    let (task_id, sub_task_id, item_id) = path.into_inner();
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    };
+    let res = sqlx::query!(
+        r#"
+        DELETE FROM sub_task_checklist_items c
+        USING sub_tasks st
+        WHERE c.id = $1 AND c.sub_task_id = $2 AND st.id = c.sub_task_id AND st.task_id = $3
+        "#,
+        item_id,
+        sub_task_id,
+        task_id
+    )
+    .execute(&mut *tx)
+    .await;
+    match res {
+        Ok(r) if r.rows_affected() == 1 => {}
+        Ok(_) => {
+            tx.rollback().await.ok();
+            return HttpResponse::NotFound().json("Checklist item not found");
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    }
+
+    match sync_task_progress(&mut tx, task_id).await {
+        Ok(_) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({ "success": false, "message": e.to_string() }));
+            }
+            HttpResponse::Ok().json(json!({ "success": true }))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }))
+        }
+    }
+}
+
+// --- Recurring Sub-task Template Handlers ---
+
+#[derive(Debug, Deserialize)]
+pub struct SubTaskTemplateInput {
+    pub title: String,
+    pub description: Option<String>,
+    pub assignee_id: Option<Uuid>,
+    pub priority: Option<i16>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    // daily / weekly / custom
+    pub frequency: String,
+    // frequency 为 custom 时必填
+    pub interval_days: Option<i32>,
+    // 第一次生成的时间
+    pub starts_at: DateTime<Utc>,
+    pub until: Option<DateTime<Utc>>,
+    // 生成的子任务 DDL 相对生成时间的偏移，默认一个周期
+    pub due_offset_hours: Option<i32>,
+}
+
+pub async fn create_sub_task_template(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    form: web::Json<SubTaskTemplateInput>,
+) -> HttpResponse {
+    if let Some(resp) = invalid_priority(form.priority) {
+        return resp;
+    }
+    let interval_days = match (form.frequency.as_str(), form.interval_days) {
+        ("daily", _) => 1,
+        ("weekly", _) => 7,
+        ("custom", Some(days)) if days > 0 => days,
+        ("custom", _) => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "success": false, "message": "自定义周期需要大于 0 的 interval_days" }));
+        }
+        _ => {
+            return HttpResponse::BadRequest()
+                .json(json!({ "success": false, "message": "frequency 只支持 daily、weekly 或 custom" }));
+        }
+    };
+    if form.until.is_some_and(|until| until < form.starts_at) {
+        return HttpResponse::BadRequest()
+            .json(json!({ "success": false, "message": "结束日期不能早于开始时间" }));
+    }
+
+    let task_id = path.into_inner();
+    let template_id = Uuid::new_v4();
+    let now = Utc::now();
+    let priority = form.priority.unwrap_or(SUB_TASK_DEFAULT_PRIORITY);
+    let labels = normalize_labels(&form.labels);
+    let due_offset_hours = form.due_offset_hours.unwrap_or(interval_days * 24);
+
+    let res = sqlx::query!(
+        r#"
+        INSERT INTO sub_task_templates
+            (id, task_id, title, description, assignee_id, priority, labels, frequency,
+             interval_days, due_offset_hours, next_run_at, until_at, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+        "#,
+        template_id,
+        task_id,
+        form.title,
+        form.description,
+        form.assignee_id,
+        priority,
+        &labels,
+        form.frequency,
+        interval_days,
+        due_offset_hours,
+        form.starts_at,
+        form.until,
+        now
+    )
+    .execute(pool.get_ref())
+    .await;
+
+    match res {
+        Ok(_) => HttpResponse::Created().json(json!({ "success": true, "id": template_id })),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({ "success": false, "message": e.to_string() })),
+    }
+}
+
+pub async fn list_sub_task_templates(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
+    let rows = sqlx::query_as::<_, SubTaskTemplate>(
+        "SELECT * FROM sub_task_templates WHERE task_id = $1 ORDER BY created_at ASC",
+    )
+    .bind(path.into_inner())
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match rows {
+        Ok(templates) => HttpResponse::Ok().json(templates),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({ "success": false, "message": e.to_string() })),
+    }
+}
+
+// 删除模板只停止后续生成，已生成的子任务保留
+pub async fn delete_sub_task_template(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> HttpResponse {
+    let (task_id, template_id) = path.into_inner();
+    let res = sqlx::query!(
+        "DELETE FROM sub_task_templates WHERE id = $1 AND task_id = $2",
+        template_id,
+        task_id
+    )
+    .execute(pool.get_ref())
+    .await;
+
+    match res {
+        Ok(result) if result.rows_affected() == 1 => {
+            HttpResponse::Ok().json(json!({ "success": true }))
+        }
+        Ok(_) => HttpResponse::NotFound().json("Sub-task template not found"),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({ "success": false, "message": e.to_string() })),
+    }
+}
+
+// --- Burndown Handlers ---
+
+// 图表最多覆盖的天数
+const BURNDOWN_MAX_DAYS: i64 = 366;
+
+#[derive(Debug, Deserialize)]
+pub struct BurndownQuery {
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BurndownPoint {
+    pub date: NaiveDate,
+    // 当天结束时的工作总量
+    pub total: Option<f64>,
+    pub completed: Option<f64>,
+    pub remaining: Option<f64>,
+    // 理想剩余量：以当前工作总量为起点，线性下降到截止日的 0
+    pub ideal: f64,
+    // 当天最后一条进度记录的百分比
+    pub percent: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Burndown {
+    pub task_id: Uuid,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    // count 为按子任务个数，hours 为按预估工时
+    pub unit: String,
+    pub series: Vec<BurndownPoint>,
+}
+
+pub async fn get_burndown(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    query: web::Query<BurndownQuery>,
+) -> HttpResponse {
+    let task_id = path.into_inner();
+    let task = match sqlx::query!(
+        "SELECT created_at, weight_by_estimate, deadline FROM tasks WHERE id = $1",
+        task_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    {
+        Ok(Some(task)) => task,
+        Ok(None) => return HttpResponse::NotFound().json("Task not found"),
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    };
+
+    let sub_tasks = match sqlx::query!(
+        "SELECT created_at, completed_at, due_date, estimate_hours FROM sub_tasks WHERE task_id = $1",
+        task_id
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    };
+
+    let snapshots = match sqlx::query!(
+        "SELECT percent, created_at FROM progress WHERE task_id = $1 ORDER BY created_at ASC",
+        task_id
+    )
+    .fetch_all(pool.get_ref())
+    .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    };
+
+    let today = Utc::now().date_naive();
+    let start = query.start.unwrap_or(task.created_at).date_naive();
+    // 未指定结束日期时取任务截止日期，没有截止日期则取最晚的子任务 DDL，至少到今天
+    let end = query
+        .end
+        .or(task.deadline)
+        .map(|end| end.date_naive())
+        .unwrap_or_else(|| {
+            sub_tasks
+                .iter()
+                .filter_map(|st| st.due_date)
+                .map(|d| d.date_naive())
+                .max()
+                .map_or(today, |d| d.max(today))
+        });
+    if end < start {
+        return HttpResponse::BadRequest()
+            .json(json!({ "success": false, "message": "结束日期不能早于开始日期" }));
+    }
+    if (end - start).num_days() >= BURNDOWN_MAX_DAYS {
+        return HttpResponse::BadRequest()
+            .json(json!({ "success": false, "message": "时间跨度过大" }));
+    }
+
+    let weight = |estimate: Option<f32>| -> f64 {
+        if task.weight_by_estimate {
+            estimate.unwrap_or(1.0) as f64
+        } else {
+            1.0
+        }
+    };
+    let work_at = |date: NaiveDate| -> (f64, f64) {
+        let mut total = 0.0;
+        let mut completed = 0.0;
+        for st in &sub_tasks {
+            if st.created_at.date_naive() <= date {
+                total += weight(st.estimate_hours);
+            }
+            if st.completed_at.is_some_and(|c| c.date_naive() <= date) {
+                completed += weight(st.estimate_hours);
+            }
+        }
+        (total, completed)
+    };
+
+    // 子任务大多在任务创建之后才拆分出来，理想线按当前的工作总量计算
+    let (scope, _) = work_at(today.min(end));
+    let span = (end - start).num_days().max(1) as f64;
+    let series = start
+        .iter_days()
+        .take_while(|date| *date <= end)
+        .map(|date| {
+            let ideal = scope * (1.0 - (date - start).num_days() as f64 / span);
+            // 未来的日期只有理想线
+            if date > today {
+                return BurndownPoint {
+                    date,
+                    total: None,
+                    completed: None,
+                    remaining: None,
+                    ideal,
+                    percent: None,
+                };
+            }
+            let (total, completed) = work_at(date);
+            let percent = snapshots
+                .iter()
+                .take_while(|p| p.created_at.date_naive() <= date)
+                .last()
+                .map(|p| p.percent);
+            BurndownPoint {
+                date,
+                total: Some(total),
+                completed: Some(completed),
+                remaining: Some(total - completed),
+                ideal,
+                percent,
+            }
+        })
+        .collect();
+
+    HttpResponse::Ok().json(Burndown {
+        task_id,
+        start,
+        end,
+        unit: if task.weight_by_estimate { "hours" } else { "count" }.to_string(),
+        series,
+    })
+}
+
+// --- Time Log Handlers ---
+
+#[derive(Debug, Deserialize)]
+pub struct TimeLogInput {
+    pub sub_task_id: Option<Uuid>,
+    pub hours: f32,
+    pub note: Option<String>,
+    // 默认为当前时间
+    pub logged_at: Option<DateTime<Utc>>,
+}
+
+pub async fn add_time_log(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    form: web::Json<TimeLogInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    if !form.hours.is_finite() || form.hours <= 0.0 {
+        return HttpResponse::BadRequest()
+            .json(json!({ "success": false, "message": "工时必须大于 0" }));
+    }
+    let task_id = path.into_inner();
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    };
+    // 工时计入贡献度，只有任务当前成员可以登记
+    match membership::current_team(&mut conn, task_id).await {
+        Ok(team) if team.contains(&user_id) => {}
+        Ok(_) => {
+            return HttpResponse::Forbidden()
+                .json(json!({ "success": false, "message": "只有任务成员可以登记工时" }));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    }
+    let id = Uuid::new_v4();
+    let now = Utc::now();
+    // 子任务必须属于该任务
+    let res = sqlx::query!(
+        r#"
+        INSERT INTO time_logs (id, task_id, sub_task_id, user_id, hours, note, logged_at, created_at)
+        SELECT $1, $2, $3, $4, $5, $6, $7, $8
+        WHERE $3::UUID IS NULL OR EXISTS (SELECT 1 FROM sub_tasks WHERE id = $3 AND task_id = $2)
+        "#,
+        id,
+        task_id,
+        form.sub_task_id,
+        user_id,
+        form.hours,
+        form.note,
+        form.logged_at.unwrap_or(now),
+        now
+    )
+    .execute(&mut *conn)
+    .await;
+
+    match res {
+        Ok(r) if r.rows_affected() == 1 => {
+            HttpResponse::Created().json(json!({ "success": true, "id": id }))
+        }
+        Ok(_) => HttpResponse::NotFound().json("Sub-task not found"),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({ "success": false, "message": e.to_string() })),
+    }
+}
+
+pub async fn list_time_logs(pool: web::Data<PgPool>, path: web::Path<Uuid>) -> HttpResponse {
+    let rows = sqlx::query_as::<_, TimeLog>(
+        "SELECT * FROM time_logs WHERE task_id = $1 ORDER BY logged_at DESC",
+    )
+    .bind(path.into_inner())
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match rows {
+        Ok(logs) => HttpResponse::Ok().json(logs),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({ "success": false, "message": e.to_string() })),
+    }
+}
+
+// --- Report Handlers ---
+
+#[derive(Debug, Deserialize)]
+pub struct ReportQuery {
+    // 默认为最近 7 天
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    // markdown | pdf
+    pub format: Option<String>,
+}
+
+// 报告文件，依次为内容、文件名和 Content-Type
+struct ReportFile {
+    bytes: Vec<u8>,
+    file_name: String,
+    content_type: &'static str,
+}
+
+async fn render_task_report(
+    pool: &PgPool,
+    task_id: Uuid,
+    query: &ReportQuery,
+) -> Result<ReportFile, HttpResponse> {
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - chrono::Duration::days(7));
+    if from >= to {
+        return Err(HttpResponse::BadRequest()
+            .json(json!({ "success": false, "message": "开始时间必须早于结束时间" })));
+    }
+
+    let report = match build_weekly_report(pool, task_id, from, to).await {
+        Ok(Some(report)) => report,
+        Ok(None) => return Err(HttpResponse::NotFound().json("Task not found")),
+        Err(e) => {
+            return Err(HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() })));
+        }
+    };
+
+    let (bytes, ext, content_type) = match query.format.as_deref().unwrap_or("markdown") {
+        "markdown" | "md" => (
+            report.to_markdown().into_bytes(),
+            "md",
+            "text/markdown; charset=utf-8",
+        ),
+        "pdf" => {
+            let font_path = match env::var("REPORT_FONT_PATH") {
+                Ok(path) => path,
+                Err(_) => {
+                    return Err(HttpResponse::InternalServerError()
+                        .json(json!({ "success": false, "message": "未配置 REPORT_FONT_PATH，无法生成 PDF" })));
+                }
+            };
+            let read_path = font_path.clone();
+            let font_data = match web::block(move || std::fs::read(read_path)).await {
+                Ok(Ok(data)) => data,
+                _ => {
+                    return Err(HttpResponse::InternalServerError().json(json!({
+                        "success": false,
+                        "message": format!("无法读取报告字体文件 {}，获取方式见 backend/README.md", font_path)
+                    })));
+                }
+            };
+            match report.to_pdf(&font_data) {
+                Ok(pdf) => (pdf, "pdf", "application/pdf"),
+                Err(message) => {
+                    return Err(HttpResponse::InternalServerError()
+                        .json(json!({ "success": false, "message": message })));
+                }
+            }
+        }
+        _ => {
+            return Err(HttpResponse::BadRequest()
+                .json(json!({ "success": false, "message": "format 只支持 markdown 或 pdf" })));
+        }
+    };
+    let file_name = format!(
+        "report-{}-{}.{}",
+        from.format("%Y%m%d"),
+        to.format("%Y%m%d"),
+        ext
+    );
+    Ok(ReportFile {
+        bytes,
+        file_name,
+        content_type,
+    })
+}
+
+// 下载周报
+pub async fn get_task_report(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    query: web::Query<ReportQuery>,
+) -> HttpResponse {
+    match render_task_report(pool.get_ref(), path.into_inner(), &query).await {
+        Ok(report) => HttpResponse::Ok()
+            .content_type(report.content_type)
+            .insert_header((
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", report.file_name),
+            ))
+            .body(report.bytes),
+        Err(resp) => resp,
+    }
+}
+
+// 生成周报并保存为任务附件，只有任务当前成员可以保存
+pub async fn save_task_report(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    form: web::Json<ReportQuery>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let task_id = path.into_inner();
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    };
+    match membership::current_team(&mut conn, task_id).await {
+        Ok(team) if team.contains(&user_id) => {}
+        Ok(_) => {
+            return HttpResponse::Forbidden()
+                .json(json!({ "success": false, "message": "只有任务成员可以保存周报" }));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    }
+    drop(conn);
+    let report = match render_task_report(pool.get_ref(), task_id, &form).await {
+        Ok(report) => report,
+        Err(resp) => return resp,
+    };
+
+    match save_task_attachment(
+        pool.get_ref(),
+        task_id,
+        &report.file_name,
+        report.content_type,
+        report.bytes,
+        Some(user_id),
+    )
+    .await
+    {
+        Ok(attachment) => {
+            HttpResponse::Created().json(json!({ "success": true, "attachment": attachment }))
+        }
+        Err(message) => HttpResponse::InternalServerError()
+            .json(json!({ "success": false, "message": message })),
+    }
+}
+
+// --- Attachment Handlers ---
+
+const ATTACHMENTS_DIR: &str = "./static/attachments";
+
+// 写入 ./static/attachments/{task_id}/ 并登记到 task_attachments
+async fn save_task_attachment(
+    pool: &PgPool,
+    task_id: Uuid,
+    file_name: &str,
+    content_type: &str,
+    bytes: Vec<u8>,
+    uploaded_by: Option<Uuid>,
+) -> Result<TaskAttachment, String> {
+    let id = Uuid::new_v4();
+    let ext = std::path::Path::new(file_name)
+        .extension()
+        .and_then(std::ffi::OsStr::to_str)
+        .unwrap_or("bin")
+        .to_string();
+    let dir = format!("{}/{}", ATTACHMENTS_DIR, task_id);
+    let stored_name = format!("{}.{}", id, ext);
+    let file_path = format!("{}/{}", dir, stored_name);
+    let size_bytes = bytes.len() as i64;
+
+    web::block(move || {
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(file_path, bytes)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    let url = format!("/static/attachments/{}/{}", task_id, stored_name);
+    sqlx::query_as::<_, TaskAttachment>(
+        r#"
+        INSERT INTO task_attachments (id, task_id, file_name, url, content_type, size_bytes, uploaded_by, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(task_id)
+    .bind(file_name)
+    .bind(url)
+    .bind(content_type)
+    .bind(size_bytes)
+    .bind(uploaded_by)
+    .bind(Utc::now())
+    .fetch_one(pool)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+pub async fn list_task_attachments(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
+    let rows = sqlx::query_as::<_, TaskAttachment>(
+        "SELECT * FROM task_attachments WHERE task_id = $1 ORDER BY created_at DESC",
+    )
+    .bind(path.into_inner())
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match rows {
+        Ok(attachments) => HttpResponse::Ok().json(attachments),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({ "success": false, "message": e.to_string() })),
+    }
+}
+
+// --- Milestone Handlers ---
+
+#[derive(Debug, Deserialize)]
+pub struct MilestoneInput {
+    pub name: String,
+    pub description: Option<String>,
+    pub target_date: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MilestoneDetails {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub target_date: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub sub_task_total: i64,
+    pub sub_task_completed: i64,
+    pub percent: i32,
+    pub completed: bool,
+    pub overdue: bool,
+    // 当前阶段：按目标日期排序后第一个未完成的里程碑
+    pub current: bool,
+}
+
+pub async fn create_milestone(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    form: web::Json<MilestoneInput>,
+) -> HttpResponse {
+    let res = sqlx::query_as::<_, Milestone>(
+        r#"
+        INSERT INTO milestones (id, task_id, name, description, target_date, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(path.into_inner())
+    .bind(&form.name)
+    .bind(&form.description)
+    .bind(form.target_date)
+    .bind(Utc::now())
+    .fetch_one(pool.get_ref())
+    .await;
+
+    match res {
+        Ok(milestone) => HttpResponse::Created()
+            .json(json!({ "success": true, "id": milestone.id, "milestone": milestone })),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({ "success": false, "message": e.to_string() })),
+    }
+}
+
+pub async fn list_milestones(pool: web::Data<PgPool>, path: web::Path<Uuid>) -> HttpResponse {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            m.id, m.task_id, m.name, m.description, m.target_date, m.created_at,
+            COUNT(st.id) as "sub_task_total!",
+            COUNT(st.id) FILTER (WHERE st.status = '已完成') as "sub_task_completed!"
+        FROM milestones m
+        LEFT JOIN sub_tasks st ON st.milestone_id = m.id
+        WHERE m.task_id = $1
+        GROUP BY m.id
+        ORDER BY m.target_date ASC
+        "#,
+        path.into_inner()
+    )
+    .fetch_all(pool.get_ref())
+    .await;
+
+    let rows = match rows {
+        Ok(rows) => rows,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({ "success": false, "message": e.to_string() }));
+        }
+    };
+
+    let now = Utc::now();
+    let mut found_current = false;
+    let milestones: Vec<MilestoneDetails> = rows
+        .into_iter()
+        .map(|m| {
+            let completed = m.sub_task_total > 0 && m.sub_task_completed == m.sub_task_total;
+            let current = !completed && !found_current;
+            found_current |= current;
+            MilestoneDetails {
+                id: m.id,
+                task_id: m.task_id,
+                name: m.name,
+                description: m.description,
+                target_date: m.target_date,
+                created_at: m.created_at,
+                sub_task_total: m.sub_task_total,
+                sub_task_completed: m.sub_task_completed,
+                percent: if m.sub_task_total > 0 {
+                    (m.sub_task_completed * 100 / m.sub_task_total) as i32
+                } else {
+                    0
+                },
+                completed,
+                overdue: !completed && m.target_date < now,
+                current,
+            }
+        })
+        .collect();
+
+    HttpResponse::Ok().json(milestones)
+}
+
+pub async fn update_milestone(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    form: web::Json<MilestoneInput>,
+) -> HttpResponse {
+    let (task_id, milestone_id) = path.into_inner();
+    let res = sqlx::query!(
+        r#"
+        UPDATE milestones SET name = $1, description = $2, target_date = $3
+        WHERE id = $4 AND task_id = $5
+        "#,
+        form.name,
+        form.description,
+        form.target_date,
+        milestone_id,
+        task_id
+    )
+    .execute(pool.get_ref())
+    .await;
+
+    match res {
+        Ok(result) if result.rows_affected() == 1 => {
+            HttpResponse::Ok().json(json!({ "success": true }))
+        }
+        Ok(_) => HttpResponse::NotFound().json("Milestone not found"),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({ "success": false, "message": e.to_string() })),
+    }
+}
+
+// 删除里程碑后，其下子任务的 milestone_id 置空
+pub async fn delete_milestone(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> HttpResponse {
+    let (task_id, milestone_id) = path.into_inner();
+    let res = sqlx::query!(
+        "DELETE FROM milestones WHERE id = $1 AND task_id = $2",
+        milestone_id,
+        task_id
+    )
+    .execute(pool.get_ref())
+    .await;
+
+    match res {
+        Ok(result) if result.rows_affected() == 1 => {
+            HttpResponse::Ok().json(json!({ "success": true }))
+        }
+        Ok(_) => HttpResponse::NotFound().json("Milestone not found"),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({ "success": false, "message": e.to_string() })),
+    }
+}
+
+// --- Task Template Handlers ---
+
+#[derive(Debug, Deserialize)]
+pub struct TaskTemplateInput {
+    // 默认使用任务标题
+    pub name: Option<String>,
+}
+
+// 新建任务（从模板创建或复制任务）的参数
+#[derive(Debug, Deserialize)]
+pub struct TaskInstanceInput {
+    pub title: Option<String>,
+    // 新任务的起始时间，截止日期、里程碑、子任务 DDL 按原任务的相对时间平移
+    pub start_date: Option<DateTime<Utc>>,
+    // 草稿 / 招募中 / 进行中，默认草稿
+    pub status: Option<String>,
+}
+
+// 将任务当前的结构保存为模板，仅发布者可操作
+pub async fn save_task_as_template(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    form: web::Json<TaskTemplateInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let task_id = path.into_inner();
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let captured = match blueprint::capture(&mut conn, task_id).await {
+        Ok(Some(captured)) if captured.creator_id == Some(user_id) => captured,
+        Ok(_) => {
+            return HttpResponse::Ok()
+                .json(json!({"success": false, "message": "任务不存在或无权操作"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+
+    let name = form
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .unwrap_or(&captured.blueprint.title)
+        .to_string();
+    let id = Uuid::new_v4();
+    let res = sqlx::query!(
+        r#"
+        INSERT INTO task_templates (id, creator_id, name, source_task_id, structure, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        id,
+        user_id,
+        name,
+        task_id,
+        Json(&captured.blueprint) as _,
+        Utc::now()
+    )
+    .execute(&mut *conn)
+    .await;
+
+    match res {
+        Ok(_) => HttpResponse::Ok().json(json!({"success": true, "id": id})),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+pub async fn list_my_task_templates(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let rows = sqlx::query_as::<_, TaskTemplate>(
+        "SELECT * FROM task_templates WHERE creator_id = $1 ORDER BY created_at DESC",
+    )
+    .bind(user_id)
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match rows {
+        Ok(templates) => HttpResponse::Ok().json(templates),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+pub async fn delete_task_template(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let res = sqlx::query!(
+        "DELETE FROM task_templates WHERE id = $1 AND creator_id = $2",
+        path.into_inner(),
+        user_id
+    )
+    .execute(pool.get_ref())
+    .await;
+
+    match res {
+        Ok(r) if r.rows_affected() == 1 => HttpResponse::Ok().json(json!({"success": true})),
+        Ok(_) => HttpResponse::Ok()
+            .json(json!({"success": false, "message": "模板不存在或无权删除"})),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+pub async fn create_task_from_template(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    form: web::Json<TaskInstanceInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let status = form.status.as_deref().unwrap_or(lifecycle::DRAFT);
+    if let Some(resp) = invalid_initial_status(status) {
+        return resp;
+    }
+    let template = sqlx::query_as::<_, TaskTemplate>(
+        "SELECT * FROM task_templates WHERE id = $1 AND creator_id = $2",
+    )
+    .bind(path.into_inner())
+    .bind(user_id)
+    .fetch_optional(pool.get_ref())
+    .await;
+    let template = match template {
+        Ok(Some(template)) => template,
+        Ok(None) => {
+            return HttpResponse::Ok()
+                .json(json!({"success": false, "message": "模板不存在或无权使用"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+
+    let title = form
+        .title
+        .as_deref()
+        .unwrap_or(&template.structure.title);
+    // 偏移相对于零点，不指定起始时间时从今天零点起算，而不是当前时刻
+    let start = form
+        .start_date
+        .unwrap_or_else(|| blueprint::day_start(Utc::now()));
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    match blueprint::instantiate(&mut tx, &template.structure, user_id, title, start, status).await
+    {
+        Ok(id) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true, "id": id}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}))
+        }
+    }
+}
+
+// 复制任务：复制职责、子任务和里程碑，不复制成员、分配、进度和历史。仅发布者可操作
+pub async fn clone_task(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    form: web::Json<TaskInstanceInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let status = form.status.as_deref().unwrap_or(lifecycle::DRAFT);
+    if let Some(resp) = invalid_initial_status(status) {
+        return resp;
+    }
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let captured = match blueprint::capture(&mut tx, path.into_inner()).await {
+        Ok(Some(captured)) if captured.creator_id == Some(user_id) => captured,
+        Ok(_) => {
+            tx.rollback().await.ok();
+            return HttpResponse::Ok()
+                .json(json!({"success": false, "message": "任务不存在或无权操作"}));
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+
+    let title = form
+        .title
+        .clone()
+        .unwrap_or_else(|| format!("{}（副本）", captured.blueprint.title));
+    // 不指定起始时间时保持原任务的日期
+    let start = form.start_date.unwrap_or(captured.anchor);
+    match blueprint::instantiate(&mut tx, &captured.blueprint, user_id, &title, start, status).await
+    {
+        Ok(id) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true, "id": id}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}))
+        }
+    }
+}
+
+// --- Join Request Handlers ---
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct JoinRequestDetails {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub task_title: String,
+    pub role_id: Uuid,
+    pub role_name: String,
+    pub user_id: Uuid,
+    pub name: Option<String>,
+    pub username: String,
+    pub message: Option<String>,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub decision_note: Option<String>,
+}
+
+const JOIN_REQUEST_SELECT: &str = r#"
+    SELECT jr.id, jr.task_id, t.title as task_title, jr.role_id, tr.role_name,
+        jr.user_id, u.name, u.username, jr.message, jr.status, jr.created_at,
+        jr.decided_at, jr.decision_note
+    FROM join_requests jr
+    JOIN tasks t ON t.id = jr.task_id
+    JOIN task_roles tr ON tr.id = jr.role_id
+    JOIN users u ON u.id = jr.user_id
+"#;
+
+#[derive(Debug, Deserialize)]
+pub struct JoinRequestQuery {
+    // 待审核 / 已通过 / 已拒绝 / 已撤回，不传返回全部
+    pub status: Option<String>,
+}
+
+// 发布者查看任务收到的申请
+pub async fn list_join_requests(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    task_id: web::Path<Uuid>,
+    query: web::Query<JoinRequestQuery>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let is_creator = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM tasks WHERE id = $1 AND creator_id = $2 AND deleted_at IS NULL) as "exists!""#,
+        *task_id,
+        user_id
+    )
+    .fetch_one(pool.get_ref())
+    .await;
+    match is_creator {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::Ok()
+                .json(json!({"success": false, "message": "任务不存在或无权操作"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    }
+
+    let rows = sqlx::query_as::<_, JoinRequestDetails>(&format!(
+        "{} WHERE jr.task_id = $1 AND ($2::TEXT IS NULL OR jr.status = $2) ORDER BY jr.created_at DESC",
+        JOIN_REQUEST_SELECT
+    ))
+    .bind(*task_id)
+    .bind(query.status.as_deref())
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match rows {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+// 申请人查看自己提交的申请
+pub async fn list_my_join_requests(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let rows = sqlx::query_as::<_, JoinRequestDetails>(&format!(
+        "{} WHERE jr.user_id = $1 AND t.deleted_at IS NULL ORDER BY jr.created_at DESC",
+        JOIN_REQUEST_SELECT
+    ))
+    .bind(user_id)
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match rows {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JoinDecisionInput {
+    pub note: Option<String>,
+}
+
+// 审核申请：锁住申请行并校验发布者身份与申请状态
+async fn lock_pending_request(
+    conn: &mut PgConnection,
+    request_id: Uuid,
+    creator_id: Uuid,
+) -> Result<JoinRequestDetails, HttpResponse> {
+    let row = sqlx::query_as::<_, JoinRequestDetails>(&format!(
+        "{} WHERE jr.id = $1 FOR UPDATE OF jr",
+        JOIN_REQUEST_SELECT
+    ))
+    .bind(request_id)
+    .fetch_optional(&mut *conn)
+    .await;
+    let request = match row {
+        Ok(Some(request)) => request,
+        Ok(None) => return Err(HttpResponse::NotFound().json("Join request not found")),
+        Err(e) => {
+            return Err(HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()})));
+        }
+    };
+    let owner = sqlx::query_scalar!(
+        "SELECT creator_id FROM tasks WHERE id = $1",
+        request.task_id
+    )
+    .fetch_one(&mut *conn)
+    .await;
+    match owner {
+        Ok(owner) if owner == Some(creator_id) => {}
+        Ok(_) => {
+            return Err(HttpResponse::Forbidden()
+                .json(json!({"success": false, "message": "只有任务发布者可以审核申请"})));
+        }
+        Err(e) => {
+            return Err(HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()})));
+        }
+    }
+    if request.status != membership::REQUEST_PENDING {
+        return Err(HttpResponse::Ok()
+            .json(json!({"success": false, "message": format!("该申请{}", request.status)})));
+    }
+    Ok(request)
+}
+
+// 通过申请：在同一事务中按名额检查加入职责、更新申请并通知申请人
+pub async fn approve_join_request(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    form: web::Json<JoinDecisionInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let request = match lock_pending_request(&mut tx, path.into_inner(), user_id).await {
+        Ok(request) => request,
+        Err(resp) => {
+            tx.rollback().await.ok();
+            return resp;
+        }
+    };
+
+    let res: Result<(), JoinError> = async {
+        membership::join_role(&mut tx, request.role_id, request.user_id, user_id).await?;
+        decide_join_request(
+            &mut tx,
+            &request,
+            membership::REQUEST_APPROVED,
+            user_id,
+            form.note.as_deref(),
+        )
+        .await?;
+        Ok(())
+    }
+    .await;
+    match res {
+        Ok(()) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true}))
+        }
+        Err(JoinError::Db(e)) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}))
+        }
+        // 名额已满等情况保留申请，发布者可以稍后再处理或拒绝
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::Ok().json(json!({"success": false, "message": e.message()}))
+        }
+    }
+}
+
+pub async fn reject_join_request(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    form: web::Json<JoinDecisionInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let request = match lock_pending_request(&mut tx, path.into_inner(), user_id).await {
+        Ok(request) => request,
+        Err(resp) => {
+            tx.rollback().await.ok();
+            return resp;
+        }
+    };
+    match decide_join_request(
+        &mut tx,
+        &request,
+        membership::REQUEST_REJECTED,
+        user_id,
+        form.note.as_deref(),
+    )
+    .await
+    {
+        Ok(()) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}))
+        }
+    }
+}
+
+async fn decide_join_request(
+    conn: &mut PgConnection,
+    request: &JoinRequestDetails,
+    status: &str,
+    decided_by: Uuid,
+    note: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE join_requests
+        SET status = $1, decided_by = $2, decided_at = $3, decision_note = $4
+        WHERE id = $5
+        "#,
+        status,
+        decided_by,
+        Utc::now(),
+        note,
+        request.id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let (kind, verdict) = if status == membership::REQUEST_APPROVED {
+        (notifications::JOIN_APPROVED, "已通过")
+    } else {
+        (notifications::JOIN_REJECTED, "未通过")
+    };
+    let mut content = format!(
+        "你对任务「{}」职责「{}」的申请{}",
+        request.task_title, request.role_name, verdict
+    );
+    if let Some(note) = note.filter(|n| !n.trim().is_empty()) {
+        content.push_str(&format!("：{}", note.trim()));
+    }
+    notifications::notify(conn, request.user_id, kind, &content, Some(request.task_id)).await
+}
+
+// 申请人撤回待审核的申请
+pub async fn withdraw_join_request(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let res = sqlx::query!(
+        "UPDATE join_requests SET status = $1, decided_at = $2 WHERE id = $3 AND user_id = $4 AND status = $5",
+        membership::REQUEST_WITHDRAWN,
+        Utc::now(),
+        path.into_inner(),
+        user_id,
+        membership::REQUEST_PENDING
+    )
+    .execute(pool.get_ref())
+    .await;
+
+    match res {
+        Ok(r) if r.rows_affected() == 1 => HttpResponse::Ok().json(json!({"success": true})),
+        Ok(_) => {
+            HttpResponse::Ok().json(json!({"success": false, "message": "申请不存在或已处理"}))
+        }
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+// --- Invitation Handlers ---
+
+#[derive(Debug, Deserialize)]
+pub struct InviteLinkInput {
+    // 为空表示受邀者可加入任一职责
+    pub role_id: Option<Uuid>,
+    pub expires_in_hours: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InviteLinkDetails {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub role_id: Option<Uuid>,
+    pub role_name: Option<String>,
+    pub token: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+// 生成邀请链接。链接是签名的 token，前端拼成 /invite/{token} 分享
+pub async fn create_invite_link(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    form: web::Json<InviteLinkInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let task_id = path.into_inner();
+    let hours = form
+        .expires_in_hours
+        .unwrap_or(invites::INVITE_DEFAULT_HOURS);
+    if !(1..=invites::INVITE_MAX_HOURS).contains(&hours) {
+        return HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": format!("有效期需在1到{}小时之间", invites::INVITE_MAX_HOURS)
+        }));
+    }
+
+    let task = sqlx::query!(
+        r#"
+        SELECT creator_id,
+            ($2::UUID IS NULL OR EXISTS (SELECT 1 FROM task_roles WHERE id = $2 AND task_id = $1)) as "role_ok!"
+        FROM tasks WHERE id = $1 AND deleted_at IS NULL
+        "#,
+        task_id,
+        form.role_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await;
+    match task {
+        Ok(Some(t)) if t.creator_id == Some(user_id) && t.role_ok => {}
+        Ok(Some(t)) if t.creator_id == Some(user_id) => {
+            return HttpResponse::Ok()
+                .json(json!({"success": false, "message": "职责不属于该任务"}));
+        }
+        Ok(_) => {
+            return HttpResponse::Ok()
+                .json(json!({"success": false, "message": "任务不存在或无权操作"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    }
+
+    let id = Uuid::new_v4();
+    let now = Utc::now();
+    let expires_at = now + chrono::Duration::hours(hours);
+    let token = match invites::sign_invite(id, task_id, form.role_id, expires_at) {
+        Ok(token) => token,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let res = sqlx::query!(
+        r#"
+        INSERT INTO task_invite_links (id, task_id, role_id, created_by, created_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        id,
+        task_id,
+        form.role_id,
+        user_id,
+        now,
+        expires_at
+    )
+    .execute(pool.get_ref())
+    .await;
+
+    match res {
+        Ok(_) => HttpResponse::Ok().json(json!({
+            "success": true,
+            "id": id,
+            "token": token,
+            "expires_at": expires_at
+        })),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+// 发布者查看任务的邀请链接。签名是确定的，重新签出的 token 与创建时相同。
+// token 可绕过加入审核，只返回给发布者
+pub async fn list_invite_links(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    task_id: web::Path<Uuid>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let is_creator = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM tasks WHERE id = $1 AND creator_id = $2 AND deleted_at IS NULL) as "exists!""#,
+        *task_id,
+        user_id
+    )
+    .fetch_one(pool.get_ref())
+    .await;
+    match is_creator {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::Ok()
+                .json(json!({"success": false, "message": "任务不存在或无权操作"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    }
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT l.id, l.task_id, l.role_id, tr.role_name as "role_name?", l.created_at, l.expires_at, l.revoked_at
+        FROM task_invite_links l
+        LEFT JOIN task_roles tr ON tr.id = l.role_id
+        WHERE l.task_id = $1
+        ORDER BY l.created_at DESC
+        "#,
+        *task_id
+    )
+    .fetch_all(pool.get_ref())
+    .await;
+
+    let rows = match rows {
+        Ok(rows) => rows,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let mut links = Vec::with_capacity(rows.len());
+    for r in rows {
+        let token = match invites::sign_invite(r.id, r.task_id, r.role_id, r.expires_at) {
+            Ok(token) => token,
+            Err(e) => {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+        };
+        links.push(InviteLinkDetails {
+            id: r.id,
+            task_id: r.task_id,
+            role_id: r.role_id,
+            role_name: r.role_name,
+            token,
+            created_at: r.created_at,
+            expires_at: r.expires_at,
+            revoked_at: r.revoked_at,
+        });
+    }
+    HttpResponse::Ok().json(links)
+}
+
+// 撤销邀请链接，已加入的成员不受影响
+pub async fn revoke_invite_link(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let res = sqlx::query!(
+        r#"
+        UPDATE task_invite_links l SET revoked_at = NOW()
+        FROM tasks t
+        WHERE l.id = $1 AND t.id = l.task_id AND t.creator_id = $2 AND l.revoked_at IS NULL
+        "#,
+        path.into_inner(),
+        user_id
+    )
+    .execute(pool.get_ref())
+    .await;
+
+    match res {
+        Ok(r) if r.rows_affected() == 1 => HttpResponse::Ok().json(json!({"success": true})),
+        Ok(_) => {
+            HttpResponse::Ok().json(json!({"success": false, "message": "邀请链接不存在或已撤销"}))
+        }
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+// 校验 token 并确认链接未被撤销
+async fn verify_invite_link(
+    conn: &mut PgConnection,
+    token: &str,
+) -> Result<Option<invites::InviteClaims>, sqlx::Error> {
+    let claims = match invites::verify_invite(token) {
+        Some(claims) => claims,
+        None => return Ok(None),
+    };
+    let active = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM task_invite_links WHERE id = $1 AND revoked_at IS NULL) as "exists!""#,
+        claims.jti
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(active.then_some(claims))
+}
+
+// 打开邀请链接时展示的任务与可加入的职责
+pub async fn get_invite_link(pool: web::Data<PgPool>, token: web::Path<String>) -> HttpResponse {
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let claims = match verify_invite_link(&mut conn, &token).await {
+        Ok(Some(claims)) => claims,
+        Ok(None) => {
+            return HttpResponse::Ok()
+                .json(json!({"success": false, "message": "邀请链接无效或已过期"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let task = sqlx::query!(
+        "SELECT title, description, status FROM tasks WHERE id = $1 AND deleted_at IS NULL",
+        claims.task_id
+    )
+    .fetch_optional(&mut *conn)
+    .await;
+    let task = match task {
+        Ok(Some(task)) => task,
+        Ok(None) => return HttpResponse::NotFound().json("Task not found"),
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let roles = match load_task_roles(pool.get_ref(), claims.task_id, None).await {
+        Ok(roles) => roles
+            .into_iter()
+            .filter(|r| claims.role_id.is_none_or(|id| id == r.role_id))
+            .map(|r| {
+                json!({
+                    "role_id": r.role_id,
+                    "role_name": r.role_name,
+                    "capacity": r.capacity,
+                    "filled": r.members.len(),
+                    "closed": r.closed
+                })
+            })
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    HttpResponse::Ok().json(json!({
+        "success": true,
+        "task_id": claims.task_id,
+        "title": task.title,
+        "description": task.description,
+        "status": task.status,
+        "role_id": claims.role_id,
+        "roles": roles
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcceptInviteLinkInput {
+    // 链接未指定职责时由受邀者选择
+    pub role_id: Option<Uuid>,
+}
+
+// 通过邀请链接加入。邀请视为发布者已同意，不再走审核，但名额检查与认领职责相同
+pub async fn accept_invite_link(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    token: web::Path<String>,
+    form: web::Json<AcceptInviteLinkInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let claims = match verify_invite_link(&mut tx, &token).await {
+        Ok(Some(claims)) => claims,
+        Ok(None) => {
+            tx.rollback().await.ok();
+            return HttpResponse::Ok()
+                .json(json!({"success": false, "message": "邀请链接无效或已过期"}));
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let role_id = match claims.role_id.or(form.role_id) {
+        Some(role_id) => role_id,
+        None => {
+            tx.rollback().await.ok();
+            return HttpResponse::BadRequest()
+                .json(json!({"success": false, "message": "请选择要加入的职责"}));
+        }
+    };
+
+    let res: Result<RoleSlot, JoinError> = async {
+        let slot = membership::join_role(&mut tx, role_id, user_id, user_id).await?;
+        // 任务级链接只能加入该任务的职责
+        if slot.task_id != claims.task_id {
+            return Err(JoinError::RoleNotFound);
+        }
+        if let Some(creator_id) = slot.creator_id.filter(|id| *id != user_id) {
+            let name = user_display_name(&mut tx, user_id).await?;
+            notifications::notify(
+                &mut tx,
+                creator_id,
+                notifications::INVITATION_ACCEPTED,
+                &format!("{} 通过邀请链接加入了职责「{}」", name, slot.role_name),
+                Some(slot.task_id),
+            )
+            .await?;
+        }
+        Ok(slot)
+    }
+    .await;
+    match res {
+        Ok(slot) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true, "task_id": slot.task_id}))
+        }
+        Err(JoinError::Db(e)) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::Ok().json(json!({"success": false, "message": e.message()}))
+        }
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct InvitationDetails {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub task_title: String,
+    pub role_id: Uuid,
+    pub role_name: String,
+    pub inviter_id: Uuid,
+    pub inviter_name: String,
+    pub invitee_id: Uuid,
+    pub invitee_name: String,
+    pub message: Option<String>,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub responded_at: Option<DateTime<Utc>>,
+}
+
+const INVITATION_SELECT: &str = r#"
+    SELECT i.id, i.task_id, t.title as task_title, i.role_id, tr.role_name,
+        i.inviter_id, COALESCE(NULLIF(a.name, ''), a.username) as inviter_name,
+        i.invitee_id, COALESCE(NULLIF(b.name, ''), b.username) as invitee_name,
+        i.message, i.status, i.created_at, i.responded_at
+    FROM task_invitations i
+    JOIN tasks t ON t.id = i.task_id
+    JOIN task_roles tr ON tr.id = i.role_id
+    JOIN users a ON a.id = i.inviter_id
+    JOIN users b ON b.id = i.invitee_id
+"#;
+
+#[derive(Debug, Deserialize)]
+pub struct InvitationInput {
+    pub username: String,
+    pub role_id: Uuid,
+    pub message: Option<String>,
+}
+
+// 按用户名直接邀请，受邀者收到通知后接受或拒绝
+pub async fn invite_user(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    form: web::Json<InvitationInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let task_id = path.into_inner();
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+
+    let res: Result<Result<Uuid, String>, JoinError> = async {
+        let task = sqlx::query!(
+            "SELECT title, creator_id FROM tasks WHERE id = $1 AND deleted_at IS NULL",
+            task_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let title = match task {
+            Some(t) if t.creator_id == Some(user_id) => t.title,
+            _ => return Ok(Err("任务不存在或无权操作".to_string())),
+        };
+        let invitee_id = match sqlx::query_scalar!(
+            "SELECT id FROM users WHERE username = $1",
+            form.username.trim()
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        {
+            Some(id) => id,
+            None => return Ok(Err("用户不存在".to_string())),
+        };
+        if invitee_id == user_id {
+            return Ok(Err("不能邀请自己".to_string()));
+        }
+
+        // 先按认领规则检查一次，名额已满或对方已在该职责中时不发出邀请
+        let slot = match membership::check_join(&mut tx, form.role_id, invitee_id).await {
+            Ok(slot) if slot.task_id == task_id => slot,
+            Ok(_) => return Ok(Err("职责不属于该任务".to_string())),
+            Err(JoinError::AlreadyHeld) => return Ok(Err("该用户已经认领了该职责".to_string())),
+            Err(e) => return Err(e),
+        };
+        let pending = sqlx::query_scalar!(
+            r#"SELECT EXISTS (SELECT 1 FROM task_invitations WHERE role_id = $1 AND invitee_id = $2 AND status = $3) as "exists!""#,
+            form.role_id,
+            invitee_id,
+            invites::INVITATION_PENDING
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if pending {
+            return Ok(Err("已邀请过该用户，请等待对方回复".to_string()));
+        }
+
+        let id = Uuid::new_v4();
+        let message = form
+            .message
+            .as_deref()
+            .map(str::trim)
+            .filter(|m| !m.is_empty());
+        sqlx::query!(
+            r#"
+            INSERT INTO task_invitations (id, task_id, role_id, inviter_id, invitee_id, message, status, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            id,
+            task_id,
+            form.role_id,
+            user_id,
+            invitee_id,
+            message,
+            invites::INVITATION_PENDING,
+            Utc::now()
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let inviter = user_display_name(&mut tx, user_id).await?;
+        let mut content = format!(
+            "{} 邀请你加入任务「{}」，担任「{}」",
+            inviter, title, slot.role_name
+        );
+        if let Some(message) = message {
+            content.push_str(&format!("：{}", message));
+        }
+        notifications::notify(
+            &mut tx,
+            invitee_id,
+            notifications::TASK_INVITATION,
+            &content,
+            Some(task_id),
+        )
+        .await?;
+        Ok(Ok(id))
+    }
+    .await;
+    match res {
+        Ok(Ok(id)) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true, "id": id}))
+        }
+        Ok(Err(message)) => {
+            tx.rollback().await.ok();
+            HttpResponse::Ok().json(json!({"success": false, "message": message}))
+        }
+        Err(JoinError::Db(e)) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::Ok().json(json!({"success": false, "message": e.message()}))
+        }
+    }
+}
+
+// 发布者查看任务发出的邀请
+pub async fn list_task_invitations(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    task_id: web::Path<Uuid>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let is_creator = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM tasks WHERE id = $1 AND creator_id = $2 AND deleted_at IS NULL) as "exists!""#,
+        *task_id,
+        user_id
+    )
+    .fetch_one(pool.get_ref())
+    .await;
+    match is_creator {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::Ok()
+                .json(json!({"success": false, "message": "任务不存在或无权操作"}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    }
+
+    let rows = sqlx::query_as::<_, InvitationDetails>(&format!(
+        "{} WHERE i.task_id = $1 ORDER BY i.created_at DESC",
+        INVITATION_SELECT
+    ))
+    .bind(*task_id)
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match rows {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+// 受邀者查看收到的邀请
+pub async fn list_my_invitations(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let rows = sqlx::query_as::<_, InvitationDetails>(&format!(
+        "{} WHERE i.invitee_id = $1 AND t.deleted_at IS NULL ORDER BY i.created_at DESC",
+        INVITATION_SELECT
+    ))
+    .bind(user_id)
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match rows {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+// 回复邀请：锁住邀请行并校验受邀者身份与邀请状态
+async fn lock_pending_invitation(
+    conn: &mut PgConnection,
+    invitation_id: Uuid,
+    invitee_id: Uuid,
+) -> Result<InvitationDetails, HttpResponse> {
+    let row = sqlx::query_as::<_, InvitationDetails>(&format!(
+        "{} WHERE i.id = $1 FOR UPDATE OF i",
+        INVITATION_SELECT
+    ))
+    .bind(invitation_id)
+    .fetch_optional(&mut *conn)
+    .await;
+    let invitation = match row {
+        Ok(Some(invitation)) => invitation,
+        Ok(None) => return Err(HttpResponse::NotFound().json("Invitation not found")),
+        Err(e) => {
+            return Err(HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()})));
+        }
+    };
+    if invitation.invitee_id != invitee_id {
+        return Err(HttpResponse::Forbidden()
+            .json(json!({"success": false, "message": "只能回复发给自己的邀请"})));
+    }
+    if invitation.status != invites::INVITATION_PENDING {
+        return Err(HttpResponse::Ok()
+            .json(json!({"success": false, "message": format!("该邀请{}", invitation.status)})));
+    }
+    Ok(invitation)
+}
+
+// 接受邀请：与认领职责相同的名额检查，通过后加入职责并通知邀请人
+pub async fn accept_invitation(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let invitation = match lock_pending_invitation(&mut tx, path.into_inner(), user_id).await {
+        Ok(invitation) => invitation,
+        Err(resp) => {
+            tx.rollback().await.ok();
+            return resp;
+        }
+    };
+
+    let res: Result<(), JoinError> = async {
+        membership::join_role(
+            &mut tx,
+            invitation.role_id,
+            invitation.invitee_id,
+            invitation.invitee_id,
+        )
+        .await?;
+        respond_invitation(&mut tx, &invitation, invites::INVITATION_ACCEPTED).await?;
+        Ok(())
+    }
+    .await;
+    match res {
+        Ok(()) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true, "task_id": invitation.task_id}))
+        }
+        Err(JoinError::Db(e)) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}))
+        }
+        // 名额已满等情况保留邀请，受邀者仍可拒绝
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::Ok().json(json!({"success": false, "message": e.message()}))
+        }
+    }
+}
+
+pub async fn decline_invitation(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let invitation = match lock_pending_invitation(&mut tx, path.into_inner(), user_id).await {
+        Ok(invitation) => invitation,
+        Err(resp) => {
+            tx.rollback().await.ok();
+            return resp;
+        }
+    };
+    match respond_invitation(&mut tx, &invitation, invites::INVITATION_DECLINED).await {
+        Ok(()) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}))
+        }
+    }
+}
+
+async fn respond_invitation(
+    conn: &mut PgConnection,
+    invitation: &InvitationDetails,
+    status: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE task_invitations SET status = $1, responded_at = $2 WHERE id = $3",
+        status,
+        Utc::now(),
+        invitation.id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let (kind, verdict) = if status == invites::INVITATION_ACCEPTED {
+        (notifications::INVITATION_ACCEPTED, "接受")
+    } else {
+        (notifications::INVITATION_DECLINED, "拒绝")
+    };
+    notifications::notify(
+        conn,
+        invitation.inviter_id,
+        kind,
+        &format!(
+            "{} {}了你对任务「{}」职责「{}」的邀请",
+            invitation.invitee_name, verdict, invitation.task_title, invitation.role_name
+        ),
+        Some(invitation.task_id),
+    )
+    .await
+}
+
+// --- Leave & Ownership Transfer Handlers ---
+
+// 成员主动退出任务：在同一事务中释放职责和子任务分配，并通知发布者
+pub async fn leave_task(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let task_id = path.into_inner();
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+
+    let res: Result<Result<(), &str>, sqlx::Error> = async {
+        let task = match sqlx::query!(
+            "SELECT title, creator_id, status FROM tasks WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+            task_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        {
+            Some(task) => task,
+            None => return Ok(Err("任务不存在")),
+        };
+        if task.creator_id == Some(user_id) {
+            return Ok(Err("发布者不能退出任务，请先转让任务"));
+        }
+        if matches!(
+            task.status.as_deref(),
+            Some(lifecycle::FINISHED | lifecycle::ARCHIVED)
+        ) {
+            return Ok(Err("任务已结束，不能退出"));
+        }
+        let released = membership::release_member(
+            &mut tx,
+            task_id,
+            user_id,
+            membership::EVENT_LEFT,
+            user_id,
+            None,
+        )
+        .await?;
+        if released == 0 {
+            return Ok(Err("你不是该任务的成员"));
+        }
+        sync_task_progress(&mut tx, task_id).await?;
+        if let Some(creator_id) = task.creator_id {
+            let name = user_display_name(&mut tx, user_id).await?;
+            notifications::notify(
+                &mut tx,
+                creator_id,
+                notifications::MEMBER_LEFT,
+                &format!("{} 退出了任务「{}」", name, task.title),
+                Some(task_id),
+            )
+            .await?;
+        }
+        Ok(Ok(()))
+    }
+    .await;
+    match res {
+        Ok(Ok(())) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true}))
+        }
+        Ok(Err(message)) => {
+            tx.rollback().await.ok();
+            HttpResponse::Ok().json(json!({"success": false, "message": message}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}))
+        }
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct OwnershipTransferDetails {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub task_title: String,
+    pub from_user_id: Uuid,
+    pub from_name: String,
+    pub to_user_id: Uuid,
+    pub to_name: String,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub responded_at: Option<DateTime<Utc>>,
+}
+
+const OWNERSHIP_TRANSFER_SELECT: &str = r#"
+    SELECT o.id, o.task_id, t.title as task_title,
+        o.from_user_id, COALESCE(NULLIF(a.name, ''), a.username) as from_name,
+        o.to_user_id, COALESCE(NULLIF(b.name, ''), b.username) as to_name,
+        o.status, o.created_at, o.responded_at
+    FROM task_ownership_transfers o
+    JOIN tasks t ON t.id = o.task_id
+    JOIN users a ON a.id = o.from_user_id
+    JOIN users b ON b.id = o.to_user_id
+"#;
+
+#[derive(Debug, Deserialize)]
+pub struct OwnershipTransferInput {
+    pub to_user_id: Uuid,
+}
+
+// 发布者发起转让，对方接受后才会成为新的发布者
+pub async fn request_ownership_transfer(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    form: web::Json<OwnershipTransferInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let task_id = path.into_inner();
+    if form.to_user_id == user_id {
+        return HttpResponse::BadRequest()
+            .json(json!({"success": false, "message": "不能转让给自己"}));
+    }
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+
+    let res: Result<Result<Uuid, &str>, sqlx::Error> = async {
+        let task = match sqlx::query!(
+            "SELECT title, creator_id FROM tasks WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+            task_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        {
+            Some(task) if task.creator_id == Some(user_id) => task,
+            _ => return Ok(Err("任务不存在或无权操作")),
+        };
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS (SELECT 1 FROM users WHERE id = $1) as "exists!""#,
+            form.to_user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if !exists {
+            return Ok(Err("用户不存在"));
+        }
+        let pending = sqlx::query_scalar!(
+            r#"SELECT EXISTS (SELECT 1 FROM task_ownership_transfers WHERE task_id = $1 AND status = $2) as "exists!""#,
+            task_id,
+            membership::TRANSFER_PENDING
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if pending {
+            return Ok(Err("已有待接受的转让，请先取消"));
+        }
+
+        let id = Uuid::new_v4();
+        sqlx::query!(
+            r#"
+            INSERT INTO task_ownership_transfers (id, task_id, from_user_id, to_user_id, status, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            id,
+            task_id,
+            user_id,
+            form.to_user_id,
+            membership::TRANSFER_PENDING,
+            Utc::now()
+        )
+        .execute(&mut *tx)
+        .await?;
+        let name = user_display_name(&mut tx, user_id).await?;
+        notifications::notify(
+            &mut tx,
+            form.to_user_id,
+            notifications::OWNERSHIP_TRANSFER,
+            &format!("{} 想将任务「{}」转让给你", name, task.title),
+            Some(task_id),
+        )
+        .await?;
+        Ok(Ok(id))
+    }
+    .await;
+    match res {
+        Ok(Ok(id)) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true, "id": id}))
+        }
+        Ok(Err(message)) => {
+            tx.rollback().await.ok();
+            HttpResponse::Ok().json(json!({"success": false, "message": message}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}))
+        }
+    }
+}
+
+// 与我相关的转让：发起的和收到的
+pub async fn list_my_ownership_transfers(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let rows = sqlx::query_as::<_, OwnershipTransferDetails>(&format!(
+        "{} WHERE (o.from_user_id = $1 OR o.to_user_id = $1) AND t.deleted_at IS NULL ORDER BY o.created_at DESC",
+        OWNERSHIP_TRANSFER_SELECT
+    ))
+    .bind(user_id)
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match rows {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+// 锁住待接受的转让。to_side 为 true 时校验接收方，否则校验发起方
+async fn lock_pending_transfer(
+    conn: &mut PgConnection,
+    transfer_id: Uuid,
+    user_id: Uuid,
+    to_side: bool,
+) -> Result<OwnershipTransferDetails, HttpResponse> {
+    let row = sqlx::query_as::<_, OwnershipTransferDetails>(&format!(
+        "{} WHERE o.id = $1 FOR UPDATE OF o, t",
+        OWNERSHIP_TRANSFER_SELECT
+    ))
+    .bind(transfer_id)
+    .fetch_optional(&mut *conn)
+    .await;
+    let transfer = match row {
+        Ok(Some(transfer)) => transfer,
+        Ok(None) => return Err(HttpResponse::NotFound().json("Transfer not found")),
+        Err(e) => {
+            return Err(HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()})));
+        }
+    };
+    let party = if to_side {
+        transfer.to_user_id
+    } else {
+        transfer.from_user_id
+    };
+    if party != user_id {
+        return Err(
+            HttpResponse::Forbidden().json(json!({"success": false, "message": "无权处理该转让"}))
+        );
+    }
+    if transfer.status != membership::TRANSFER_PENDING {
+        return Err(HttpResponse::Ok()
+            .json(json!({"success": false, "message": format!("该转让{}", transfer.status)})));
+    }
+    Ok(transfer)
+}
+
+async fn close_transfer(
+    conn: &mut PgConnection,
+    transfer_id: Uuid,
+    status: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE task_ownership_transfers SET status = $1, responded_at = $2 WHERE id = $3",
+        status,
+        Utc::now(),
+        transfer_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+// 接受转让：发起后发布者已变更时转让作废
+pub async fn accept_ownership_transfer(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let transfer = match lock_pending_transfer(&mut tx, path.into_inner(), user_id, true).await {
+        Ok(transfer) => transfer,
+        Err(resp) => {
+            tx.rollback().await.ok();
+            return resp;
+        }
+    };
+
+    let res: Result<bool, sqlx::Error> = async {
+        let moved = sqlx::query!(
+            "UPDATE tasks SET creator_id = $1 WHERE id = $2 AND creator_id = $3",
+            transfer.to_user_id,
+            transfer.task_id,
+            transfer.from_user_id
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            == 1;
+        if !moved {
+            close_transfer(&mut tx, transfer.id, membership::TRANSFER_CANCELLED).await?;
+            return Ok(false);
+        }
+        close_transfer(&mut tx, transfer.id, membership::TRANSFER_ACCEPTED).await?;
+        notifications::notify(
+            &mut tx,
+            transfer.from_user_id,
+            notifications::TRANSFER_ACCEPTED,
+            &format!(
+                "{} 已接受任务「{}」的转让",
+                transfer.to_name, transfer.task_title
+            ),
+            Some(transfer.task_id),
+        )
+        .await?;
+        Ok(true)
+    }
+    .await;
+    match res {
+        Ok(true) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true, "task_id": transfer.task_id}))
+        }
+        Ok(false) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok()
+                .json(json!({"success": false, "message": "任务发布者已变更，该转让已作废"}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}))
+        }
+    }
+}
+
+pub async fn decline_ownership_transfer(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let transfer = match lock_pending_transfer(&mut tx, path.into_inner(), user_id, true).await {
+        Ok(transfer) => transfer,
+        Err(resp) => {
+            tx.rollback().await.ok();
+            return resp;
+        }
+    };
+
+    let res: Result<(), sqlx::Error> = async {
+        close_transfer(&mut tx, transfer.id, membership::TRANSFER_DECLINED).await?;
+        notifications::notify(
+            &mut tx,
+            transfer.from_user_id,
+            notifications::TRANSFER_DECLINED,
+            &format!(
+                "{} 拒绝了任务「{}」的转让",
+                transfer.to_name, transfer.task_title
+            ),
+            Some(transfer.task_id),
+        )
+        .await
+    }
+    .await;
+    match res {
+        Ok(()) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}))
+        }
+    }
+}
+
+// 发起方取消待接受的转让
+pub async fn cancel_ownership_transfer(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let transfer = match lock_pending_transfer(&mut tx, path.into_inner(), user_id, false).await {
+        Ok(transfer) => transfer,
+        Err(resp) => {
+            tx.rollback().await.ok();
+            return resp;
+        }
+    };
+    match close_transfer(&mut tx, transfer.id, membership::TRANSFER_CANCELLED).await {
+        Ok(()) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}))
+        }
+    }
+}
+
+// --- Peer Evaluation Handlers ---
+
+#[derive(Debug, Deserialize)]
+pub struct RubricInput {
+    pub criteria: Vec<evaluation::CriterionInput>,
+}
+
+// 任务的评分标准和匿名设置。configured 为 false 时是默认标准，locked 表示已有评价、不能再修改
+pub async fn get_evaluation_rubric(
+    pool: web::Data<PgPool>,
+    task_id: web::Path<Uuid>,
+) -> HttpResponse {
+    let task_id = task_id.into_inner();
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let res = async {
+        let (criteria, configured) = evaluation::load_rubric(&mut conn, task_id).await?;
+        let settings = sqlx::query!(
+            r#"
+            SELECT evaluation_anonymous, evaluation_min_ratings,
+                EXISTS (SELECT 1 FROM evaluations WHERE task_id = $1) as "locked!"
+            FROM tasks WHERE id = $1
+            "#,
+            task_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+        Ok::<_, sqlx::Error>(settings.map(|t| {
+            json!({
+                "configured": configured,
+                "locked": t.locked,
+                "anonymous": t.evaluation_anonymous,
+                "min_ratings": t.evaluation_min_ratings,
+                "criteria": criteria
+            })
+        }))
+    }
+    .await;
+
+    match res {
+        Ok(Some(body)) => HttpResponse::Ok().json(body),
+        Ok(None) => HttpResponse::NotFound().json("Task not found"),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+// 发布者自定义评分标准，已有评价后不能修改
+pub async fn update_evaluation_rubric(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    form: web::Json<RubricInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let task_id = path.into_inner();
+    if let Some(message) = evaluation::validate_rubric(&form.criteria) {
+        return HttpResponse::BadRequest().json(json!({"success": false, "message": message}));
+    }
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+
+    let res: Result<Result<(), &str>, sqlx::Error> = async {
+        let creator_id = sqlx::query_scalar!(
+            "SELECT creator_id FROM tasks WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+            task_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        if creator_id.flatten() != Some(user_id) {
+            return Ok(Err("任务不存在或无权操作"));
+        }
+        let locked = sqlx::query_scalar!(
+            r#"SELECT EXISTS (SELECT 1 FROM evaluations WHERE task_id = $1) as "exists!""#,
+            task_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if locked {
+            return Ok(Err("已有成员提交评价，不能再修改评分标准"));
+        }
+
+        sqlx::query!(
+            "DELETE FROM evaluation_criteria WHERE task_id = $1",
+            task_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        let criteria: Vec<EvaluationCriterion> = form
+            .criteria
+            .iter()
+            .enumerate()
+            .map(|(i, c)| EvaluationCriterion {
+                id: Uuid::new_v4(),
+                task_id,
+                name: c.name.trim().to_string(),
+                description: c
+                    .description
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|d| !d.is_empty())
+                    .map(str::to_string),
+                min_score: c.min_score.unwrap_or(evaluation::DEFAULT_MIN_SCORE),
+                max_score: c.max_score.unwrap_or(evaluation::DEFAULT_MAX_SCORE),
+                position: i as i32,
+            })
+            .collect();
+        evaluation::save_rubric(&mut tx, &criteria).await?;
+        Ok(Ok(()))
+    }
+    .await;
+    match res {
+        Ok(Ok(())) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true}))
+        }
+        Ok(Err(message)) => {
+            tx.rollback().await.ok();
+            HttpResponse::Ok().json(json!({"success": false, "message": message}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EvaluationInput {
+    pub task_id: Uuid,
+    pub target_id: Uuid,
+    // 评分标准名称 -> 分数
+    pub scores: HashMap<String, i32>,
+    pub comment: Option<String>,
+}
+
+// 提交互评：任务结束后，成员对每名队友各评价一次
+pub async fn add_evaluation(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    form: web::Json<EvaluationInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let comment = form
+        .comment
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty());
+    match evaluation::submit(
+        &mut tx,
+        form.task_id,
+        user_id,
+        form.target_id,
+        &form.scores,
+        comment,
+    )
+    .await
+    {
+        Ok(id) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true, "id": id}))
+        }
+        Err(EvaluationError::Db(e)) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::Ok().json(json!({"success": false, "message": e.message()}))
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct EvaluationScore {
+    pub criterion_id: Uuid,
+    pub name: String,
+    pub score: i32,
+}
+
+// 匿名互评中非管理员看不到 id、评价人和评价时间，列表顺序随机
+#[derive(Debug, Serialize)]
+pub struct EvaluationDetails {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    pub task_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rater_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rater_name: Option<String>,
+    pub target_id: Uuid,
+    pub target_name: String,
+    pub comment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    pub scores: Vec<EvaluationScore>,
+}
+
+pub async fn list_evaluations(
+    pool: web::Data<PgPool>,
+    req: actix_web::HttpRequest,
+    task_id: web::Path<Uuid>,
+) -> HttpResponse {
+    let task_id = task_id.into_inner();
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let res = async {
+        let Some(visibility) =
+            evaluation::visibility(&mut conn, task_id, auth::request_user_id(&req)).await?
+        else {
+            return Ok(None);
+        };
+        // 受限时只返回收到评价数达到门槛的成员
+        let min_ratings = if visibility.restricted {
+            visibility.min_ratings
+        } else {
+            0
+        };
+        let evaluations = sqlx::query!(
+            r#"
+            SELECT e.id, e.task_id, e.rater_id, e.target_id, e.comment, e.created_at,
+                COALESCE(NULLIF(r.name, ''), r.username) as "rater_name!",
+                COALESCE(NULLIF(t.name, ''), t.username) as "target_name!"
+            FROM evaluations e
+            JOIN users r ON r.id = e.rater_id
+            JOIN users t ON t.id = e.target_id
+            WHERE e.task_id = $1
+                AND (SELECT COUNT(*) FROM evaluations c
+                    WHERE c.task_id = e.task_id AND c.target_id = e.target_id) >= $2
+            ORDER BY CASE WHEN $3 THEN random() END, e.created_at DESC
+            "#,
+            task_id,
+            min_ratings,
+            visibility.restricted
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        let scores = sqlx::query!(
+            r#"
+            SELECT s.evaluation_id, s.criterion_id, c.name, s.score
+            FROM evaluation_scores s
+            JOIN evaluation_criteria c ON c.id = s.criterion_id
+            WHERE c.task_id = $1
+            ORDER BY c.position ASC
+            "#,
+            task_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let reveal = !visibility.restricted;
+        let list: Vec<EvaluationDetails> = evaluations
+            .into_iter()
+            .map(|e| EvaluationDetails {
+                scores: scores
+                    .iter()
+                    .filter(|s| s.evaluation_id == e.id)
+                    .map(|s| EvaluationScore {
+                        criterion_id: s.criterion_id,
+                        name: s.name.clone(),
+                        score: s.score,
+                    })
+                    .collect(),
+                id: reveal.then_some(e.id),
+                task_id: e.task_id,
+                rater_id: reveal.then_some(e.rater_id),
+                rater_name: reveal.then_some(e.rater_name),
+                target_id: e.target_id,
+                target_name: e.target_name,
+                comment: e.comment,
+                created_at: reveal.then_some(e.created_at),
+            })
+            .collect();
+        Ok::<_, sqlx::Error>(Some(list))
+    }
+    .await;
+
+    match res {
+        Ok(Some(list)) => HttpResponse::Ok().json(list),
+        Ok(None) => HttpResponse::NotFound().json("Task not found"),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+// 按成员汇总的互评结果，匿名互评中评价数不足的成员不公开分数
+pub async fn get_evaluation_summary(
+    pool: web::Data<PgPool>,
+    req: actix_web::HttpRequest,
+    task_id: web::Path<Uuid>,
+) -> HttpResponse {
+    let task_id = task_id.into_inner();
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let res = async {
+        let Some(visibility) =
+            evaluation::visibility(&mut conn, task_id, auth::request_user_id(&req)).await?
+        else {
+            return Ok(None);
+        };
+        let mut list = evaluation::summarize(&mut conn, task_id).await?;
+        if visibility.restricted {
+            evaluation::apply_k_anonymity(&mut list, visibility.min_ratings);
+        }
+        Ok::<_, sqlx::Error>(Some(list))
+    }
+    .await;
+
+    match res {
+        Ok(Some(list)) => HttpResponse::Ok().json(list),
+        Ok(None) => HttpResponse::NotFound().json("Task not found"),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EvaluationSettingsInput {
+    pub anonymous: bool,
+    pub min_ratings: i32,
+}
+
+// 设置匿名互评。已有评价后不能关闭匿名，也不能降低公开门槛
+pub async fn update_evaluation_settings(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    form: web::Json<EvaluationSettingsInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let task_id = path.into_inner();
+    if !evaluation::MIN_RATINGS_RANGE.contains(&form.min_ratings) {
+        return HttpResponse::BadRequest().json(json!({
+            "success": false,
+            "message": format!(
+                "公开门槛需在 {} 到 {} 条评价之间",
+                evaluation::MIN_RATINGS_RANGE.start(),
+                evaluation::MIN_RATINGS_RANGE.end()
+            )
+        }));
+    }
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+
+    let res: Result<Result<(), &str>, sqlx::Error> = async {
+        let task = sqlx::query!(
+            r#"
+            SELECT creator_id, evaluation_anonymous, evaluation_min_ratings,
+                EXISTS (SELECT 1 FROM evaluations WHERE task_id = tasks.id) as "locked!"
+            FROM tasks WHERE id = $1 AND deleted_at IS NULL FOR UPDATE
+            "#,
+            task_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(task) = task.filter(|t| t.creator_id == Some(user_id)) else {
+            return Ok(Err("任务不存在或无权操作"));
+        };
+        if task.locked && task.evaluation_anonymous && !form.anonymous {
+            return Ok(Err("已有成员提交评价，不能关闭匿名互评"));
+        }
+        if task.locked && form.min_ratings < task.evaluation_min_ratings {
+            return Ok(Err("已有成员提交评价，不能降低公开门槛"));
+        }
+        sqlx::query!(
+            "UPDATE tasks SET evaluation_anonymous = $2, evaluation_min_ratings = $3 WHERE id = $1",
+            task_id,
+            form.anonymous,
+            form.min_ratings
+        )
+        .execute(&mut *tx)
+        .await?;
+        Ok(Ok(()))
+    }
+    .await;
+    match res {
+        Ok(Ok(())) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            HttpResponse::Ok().json(json!({"success": true}))
+        }
+        Ok(Err(message)) => {
+            tx.rollback().await.ok();
+            HttpResponse::Ok().json(json!({"success": false, "message": message}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}))
+        }
+    }
+}
+
+// 当前用户需要评价的队友，evaluated 表示已经评价过。
+// 只回答登录用户本人，否则可以轮询他人的 evaluated 推断匿名评价的评价人
+pub async fn list_evaluation_targets(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    task_id: web::Path<Uuid>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let task_id = task_id.into_inner();
+    let rows = sqlx::query!(
+        r#"
+        SELECT u.id as user_id, u.name, u.username,
+            EXISTS (
+                SELECT 1 FROM evaluations e
+                WHERE e.task_id = $1 AND e.rater_id = $2 AND e.target_id = u.id
+            ) as "evaluated!"
+        FROM users u
+        WHERE u.id <> $2
+            AND u.id IN (SELECT user_id FROM task_membership_events WHERE task_id = $1 AND event = $3)
+        ORDER BY u.username
+        "#,
+        task_id,
+        user_id,
+        membership::EVENT_JOINED
+    )
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match rows {
+        Ok(rows) => HttpResponse::Ok().json(
+            rows.into_iter()
+                .map(|r| {
+                    json!({
+                        "user_id": r.user_id,
+                        "name": r.name,
+                        "username": r.username,
+                        "evaluated": r.evaluated
+                    })
+                })
+                .collect::<Vec<_>>(),
+        ),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+// --- Contribution Handlers ---
+
+// 发布者和管理员（教师）可以查看、配置任务的贡献度。user_id 需取自登录 token
+async fn can_grade_task(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    user_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (SELECT 1 FROM tasks WHERE id = $1 AND creator_id = $2)
+            OR EXISTS (SELECT 1 FROM users WHERE id = $2 AND is_admin) as "allowed!"
+        "#,
+        task_id,
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await
+}
+
+pub async fn get_contribution_weights(
+    pool: web::Data<PgPool>,
+    task_id: web::Path<Uuid>,
+) -> HttpResponse {
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    match contribution::load_weights(&mut conn, task_id.into_inner()).await {
+        Ok((weights, configured)) => {
+            HttpResponse::Ok().json(json!({"configured": configured, "weights": weights}))
+        }
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ContributionWeightsInput {
+    #[serde(flatten)]
+    pub weights: contribution::ContributionWeights,
+}
+
+// 发布者或管理员配置贡献度权重
+pub async fn update_contribution_weights(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    form: web::Json<ContributionWeightsInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let task_id = path.into_inner();
+    if let Some(message) = form.weights.validate() {
+        return HttpResponse::BadRequest().json(json!({"success": false, "message": message}));
+    }
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let res: Result<Result<(), &str>, sqlx::Error> = async {
+        if !can_grade_task(&mut conn, task_id, user_id).await? {
+            return Ok(Err("任务不存在或无权操作"));
+        }
+        contribution::save_weights(&mut conn, task_id, &form.weights).await?;
+        Ok(Ok(()))
+    }
+    .await;
+    match res {
+        Ok(Ok(())) => HttpResponse::Ok().json(json!({"success": true})),
+        Ok(Err(message)) => HttpResponse::Ok().json(json!({"success": false, "message": message})),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ContributionQuery {
+    // json | csv | xlsx，默认 json
+    pub format: Option<String>,
+}
+
+// 按 format 返回 JSON 或导出文件
+fn contribution_response(
+    rows: Vec<contribution::MemberContribution>,
+    format: Option<&str>,
+    file_stem: &str,
+) -> HttpResponse {
+    let (bytes, ext, content_type) = match format.unwrap_or("json") {
+        "json" => return HttpResponse::Ok().json(rows),
+        "csv" => (
+            contribution::to_csv(&rows).into_bytes(),
+            "csv",
+            "text/csv; charset=utf-8",
+        ),
+        "xlsx" => match contribution::to_xlsx(&rows) {
+            Ok(bytes) => (
+                bytes,
+                "xlsx",
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            ),
+            Err(e) => {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+        },
+        _ => {
+            return HttpResponse::BadRequest()
+                .json(json!({"success": false, "message": "format 只支持 json、csv 或 xlsx"}));
+        }
+    };
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header((
+            "Content-Disposition",
+            format!(
+                "attachment; filename=\"{}-{}.{}\"",
+                file_stem,
+                Utc::now().format("%Y%m%d"),
+                ext
+            ),
+        ))
+        .body(bytes)
+}
+
+// 任务每名成员的贡献度，查看人需为发布者或管理员
+pub async fn get_task_contributions(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    query: web::Query<ContributionQuery>,
+) -> HttpResponse {
+    let task_id = path.into_inner();
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let res: Result<Result<Option<_>, &str>, sqlx::Error> = async {
+        if !can_grade_task(&mut conn, task_id, user_id).await? {
+            return Ok(Err("无权查看该任务的贡献度"));
+        }
+        Ok(Ok(contribution::compute(&mut conn, task_id, user_id).await?))
+    }
+    .await;
+    match res {
+        Ok(Ok(Some(rows))) => contribution_response(rows, query.format.as_deref(), "contributions"),
+        Ok(Ok(None)) => HttpResponse::NotFound().json("Task not found"),
+        Ok(Err(message)) => {
+            HttpResponse::Forbidden().json(json!({"success": false, "message": message}))
+        }
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+// 已设置的课程及其任务数
+pub async fn list_courses(pool: web::Data<PgPool>) -> HttpResponse {
+    let rows = sqlx::query!(
+        r#"
+        SELECT course as "course!", COUNT(*) as "task_count!"
+        FROM tasks
+        WHERE course IS NOT NULL AND deleted_at IS NULL
+        GROUP BY course
+        ORDER BY course
+        "#
+    )
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match rows {
+        Ok(rows) => HttpResponse::Ok().json(
+            rows.into_iter()
+                .map(|r| json!({"course": r.course, "task_count": r.task_count}))
+                .collect::<Vec<_>>(),
+        ),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+// 课程下所有任务的贡献度，只有管理员（教师）可以查看
+pub async fn get_course_contributions(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    query: web::Query<ContributionQuery>,
+) -> HttpResponse {
+    let course = path.into_inner();
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let res: Result<Option<Vec<_>>, sqlx::Error> = async {
+        let is_admin = sqlx::query_scalar!("SELECT is_admin FROM users WHERE id = $1", user_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .unwrap_or(false);
+        if !is_admin {
+            return Ok(None);
+        }
+        let task_ids = sqlx::query_scalar!(
+            "SELECT id FROM tasks WHERE course = $1 AND deleted_at IS NULL ORDER BY created_at",
+            course
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        let mut rows = Vec::new();
+        for task_id in task_ids {
+            rows.extend(
+                contribution::compute(&mut conn, task_id, user_id)
+                    .await?
+                    .unwrap_or_default(),
+            );
+        }
+        Ok(Some(rows))
+    }
+    .await;
+    match res {
+        Ok(Some(rows)) => {
+            contribution_response(rows, query.format.as_deref(), "course-contributions")
+        }
+        Ok(None) => HttpResponse::Forbidden()
+            .json(json!({"success": false, "message": "只有管理员可以导出课程成绩"})),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+// --- Notification Handlers ---
+
+#[derive(Debug, Deserialize)]
+pub struct NotificationQuery {
+    #[serde(default)]
+    pub unread_only: bool,
+}
+
+pub async fn list_notifications(
+    pool: web::Data<PgPool>,
+    user_id: web::Path<Uuid>,
+    query: web::Query<NotificationQuery>,
+) -> HttpResponse {
+    let rows = sqlx::query_as::<_, Notification>(
+        r#"
+        SELECT * FROM notifications
+        WHERE user_id = $1 AND (NOT $2 OR read_at IS NULL)
+        ORDER BY created_at DESC
+        LIMIT 100
+        "#,
+    )
+    .bind(*user_id)
+    .bind(query.unread_only)
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match rows {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+pub async fn mark_notification_read(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    form: web::Json<TaskOwnerInput>,
+) -> HttpResponse {
+    let res = sqlx::query!(
+        "UPDATE notifications SET read_at = COALESCE(read_at, NOW()) WHERE id = $1 AND user_id = $2",
+        path.into_inner(),
+        form.user_id
+    )
+    .execute(pool.get_ref())
+    .await;
+
+    match res {
+        Ok(r) if r.rows_affected() == 1 => HttpResponse::Ok().json(json!({"success": true})),
+        Ok(_) => HttpResponse::NotFound().json("Notification not found"),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+pub async fn mark_all_notifications_read(
+    pool: web::Data<PgPool>,
+    form: web::Json<TaskOwnerInput>,
+) -> HttpResponse {
+    let res = sqlx::query!(
+        "UPDATE notifications SET read_at = NOW() WHERE user_id = $1 AND read_at IS NULL",
+        form.user_id
+    )
+    .execute(pool.get_ref())
+    .await;
+
+    match res {
+        Ok(_) => HttpResponse::Ok().json(json!({"success": true})),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+// --- Direct Message Handlers ---
+
+// 私信接口的当前用户一律取自登录 token，不接受客户端传入的 user_id
+
+#[derive(Debug, Deserialize)]
+pub struct DirectMessageInput {
+    pub to_user_id: Uuid,
+    pub content: String,
+}
+
+// 发送私信，并通过 WebSocket 推送给双方在线的连接
+pub async fn send_direct_message(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    ws_server: web::Data<Addr<ws::WsServer>>,
+    form: web::Json<DirectMessageInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    match direct_messages::send(&mut tx, user_id, form.to_user_id, &form.content).await {
+        Ok(message) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            ws::push(
+                &ws_server,
+                vec![user_id, form.to_user_id],
+                json!({"type": "direct_message", "direct_message": &message}),
+            );
+            HttpResponse::Ok().json(json!({"success": true, "direct_message": message}))
+        }
+        Err(DirectMessageError::Db(e)) => {
+            tx.rollback().await.ok();
+            HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            HttpResponse::Ok().json(json!({"success": false, "message": e.message()}))
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DmThreadSummary {
+    pub id: Uuid,
+    pub peer_id: Uuid,
+    pub peer_username: String,
+    pub peer_name: Option<String>,
+    pub peer_avatar_url: Option<String>,
+    pub last_message: Option<String>,
+    pub last_sender_id: Option<Uuid>,
+    pub last_message_at: DateTime<Utc>,
+    pub unread_count: i64,
+    // 当前用户是否屏蔽了对方
+    pub blocked: bool,
+}
+
+// 用户的私信会话列表，最近有消息的在前
+pub async fn list_dm_threads(req: actix_web::HttpRequest, pool: web::Data<PgPool>) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let rows = sqlx::query!(
+        r#"
+        SELECT th.id, th.last_message_at,
+            o.id as peer_id, o.username as peer_username, o.name as peer_name,
+            o.avatar_url as peer_avatar_url,
+            lm.content as "last_message?", lm.sender_id as "last_sender_id?",
+            (SELECT COUNT(*) FROM direct_messages d
+                WHERE d.thread_id = th.id AND d.sender_id <> $1
+                    AND d.created_at > COALESCE(r.last_read_at, '-infinity')) as "unread_count!",
+            EXISTS (SELECT 1 FROM user_blocks b
+                WHERE b.blocker_id = $1 AND b.blocked_id = o.id) as "blocked!"
+        FROM dm_threads th
+        JOIN users o ON o.id = CASE WHEN th.user_a = $1 THEN th.user_b ELSE th.user_a END
+        LEFT JOIN dm_thread_reads r ON r.thread_id = th.id AND r.user_id = $1
+        LEFT JOIN LATERAL (
+            SELECT content, sender_id FROM direct_messages
+            WHERE thread_id = th.id ORDER BY created_at DESC, id DESC LIMIT 1
+        ) lm ON TRUE
+        WHERE $1 IN (th.user_a, th.user_b)
+        ORDER BY th.last_message_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match rows {
+        Ok(rows) => HttpResponse::Ok().json(
+            rows.into_iter()
+                .map(|r| DmThreadSummary {
+                    id: r.id,
+                    peer_id: r.peer_id,
+                    peer_username: r.peer_username,
+                    peer_name: r.peer_name,
+                    peer_avatar_url: r.peer_avatar_url,
+                    last_message: r.last_message,
+                    last_sender_id: r.last_sender_id,
+                    last_message_at: r.last_message_at,
+                    unread_count: r.unread_count,
+                    blocked: r.blocked,
+                })
+                .collect::<Vec<_>>(),
+        ),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DmHistoryQuery {
+    // 上一页返回的 next_cursor，格式为 <created_at 微秒>_<id>
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DirectMessagePage {
+    pub items: Vec<DirectMessage>,
+    pub next_cursor: Option<String>,
+}
+
+const DM_PAGE_DEFAULT_LIMIT: i64 = 30;
+const DM_PAGE_MAX_LIMIT: i64 = 100;
+
+// 会话的历史私信，从新到旧分页，只有会话双方可以查看
+pub async fn list_direct_messages(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    thread_id: web::Path<Uuid>,
+    query: web::Query<DmHistoryQuery>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let thread_id = thread_id.into_inner();
+    let limit = query
+        .limit
+        .unwrap_or(DM_PAGE_DEFAULT_LIMIT)
+        .clamp(1, DM_PAGE_MAX_LIMIT);
+    let cursor = match query.cursor.as_deref() {
+        None | Some("") => None,
+        Some(c) => match TaskCursor::decode(c, false) {
+            Some(TaskCursor::Created(created_at, id)) => Some((created_at, id)),
+            _ => {
+                return HttpResponse::BadRequest()
+                    .json(json!({"success": false, "message": "cursor 无效"}));
+            }
+        },
+    };
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let res: Result<Option<DirectMessagePage>, sqlx::Error> = async {
+        if direct_messages::thread_peer(&mut conn, thread_id, user_id)
+            .await?
+            .is_none()
+        {
+            return Ok(None);
+        }
+        let (before_at, before_id) = cursor.unzip();
+        let mut items = sqlx::query_as!(
+            DirectMessage,
+            r#"
+            SELECT id, thread_id, sender_id, content, created_at
+            FROM direct_messages
+            WHERE thread_id = $1 AND ($2::TIMESTAMPTZ IS NULL OR (created_at, id) < ($2, $3))
+            ORDER BY created_at DESC, id DESC
+            LIMIT $4
+            "#,
+            thread_id,
+            before_at,
+            before_id,
+            limit + 1
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        let next_cursor = if items.len() as i64 > limit {
+            items.truncate(limit as usize);
+            items
+                .last()
+                .map(|m| TaskCursor::Created(m.created_at, m.id).encode())
+        } else {
+            None
+        };
+        Ok(Some(DirectMessagePage { items, next_cursor }))
+    }
+    .await;
+
+    match res {
+        Ok(Some(page)) => HttpResponse::Ok().json(page),
+        Ok(None) => HttpResponse::NotFound().json("Thread not found"),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+// 把会话标记为已读
+pub async fn mark_dm_thread_read(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    thread_id: web::Path<Uuid>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let thread_id = thread_id.into_inner();
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let res: Result<bool, sqlx::Error> = async {
+        if direct_messages::thread_peer(&mut conn, thread_id, user_id)
+            .await?
+            .is_none()
+        {
+            return Ok(false);
+        }
+        direct_messages::mark_read(&mut conn, thread_id, user_id).await?;
+        Ok(true)
+    }
+    .await;
+
+    match res {
+        Ok(true) => HttpResponse::Ok().json(json!({"success": true})),
+        Ok(false) => HttpResponse::NotFound().json("Thread not found"),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+// 所有会话的未读私信总数
+pub async fn get_dm_unread_count(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    match direct_messages::unread_count(&mut conn, user_id).await {
+        Ok(unread) => HttpResponse::Ok().json(json!({"unread": unread})),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BlockInput {
+    pub blocked_id: Uuid,
+}
+
+// 屏蔽用户，被屏蔽的用户不能再给自己发私信
+pub async fn block_user(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    form: web::Json<BlockInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    if user_id == form.blocked_id {
+        return HttpResponse::Ok().json(json!({"success": false, "message": "不能屏蔽自己"}));
+    }
+    let res = sqlx::query!(
+        r#"
+        INSERT INTO user_blocks (blocker_id, blocked_id, created_at)
+        SELECT $1, id, $3 FROM users WHERE id = $2
+        ON CONFLICT (blocker_id, blocked_id) DO NOTHING
+        "#,
+        user_id,
+        form.blocked_id,
+        Utc::now()
+    )
+    .execute(pool.get_ref())
+    .await;
+
+    match res {
+        Ok(_) => HttpResponse::Ok().json(json!({"success": true})),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+pub async fn unblock_user(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    blocked_id: web::Path<Uuid>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let res = sqlx::query!(
+        "DELETE FROM user_blocks WHERE blocker_id = $1 AND blocked_id = $2",
+        user_id,
+        *blocked_id
+    )
+    .execute(pool.get_ref())
+    .await;
+
+    match res {
+        Ok(r) if r.rows_affected() == 1 => HttpResponse::Ok().json(json!({"success": true})),
+        Ok(_) => HttpResponse::NotFound().json("Block not found"),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlockedUser {
+    pub user_id: Uuid,
+    pub username: String,
+    pub name: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+// 用户屏蔽的人
+pub async fn list_blocked_users(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let rows = sqlx::query_as!(
+        BlockedUser,
+        r#"
+        SELECT u.id as user_id, u.username, u.name, b.created_at
+        FROM user_blocks b JOIN users u ON u.id = b.blocked_id
+        WHERE b.blocker_id = $1
+        ORDER BY b.created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match rows {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+    }
+}
+
+// --- Task Chat Handlers ---
+
+fn chat_error_response(e: ChatError) -> HttpResponse {
+    match e {
+        ChatError::Db(e) => HttpResponse::InternalServerError()
+            .json(json!({"success": false, "message": e.to_string()})),
+        e => HttpResponse::Ok().json(json!({"success": false, "message": e.message()})),
+    }
+}
+
+// 提交修改，并把消息的最新状态推送给聊天室成员。
+// change 为 created / edited / deleted / reaction；回复的增删会改变根消息的回复数，根消息以 reply 一并推送
+async fn publish_chat_change(
+    mut tx: sqlx::Transaction<'_, Postgres>,
+    ws_server: &Addr<ws::WsServer>,
+    message_id: Uuid,
+    change: &str,
+) -> HttpResponse {
+    let res: Result<(Vec<Uuid>, Vec<ChatMessage>), ChatError> = async {
+        let mut messages = task_chat::load(&mut tx, &[message_id]).await?;
+        let (task_id, parent_id) = match messages.first() {
+            Some(m) => (m.task_id, m.parent_id),
+            None => return Err(ChatError::MessageNotFound),
+        };
+        if let Some(parent_id) = parent_id.filter(|_| change == "created" || change == "deleted") {
+            messages.extend(task_chat::load(&mut tx, &[parent_id]).await?);
+        }
+        let members = task_chat::room_members(&mut tx, task_id).await?;
+        Ok((members, messages))
+    }
+    .await;
+
+    match res {
+        Ok((members, messages)) => {
+            if let Err(e) = tx.commit().await {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"success": false, "message": e.to_string()}));
+            }
+            let mut messages = messages.into_iter();
+            let message = messages.next();
+            ws::push(
+                ws_server,
+                members.clone(),
+                json!({"type": "chat_message", "change": change, "chat_message": &message}),
+            );
+            for parent in messages {
+                ws::push(
+                    ws_server,
+                    members.clone(),
+                    json!({"type": "chat_message", "change": "reply", "chat_message": parent}),
+                );
+            }
+            HttpResponse::Ok().json(json!({"success": true, "chat_message": message}))
+        }
+        Err(e) => {
+            tx.rollback().await.ok();
+            chat_error_response(e)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatMessageInput {
+    pub content: String,
+    // 回复的消息
+    pub parent_id: Option<Uuid>,
+}
+
+pub async fn post_task_message(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    ws_server: web::Data<Addr<ws::WsServer>>,
+    task_id: web::Path<Uuid>,
+    form: web::Json<ChatMessageInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    match task_chat::post(&mut tx, *task_id, user_id, &form.content, form.parent_id).await {
+        Ok(id) => publish_chat_change(tx, &ws_server, id, "created").await,
+        Err(e) => {
+            tx.rollback().await.ok();
+            chat_error_response(e)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatHistoryQuery {
+    // 上一页返回的 next_cursor，格式为 <created_at 微秒>_<id>
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChatMessagePage {
+    pub items: Vec<ChatMessage>,
+    pub next_cursor: Option<String>,
+}
+
+const CHAT_PAGE_DEFAULT_LIMIT: i64 = 30;
+const CHAT_PAGE_MAX_LIMIT: i64 = 100;
+
+// 聊天室的消息（不含回复），从新到旧分页，只有任务成员可以查看
+pub async fn list_task_messages(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    task_id: web::Path<Uuid>,
+    query: web::Query<ChatHistoryQuery>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let task_id = task_id.into_inner();
+    let limit = query
+        .limit
+        .unwrap_or(CHAT_PAGE_DEFAULT_LIMIT)
+        .clamp(1, CHAT_PAGE_MAX_LIMIT);
+    let cursor = match query.cursor.as_deref() {
+        None | Some("") => None,
+        Some(c) => match TaskCursor::decode(c, false) {
+            Some(TaskCursor::Created(created_at, id)) => Some((created_at, id)),
+            _ => {
+                return HttpResponse::BadRequest()
+                    .json(json!({"success": false, "message": "cursor 无效"}));
+            }
+        },
+    };
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    let res: Result<ChatMessagePage, ChatError> = async {
+        task_chat::check_member(&mut conn, task_id, user_id).await?;
+        let (before_at, before_id) = cursor.unzip();
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, created_at FROM task_messages
+            WHERE task_id = $1 AND parent_id IS NULL
+                AND ($2::TIMESTAMPTZ IS NULL OR (created_at, id) < ($2, $3))
+            ORDER BY created_at DESC, id DESC
+            LIMIT $4
+            "#,
+            task_id,
+            before_at,
+            before_id,
+            limit + 1
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        let next_cursor = if rows.len() as i64 > limit {
+            rows.get(limit as usize - 1)
+                .map(|r| TaskCursor::Created(r.created_at, r.id).encode())
+        } else {
+            None
+        };
+        let ids: Vec<Uuid> = rows.iter().take(limit as usize).map(|r| r.id).collect();
+        let items = task_chat::load(&mut conn, &ids).await?;
+        Ok(ChatMessagePage { items, next_cursor })
+    }
+    .await;
+
+    match res {
+        Ok(page) => HttpResponse::Ok().json(page),
+        Err(e) => chat_error_response(e),
+    }
+}
+
+pub async fn list_task_message_replies(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    message_id: web::Path<Uuid>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    match task_chat::replies(&mut conn, *message_id, user_id).await {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => chat_error_response(e),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatEditInput {
+    pub content: String,
+}
+
+pub async fn edit_task_message(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    ws_server: web::Data<Addr<ws::WsServer>>,
+    message_id: web::Path<Uuid>,
+    form: web::Json<ChatEditInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let message_id = message_id.into_inner();
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    match task_chat::edit(&mut tx, message_id, user_id, &form.content).await {
+        Ok(_) => publish_chat_change(tx, &ws_server, message_id, "edited").await,
+        Err(e) => {
+            tx.rollback().await.ok();
+            chat_error_response(e)
+        }
+    }
+}
+
+pub async fn delete_task_message(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    ws_server: web::Data<Addr<ws::WsServer>>,
+    message_id: web::Path<Uuid>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let message_id = message_id.into_inner();
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    match task_chat::delete(&mut tx, message_id, user_id).await {
+        Ok(_) => publish_chat_change(tx, &ws_server, message_id, "deleted").await,
+        Err(e) => {
+            tx.rollback().await.ok();
+            chat_error_response(e)
+        }
+    }
+}
+
+pub async fn get_task_message_edits(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    message_id: web::Path<Uuid>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    match task_chat::edit_history(&mut conn, *message_id, user_id).await {
+        Ok(list) => HttpResponse::Ok().json(list),
+        Err(e) => chat_error_response(e),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReactionInput {
+    pub emoji: String,
+}
+
+async fn set_task_message_reaction(
+    pool: &PgPool,
+    ws_server: &Addr<ws::WsServer>,
+    message_id: Uuid,
+    user_id: Uuid,
+    input: &ReactionInput,
+    add: bool,
+) -> HttpResponse {
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"success": false, "message": e.to_string()}));
+        }
+    };
+    match task_chat::react(&mut tx, message_id, user_id, &input.emoji, add).await {
+        Ok(_) => publish_chat_change(tx, ws_server, message_id, "reaction").await,
+        Err(e) => {
+            tx.rollback().await.ok();
+            chat_error_response(e)
+        }
+    }
+}
+
+pub async fn add_task_message_reaction(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    ws_server: web::Data<Addr<ws::WsServer>>,
+    message_id: web::Path<Uuid>,
+    form: web::Json<ReactionInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    set_task_message_reaction(&pool, &ws_server, *message_id, user_id, &form, true).await
+}
+
+pub async fn remove_task_message_reaction(
+    req: actix_web::HttpRequest,
+    pool: web::Data<PgPool>,
+    ws_server: web::Data<Addr<ws::WsServer>>,
+    message_id: web::Path<Uuid>,
+    query: web::Query<ReactionInput>,
+) -> HttpResponse {
+    let Some(user_id) = auth::request_user_id(&req) else {
+        return HttpResponse::Unauthorized().json("Missing token");
+    };
+    set_task_message_reaction(&pool, &ws_server, *message_id, user_id, &query, false).await
+}
@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use serde::{Deserialize, Serialize};
+use std::env;
+use uuid::Uuid;
+
+const INVITE_KIND: &str = "task_invite";
+
+// 邀请链接有效期（小时）
+pub const INVITE_DEFAULT_HOURS: i64 = 72;
+pub const INVITE_MAX_HOURS: i64 = 24 * 30;
+
+// 直接邀请状态
+pub const INVITATION_PENDING: &str = "待回复";
+pub const INVITATION_ACCEPTED: &str = "已接受";
+pub const INVITATION_DECLINED: &str = "已拒绝";
+
+// 邀请链接中的签名内容。jti 对应 task_invite_links 中的记录，用于撤销。
+// kind 用来区分登录 token，登录 token 不能当作邀请使用。
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InviteClaims {
+    pub kind: String,
+    pub jti: Uuid,
+    pub task_id: Uuid,
+    pub role_id: Option<Uuid>,
+    pub exp: usize,
+}
+
+pub fn sign_invite(
+    link_id: Uuid,
+    task_id: Uuid,
+    role_id: Option<Uuid>,
+    expires_at: DateTime<Utc>,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let claims = InviteClaims {
+        kind: INVITE_KIND.to_string(),
+        jti: link_id,
+        task_id,
+        role_id,
+        exp: expires_at.timestamp() as usize,
+    };
+    let secret = env::var("JWT_SECRET").unwrap();
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret.as_ref()),
+    )
+}
+
+// 校验签名和有效期，返回 None 表示链接无效或已过期
+pub fn verify_invite(token: &str) -> Option<InviteClaims> {
+    let secret = env::var("JWT_SECRET").unwrap();
+    let mut validation = Validation::default();
+    validation.leeway = 0;
+    decode::<InviteClaims>(
+        token,
+        &DecodingKey::from_secret(secret.as_ref()),
+        &validation,
+    )
+    .ok()
+    .map(|data| data.claims)
+    .filter(|claims| claims.kind == INVITE_KIND)
+}
@@ -0,0 +1,175 @@
+use chrono::Utc;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+pub const DRAFT: &str = "草稿";
+pub const RECRUITING: &str = "招募中";
+pub const IN_PROGRESS: &str = "进行中";
+pub const IN_REVIEW: &str = "审核中";
+pub const FINISHED: &str = "已结束";
+pub const ARCHIVED: &str = "已归档";
+pub const REOPENED: &str = "重新开启";
+
+pub const ALL_STATUSES: [&str; 7] = [
+    DRAFT,
+    RECRUITING,
+    IN_PROGRESS,
+    IN_REVIEW,
+    FINISHED,
+    ARCHIVED,
+    REOPENED,
+];
+
+// 出现在任务广场 list_tasks 中的状态
+pub const LISTED_STATUSES: [&str; 4] = [RECRUITING, IN_PROGRESS, IN_REVIEW, REOPENED];
+
+// 新建任务时允许的初始状态
+pub const INITIAL_STATUSES: [&str; 3] = [DRAFT, RECRUITING, IN_PROGRESS];
+
+pub fn can_transition(from: &str, to: &str) -> bool {
+    matches!(
+        (from, to),
+        (DRAFT, RECRUITING | IN_PROGRESS | FINISHED)
+            | (RECRUITING, DRAFT | IN_PROGRESS | FINISHED)
+            | (IN_PROGRESS | REOPENED, IN_REVIEW | FINISHED)
+            | (IN_REVIEW, IN_PROGRESS | FINISHED)
+            | (FINISHED, ARCHIVED | REOPENED)
+            | (ARCHIVED, FINISHED)
+    )
+}
+
+pub enum TransitionError {
+    NotFound,
+    Forbidden,
+    Invalid { from: String, to: String },
+    Db(sqlx::Error),
+}
+
+impl From<sqlx::Error> for TransitionError {
+    fn from(e: sqlx::Error) -> Self {
+        TransitionError::Db(e)
+    }
+}
+
+impl TransitionError {
+    pub fn message(&self) -> String {
+        match self {
+            TransitionError::NotFound => "任务不存在".to_string(),
+            TransitionError::Forbidden => "只有任务发布者可以修改任务状态".to_string(),
+            TransitionError::Invalid { from, to } => {
+                format!("任务状态不能从「{}」变为「{}」", from, to)
+            }
+            TransitionError::Db(e) => e.to_string(),
+        }
+    }
+}
+
+// 校验并执行状态变更，写入历史并处理副作用。返回变更前的状态。
+// actor 为操作人，需为任务发布者。
+pub async fn transition_task(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    to: &str,
+    actor: Uuid,
+    note: Option<&str>,
+) -> Result<String, TransitionError> {
+    let task = sqlx::query!(
+        "SELECT creator_id, status FROM tasks WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+        task_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(TransitionError::NotFound)?;
+
+    if task.creator_id != Some(actor) {
+        return Err(TransitionError::Forbidden);
+    }
+    let from = task.status.unwrap_or_else(|| IN_PROGRESS.to_string());
+    if !can_transition(&from, to) {
+        return Err(TransitionError::Invalid {
+            from,
+            to: to.to_string(),
+        });
+    }
+
+    sqlx::query!("UPDATE tasks SET status = $1 WHERE id = $2", to, task_id)
+        .execute(&mut *conn)
+        .await?;
+
+    // 开始工作后不再招人：关闭所有未满的职责
+    if to == IN_PROGRESS && (from == DRAFT || from == RECRUITING) {
+        sqlx::query!(
+            r#"
+            UPDATE task_roles tr SET closed = TRUE
+            WHERE tr.task_id = $1
+                AND (SELECT COUNT(*) FROM task_role_members m WHERE m.role_id = tr.id) < tr.capacity
+            "#,
+            task_id
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO task_status_history (id, task_id, from_status, to_status, changed_by, note, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        Uuid::new_v4(),
+        task_id,
+        from,
+        to,
+        actor,
+        note,
+        Utc::now()
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 允许的状态变更，其余组合（包括变为自身）都应被拒绝
+    const ALLOWED: [(&str, &str); 15] = [
+        (DRAFT, RECRUITING),
+        (DRAFT, IN_PROGRESS),
+        (DRAFT, FINISHED),
+        (RECRUITING, DRAFT),
+        (RECRUITING, IN_PROGRESS),
+        (RECRUITING, FINISHED),
+        (IN_PROGRESS, IN_REVIEW),
+        (IN_PROGRESS, FINISHED),
+        (REOPENED, IN_REVIEW),
+        (REOPENED, FINISHED),
+        (IN_REVIEW, IN_PROGRESS),
+        (IN_REVIEW, FINISHED),
+        (FINISHED, ARCHIVED),
+        (FINISHED, REOPENED),
+        (ARCHIVED, FINISHED),
+    ];
+
+    #[test]
+    fn transition_table() {
+        for from in ALL_STATUSES {
+            for to in ALL_STATUSES {
+                assert_eq!(
+                    can_transition(from, to),
+                    ALLOWED.contains(&(from, to)),
+                    "{} -> {}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_status_is_rejected() {
+        assert!(!can_transition("未知", IN_PROGRESS));
+        assert!(!can_transition(IN_PROGRESS, "未知"));
+    }
+}
@@ -1,3 +1,4 @@
+use actix::Actor;
 use actix_cors::Cors;
 use actix_files::Files;
 use actix_web::{App, HttpServer, web};
@@ -7,16 +8,31 @@ use std::env;
 use uuid::Uuid;
 
 mod auth;
+mod blueprint;
+mod contribution;
 mod db;
+mod direct_messages;
+mod evaluation;
 mod handlers;
+mod invites;
+mod lifecycle;
+mod membership;
+mod mentions;
 mod models;
+mod notifications;
+mod privacy;
+mod progress;
+mod report;
+mod reputation;
+mod scheduler;
+mod task_chat;
 mod ws;
 
 use crate::handlers::{
-    add_evaluation, add_message, add_progress, claim_role, create_sub_task, create_task,
-    delete_sub_task, finish_task, get_my_published_tasks, get_my_roles, get_task_roles,
-    get_user_info, list_evaluations, list_messages, list_progress, list_sub_tasks, list_tasks,
-    update_sub_task, update_task, update_user_profile, upload_avatar,
+    add_evaluation, add_progress, claim_role, create_sub_task, create_task, delete_sub_task,
+    finish_task, get_my_published_tasks, get_my_roles, get_task_roles, get_user_info,
+    list_evaluations, list_progress, list_sub_tasks, list_tasks, update_sub_task, update_task,
+    update_user_profile, upload_avatar,
 };
 use crate::models::RoleInfo;
 
@@ -24,6 +40,8 @@ use crate::models::RoleInfo;
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
     let pool = db::get_db_pool().await;
+    scheduler::start(pool.clone());
+    let ws_server = ws::WsServer::default().start();
     let bind_addr = "127.0.0.1:8080";
     println!("Server running on http://{}", bind_addr);
 
@@ -35,21 +53,239 @@ async fn main() -> std::io::Result<()> {
         App::new()
             .wrap(cors)
             .app_data(web::Data::new(pool.clone()))
+            .app_data(web::Data::new(ws_server.clone()))
             .service(
                 web::scope("/api")
                     .route("/register", web::post().to(auth::register))
                     .route("/login", web::post().to(auth::login))
                     .route("/tasks", web::get().to(handlers::list_tasks))
                     .route("/tasks", web::post().to(handlers::create_task))
+                    .route("/tasks/{task_id}", web::get().to(handlers::get_task))
+                    .route("/task_tags", web::get().to(handlers::list_task_tags))
+                    .route(
+                        "/tasks/{task_id}/save_as_template",
+                        web::post().to(handlers::save_task_as_template),
+                    )
+                    .route(
+                        "/tasks/{task_id}/clone",
+                        web::post().to(handlers::clone_task),
+                    )
+                    .route(
+                        "/my_task_templates",
+                        web::get().to(handlers::list_my_task_templates),
+                    )
+                    .route(
+                        "/task_templates/{template_id}/create_task",
+                        web::post().to(handlers::create_task_from_template),
+                    )
+                    .route(
+                        "/task_templates/{template_id}/delete",
+                        web::post().to(handlers::delete_task_template),
+                    )
                     .route(
                         "/task_roles/{task_id}",
                         web::get().to(handlers::get_task_roles),
                     )
                     .route("/claim_role", web::post().to(handlers::claim_role))
+                    .route(
+                        "/tasks/{task_id}/join_requests",
+                        web::get().to(handlers::list_join_requests),
+                    )
+                    .route(
+                        "/my_join_requests",
+                        web::get().to(handlers::list_my_join_requests),
+                    )
+                    .route(
+                        "/join_requests/{request_id}/approve",
+                        web::post().to(handlers::approve_join_request),
+                    )
+                    .route(
+                        "/join_requests/{request_id}/reject",
+                        web::post().to(handlers::reject_join_request),
+                    )
+                    .route(
+                        "/join_requests/{request_id}/withdraw",
+                        web::post().to(handlers::withdraw_join_request),
+                    )
+                    .route(
+                        "/tasks/{task_id}/invite_links",
+                        web::post().to(handlers::create_invite_link),
+                    )
+                    .route(
+                        "/tasks/{task_id}/invite_links",
+                        web::get().to(handlers::list_invite_links),
+                    )
+                    .route(
+                        "/invite_links/{link_id}/revoke",
+                        web::post().to(handlers::revoke_invite_link),
+                    )
+                    .route("/invite/{token}", web::get().to(handlers::get_invite_link))
+                    .route(
+                        "/invite/{token}/accept",
+                        web::post().to(handlers::accept_invite_link),
+                    )
+                    .route(
+                        "/tasks/{task_id}/invitations",
+                        web::post().to(handlers::invite_user),
+                    )
+                    .route(
+                        "/tasks/{task_id}/invitations",
+                        web::get().to(handlers::list_task_invitations),
+                    )
+                    .route(
+                        "/my_invitations",
+                        web::get().to(handlers::list_my_invitations),
+                    )
+                    .route(
+                        "/invitations/{invitation_id}/accept",
+                        web::post().to(handlers::accept_invitation),
+                    )
+                    .route(
+                        "/invitations/{invitation_id}/decline",
+                        web::post().to(handlers::decline_invitation),
+                    )
+                    .route(
+                        "/tasks/{task_id}/membership_history",
+                        web::get().to(handlers::get_membership_history),
+                    )
+                    .route(
+                        "/tasks/{task_id}/leave",
+                        web::post().to(handlers::leave_task),
+                    )
+                    .route(
+                        "/tasks/{task_id}/transfer",
+                        web::post().to(handlers::request_ownership_transfer),
+                    )
+                    .route(
+                        "/my_ownership_transfers",
+                        web::get().to(handlers::list_my_ownership_transfers),
+                    )
+                    .route(
+                        "/ownership_transfers/{transfer_id}/accept",
+                        web::post().to(handlers::accept_ownership_transfer),
+                    )
+                    .route(
+                        "/ownership_transfers/{transfer_id}/decline",
+                        web::post().to(handlers::decline_ownership_transfer),
+                    )
+                    .route(
+                        "/ownership_transfers/{transfer_id}/cancel",
+                        web::post().to(handlers::cancel_ownership_transfer),
+                    )
+                    .route(
+                        "/tasks/{task_id}/evaluation_rubric",
+                        web::get().to(handlers::get_evaluation_rubric),
+                    )
+                    .route(
+                        "/tasks/{task_id}/evaluation_rubric",
+                        web::post().to(handlers::update_evaluation_rubric),
+                    )
+                    .route(
+                        "/tasks/{task_id}/evaluation_settings",
+                        web::post().to(handlers::update_evaluation_settings),
+                    )
+                    .route("/evaluations", web::post().to(handlers::add_evaluation))
+                    .route(
+                        "/tasks/{task_id}/evaluations",
+                        web::get().to(handlers::list_evaluations),
+                    )
+                    .route(
+                        "/tasks/{task_id}/evaluation_summary",
+                        web::get().to(handlers::get_evaluation_summary),
+                    )
+                    .route(
+                        "/tasks/{task_id}/evaluation_targets",
+                        web::get().to(handlers::list_evaluation_targets),
+                    )
+                    .route(
+                        "/tasks/{task_id}/contribution_weights",
+                        web::get().to(handlers::get_contribution_weights),
+                    )
+                    .route(
+                        "/tasks/{task_id}/contribution_weights",
+                        web::post().to(handlers::update_contribution_weights),
+                    )
+                    .route(
+                        "/tasks/{task_id}/contributions",
+                        web::get().to(handlers::get_task_contributions),
+                    )
+                    .route("/courses", web::get().to(handlers::list_courses))
+                    .route(
+                        "/courses/{course}/contributions",
+                        web::get().to(handlers::get_course_contributions),
+                    )
+                    .route(
+                        "/notifications/{user_id}",
+                        web::get().to(handlers::list_notifications),
+                    )
+                    .route(
+                        "/notifications/{notification_id}/read",
+                        web::post().to(handlers::mark_notification_read),
+                    )
+                    .route(
+                        "/tasks/{task_id}/messages",
+                        web::post().to(handlers::post_task_message),
+                    )
+                    .route(
+                        "/tasks/{task_id}/messages",
+                        web::get().to(handlers::list_task_messages),
+                    )
+                    .route(
+                        "/task_messages/{message_id}",
+                        web::put().to(handlers::edit_task_message),
+                    )
+                    .route(
+                        "/task_messages/{message_id}",
+                        web::delete().to(handlers::delete_task_message),
+                    )
+                    .route(
+                        "/task_messages/{message_id}/replies",
+                        web::get().to(handlers::list_task_message_replies),
+                    )
+                    .route(
+                        "/task_messages/{message_id}/edits",
+                        web::get().to(handlers::get_task_message_edits),
+                    )
+                    .route(
+                        "/task_messages/{message_id}/reactions",
+                        web::post().to(handlers::add_task_message_reaction),
+                    )
+                    .route(
+                        "/task_messages/{message_id}/reactions",
+                        web::delete().to(handlers::remove_task_message_reaction),
+                    )
+                    .route(
+                        "/direct_messages",
+                        web::post().to(handlers::send_direct_message),
+                    )
+                    .route("/dm_threads", web::get().to(handlers::list_dm_threads))
+                    .route(
+                        "/dm_threads/{thread_id}/messages",
+                        web::get().to(handlers::list_direct_messages),
+                    )
+                    .route(
+                        "/dm_threads/{thread_id}/read",
+                        web::post().to(handlers::mark_dm_thread_read),
+                    )
+                    .route("/dm_unread", web::get().to(handlers::get_dm_unread_count))
+                    .route("/blocks", web::post().to(handlers::block_user))
+                    .route("/blocks", web::get().to(handlers::list_blocked_users))
+                    .route(
+                        "/blocks/{blocked_id}",
+                        web::delete().to(handlers::unblock_user),
+                    )
+                    .route(
+                        "/notifications/read_all",
+                        web::post().to(handlers::mark_all_notifications_read),
+                    )
                     .route(
                         "/user_info/{user_id}",
                         web::get().to(handlers::get_user_info),
                     )
+                    .route(
+                        "/users/{user_id}/profile",
+                        web::get().to(handlers::get_public_profile),
+                    )
                     .route(
                         "/update_profile",
                         web::post().to(handlers::update_user_profile),
@@ -59,7 +295,29 @@ async fn main() -> std::io::Result<()> {
                         "/my_published_tasks/{user_id}",
                         web::get().to(handlers::get_my_published_tasks),
                     )
+                    .route("/progress", web::post().to(handlers::add_progress))
+                    .route("/progress/{task_id}", web::get().to(handlers::list_progress))
                     .route("/finish_task", web::post().to(handlers::finish_task))
+                    .route(
+                        "/tasks/{task_id}/status",
+                        web::post().to(handlers::change_task_status),
+                    )
+                    .route(
+                        "/tasks/{task_id}/delete",
+                        web::post().to(handlers::delete_task),
+                    )
+                    .route(
+                        "/tasks/{task_id}/restore",
+                        web::post().to(handlers::restore_task),
+                    )
+                    .route(
+                        "/my_deleted_tasks",
+                        web::get().to(handlers::get_my_deleted_tasks),
+                    )
+                    .route(
+                        "/tasks/{task_id}/status_history",
+                        web::get().to(handlers::list_task_status_history),
+                    )
                     .route("/update_task", web::post().to(handlers::update_task))
                     .route("/upload_avatar", web::post().to(handlers::upload_avatar))
                     .route(
@@ -75,6 +333,74 @@ async fn main() -> std::io::Result<()> {
                         "/tasks/{task_id}/sub_tasks/{sub_task_id}",
                         web::delete().to(delete_sub_task),
                     )
+                    .route(
+                        "/tasks/{task_id}/sub_tasks/{sub_task_id}/checklist",
+                        web::get().to(handlers::list_checklist_items),
+                    )
+                    .route(
+                        "/tasks/{task_id}/sub_tasks/{sub_task_id}/checklist",
+                        web::post().to(handlers::create_checklist_item),
+                    )
+                    .route(
+                        "/tasks/{task_id}/sub_tasks/{sub_task_id}/checklist/{item_id}",
+                        web::put().to(handlers::update_checklist_item),
+                    )
+                    .route(
+                        "/tasks/{task_id}/sub_tasks/{sub_task_id}/checklist/{item_id}",
+                        web::delete().to(handlers::delete_checklist_item),
+                    )
+                    .route(
+                        "/tasks/{task_id}/milestones",
+                        web::post().to(handlers::create_milestone),
+                    )
+                    .route(
+                        "/tasks/{task_id}/milestones",
+                        web::get().to(handlers::list_milestones),
+                    )
+                    .route(
+                        "/tasks/{task_id}/milestones/{milestone_id}",
+                        web::put().to(handlers::update_milestone),
+                    )
+                    .route(
+                        "/tasks/{task_id}/milestones/{milestone_id}",
+                        web::delete().to(handlers::delete_milestone),
+                    )
+                    .route(
+                        "/tasks/{task_id}/burndown",
+                        web::get().to(handlers::get_burndown),
+                    )
+                    .route(
+                        "/tasks/{task_id}/time_logs",
+                        web::post().to(handlers::add_time_log),
+                    )
+                    .route(
+                        "/tasks/{task_id}/time_logs",
+                        web::get().to(handlers::list_time_logs),
+                    )
+                    .route(
+                        "/tasks/{task_id}/report",
+                        web::get().to(handlers::get_task_report),
+                    )
+                    .route(
+                        "/tasks/{task_id}/report",
+                        web::post().to(handlers::save_task_report),
+                    )
+                    .route(
+                        "/tasks/{task_id}/attachments",
+                        web::get().to(handlers::list_task_attachments),
+                    )
+                    .route(
+                        "/tasks/{task_id}/sub_task_templates",
+                        web::post().to(handlers::create_sub_task_template),
+                    )
+                    .route(
+                        "/tasks/{task_id}/sub_task_templates",
+                        web::get().to(handlers::list_sub_task_templates),
+                    )
+                    .route(
+                        "/tasks/{task_id}/sub_task_templates/{template_id}",
+                        web::delete().to(handlers::delete_sub_task_template),
+                    )
                     .route(
                         "/task_roles/{role_id}/remove_member",
                         web::post().to(handlers::remove_member_from_task_role),
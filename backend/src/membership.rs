@@ -0,0 +1,298 @@
+use crate::lifecycle;
+use chrono::Utc;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+// 可以加入成员的任务状态
+pub const JOINABLE_STATUSES: [&str; 3] = [
+    lifecycle::RECRUITING,
+    lifecycle::IN_PROGRESS,
+    lifecycle::REOPENED,
+];
+
+// 加入申请状态
+pub const REQUEST_PENDING: &str = "待审核";
+pub const REQUEST_APPROVED: &str = "已通过";
+pub const REQUEST_REJECTED: &str = "已拒绝";
+pub const REQUEST_WITHDRAWN: &str = "已撤回";
+
+// 任务转让状态
+pub const TRANSFER_PENDING: &str = "待接受";
+pub const TRANSFER_ACCEPTED: &str = "已接受";
+pub const TRANSFER_DECLINED: &str = "已拒绝";
+pub const TRANSFER_CANCELLED: &str = "已取消";
+
+// 成员变动记录类型
+pub const EVENT_JOINED: &str = "加入";
+pub const EVENT_LEFT: &str = "退出";
+pub const EVENT_REMOVED: &str = "移除";
+
+pub enum JoinError {
+    RoleNotFound,
+    NotJoinable,
+    AlreadyHeld,
+    AlreadyApplied,
+    RoleFull,
+    TeamFull,
+    Db(sqlx::Error),
+}
+
+impl From<sqlx::Error> for JoinError {
+    fn from(e: sqlx::Error) -> Self {
+        JoinError::Db(e)
+    }
+}
+
+impl JoinError {
+    pub fn message(&self) -> String {
+        match self {
+            JoinError::RoleNotFound => "职责不存在".to_string(),
+            JoinError::NotJoinable => "该职责已关闭或任务当前不可加入".to_string(),
+            JoinError::AlreadyHeld => "你已经认领了该职责".to_string(),
+            JoinError::AlreadyApplied => "你已经申请过该职责，请等待审核".to_string(),
+            JoinError::RoleFull => "该职责名额已满".to_string(),
+            JoinError::TeamFull => "队伍人数已满".to_string(),
+            JoinError::Db(e) => e.to_string(),
+        }
+    }
+}
+
+pub struct RoleSlot {
+    pub task_id: Uuid,
+    pub role_name: String,
+    pub creator_id: Option<Uuid>,
+    pub approval_required: bool,
+}
+
+// 检查用户能否加入职责。
+// 先锁住任务行，同一任务的并发加入依次执行，职责名额和队伍人数的检查不会同时通过。
+// 需要在事务中调用，检查通过后在同一事务中写入成员。
+pub async fn check_join(
+    conn: &mut PgConnection,
+    role_id: Uuid,
+    user_id: Uuid,
+) -> Result<RoleSlot, JoinError> {
+    let role = sqlx::query!(
+        r#"
+        SELECT tr.task_id as "task_id!", tr.role_name, tr.capacity, tr.closed,
+            t.creator_id, t.team_size, t.status, t.deleted_at, t.approval_required
+        FROM task_roles tr
+        JOIN tasks t ON t.id = tr.task_id
+        WHERE tr.id = $1
+        FOR UPDATE OF t
+        "#,
+        role_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(JoinError::RoleNotFound)?;
+
+    let joinable = role
+        .status
+        .as_deref()
+        .is_some_and(|s| JOINABLE_STATUSES.contains(&s));
+    if role.closed || role.deleted_at.is_some() || !joinable {
+        return Err(JoinError::NotJoinable);
+    }
+
+    let counts = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE m.role_id = $2) as "role_filled!",
+            COUNT(*) FILTER (WHERE m.role_id = $2 AND m.user_id = $3) as "held!",
+            COUNT(DISTINCT m.user_id) as "team_filled!",
+            COUNT(*) FILTER (WHERE m.user_id = $3) as "user_roles!"
+        FROM task_role_members m
+        JOIN task_roles tr ON tr.id = m.role_id
+        WHERE tr.task_id = $1
+        "#,
+        role.task_id,
+        role_id,
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if counts.held > 0 {
+        return Err(JoinError::AlreadyHeld);
+    }
+    if counts.role_filled >= role.capacity as i64 {
+        return Err(JoinError::RoleFull);
+    }
+    // 已在队伍中的成员认领更多职责不占用新的队伍名额
+    if counts.user_roles == 0 && counts.team_filled >= role.team_size as i64 {
+        return Err(JoinError::TeamFull);
+    }
+
+    Ok(RoleSlot {
+        task_id: role.task_id,
+        role_name: role.role_name,
+        creator_id: role.creator_id,
+        approval_required: role.approval_required,
+    })
+}
+
+// 检查通过后将用户加入职责，actor_id 为操作人（本人认领或发布者审核通过）
+pub async fn join_role(
+    conn: &mut PgConnection,
+    role_id: Uuid,
+    user_id: Uuid,
+    actor_id: Uuid,
+) -> Result<RoleSlot, JoinError> {
+    let slot = check_join(conn, role_id, user_id).await?;
+    add_member(conn, role_id, user_id, actor_id).await?;
+    Ok(slot)
+}
+
+// 写入成员并记录加入，调用前需先通过 check_join
+pub async fn add_member(
+    conn: &mut PgConnection,
+    role_id: Uuid,
+    user_id: Uuid,
+    actor_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let now = Utc::now();
+    sqlx::query!(
+        "INSERT INTO task_role_members (role_id, user_id, joined_at) VALUES ($1, $2, $3)",
+        role_id,
+        user_id,
+        now
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO task_membership_events (id, task_id, user_id, role_id, role_name, event, actor_id, created_at)
+        SELECT $1, task_id, $2, id, role_name, $3, $4, $5 FROM task_roles WHERE id = $6
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        EVENT_JOINED,
+        actor_id,
+        now,
+        role_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+// 需要审核的任务：检查通过后创建待审核的申请，返回申请 id
+pub async fn apply_for_role(
+    conn: &mut PgConnection,
+    slot: &RoleSlot,
+    role_id: Uuid,
+    user_id: Uuid,
+    message: Option<&str>,
+) -> Result<Uuid, JoinError> {
+    let pending = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM join_requests WHERE role_id = $1 AND user_id = $2 AND status = $3) as "exists!""#,
+        role_id,
+        user_id,
+        REQUEST_PENDING
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if pending {
+        return Err(JoinError::AlreadyApplied);
+    }
+
+    let id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO join_requests (id, task_id, role_id, user_id, message, status, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        id,
+        slot.task_id,
+        role_id,
+        user_id,
+        message,
+        REQUEST_PENDING,
+        Utc::now()
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(id)
+}
+
+// 释放成员在任务中的全部职责，并取消其子任务和周期模板的分配。
+// 每个释放的职责记录一条退出或移除。返回释放的职责数，为 0 表示该用户不是任务成员。
+// 需要在事务中调用。
+pub async fn release_member(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    user_id: Uuid,
+    event: &str,
+    actor_id: Uuid,
+    reason: Option<&str>,
+) -> Result<u64, sqlx::Error> {
+    let released = sqlx::query!(
+        r#"
+        WITH released AS (
+            DELETE FROM task_role_members m
+            USING task_roles tr
+            WHERE m.role_id = tr.id AND tr.task_id = $1 AND m.user_id = $2
+            RETURNING m.role_id, tr.role_name
+        )
+        INSERT INTO task_membership_events (id, task_id, user_id, role_id, role_name, event, actor_id, reason, created_at)
+        SELECT gen_random_uuid(), $1, $2, role_id, role_name, $3, $4, $5, $6 FROM released
+        "#,
+        task_id,
+        user_id,
+        event,
+        actor_id,
+        reason,
+        Utc::now()
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    // 已完成的子任务保留负责人，用于统计贡献度
+    sqlx::query!(
+        "UPDATE sub_tasks SET assignee_id = NULL WHERE task_id = $1 AND assignee_id = $2 AND status <> '已完成'",
+        task_id,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query!(
+        "UPDATE sub_task_templates SET assignee_id = NULL WHERE task_id = $1 AND assignee_id = $2",
+        task_id,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(released)
+}
+
+// 曾在任务中担任职责的全部成员，包括已退出或被移除的
+pub async fn team_roster(conn: &mut PgConnection, task_id: Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
+    sqlx::query_scalar!(
+        "SELECT DISTINCT user_id FROM task_membership_events WHERE task_id = $1 AND event = $2",
+        task_id,
+        EVENT_JOINED
+    )
+    .fetch_all(&mut *conn)
+    .await
+}
+
+// 任务当前的成员（仍担任职责的用户）和发布者
+pub async fn current_team(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+) -> Result<Vec<Uuid>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT m.user_id as "user_id!" FROM task_role_members m
+        JOIN task_roles r ON r.id = m.role_id
+        WHERE r.task_id = $1
+        UNION
+        SELECT creator_id FROM tasks WHERE id = $1 AND creator_id IS NOT NULL
+        "#,
+        task_id
+    )
+    .fetch_all(&mut *conn)
+    .await
+}
@@ -0,0 +1,192 @@
+use crate::membership;
+use crate::notifications;
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+// 文本中 @ 到的一名任务成员。start、end 为 "@用户名" 在文本中的 UTF-16 偏移，
+// 与前端字符串下标一致，前端据此高亮
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mention {
+    pub user_id: Uuid,
+    pub username: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+// 在文本中查找 @用户名，只认 members 中的用户名。
+// 中文之间没有空格，按最长的成员用户名匹配；@ 前是字母数字（如邮箱）或用户名后紧跟字母数字时不算提及
+pub fn find(text: &str, members: &[(Uuid, String)]) -> Vec<Mention> {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut mentions = Vec::new();
+    let mut offset = 0;
+    let mut prev: Option<char> = None;
+    let mut skip_until = 0;
+    for (i, c) in text.char_indices() {
+        if i >= skip_until && c == '@' && !prev.is_some_and(is_word) {
+            let rest = &text[i + 1..];
+            let matched = members
+                .iter()
+                .filter(|(_, name)| {
+                    !name.is_empty()
+                        && rest.starts_with(name.as_str())
+                        && !rest[name.len()..].starts_with(is_word)
+                })
+                .max_by_key(|(_, name)| name.len());
+            if let Some((user_id, username)) = matched {
+                mentions.push(Mention {
+                    user_id: *user_id,
+                    username: username.clone(),
+                    start: offset,
+                    end: offset + 1 + username.encode_utf16().count(),
+                });
+                skip_until = i + 1 + username.len();
+            }
+        }
+        offset += c.len_utf16();
+        prev = Some(c);
+    }
+    mentions
+}
+
+// 解析文本中 @ 到的任务成员（当前成员和发布者）
+pub async fn resolve(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    text: &str,
+) -> Result<Vec<Mention>, sqlx::Error> {
+    if !text.contains('@') {
+        return Ok(Vec::new());
+    }
+    let team = membership::current_team(conn, task_id).await?;
+    let members: Vec<(Uuid, String)> =
+        sqlx::query!("SELECT id, username FROM users WHERE id = ANY($1)", &team)
+            .fetch_all(&mut *conn)
+            .await?
+            .into_iter()
+            .map(|r| (r.id, r.username))
+            .collect();
+    Ok(find(text, &members))
+}
+
+// 通知被 @ 到的成员。previous 为修改前已提到的成员，不重复通知；作者提到自己也不通知。
+// place 描述提及出现的位置，如 "聊天"
+pub async fn notify_mentioned(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    author_id: Option<Uuid>,
+    mentions: &[Mention],
+    previous: &[Mention],
+    place: &str,
+) -> Result<(), sqlx::Error> {
+    let mut targets: Vec<Uuid> = mentions
+        .iter()
+        .map(|m| m.user_id)
+        .filter(|id| Some(*id) != author_id && !previous.iter().any(|p| p.user_id == *id))
+        .collect();
+    targets.sort();
+    targets.dedup();
+    if targets.is_empty() {
+        return Ok(());
+    }
+    let row = sqlx::query!(
+        r#"
+        SELECT t.title, (SELECT COALESCE(NULLIF(name, ''), username) FROM users WHERE id = $2) as author
+        FROM tasks t WHERE t.id = $1
+        "#,
+        task_id,
+        author_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    let content = format!(
+        "{} 在任务「{}」的{}中提到了你",
+        row.author.as_deref().unwrap_or("有人"),
+        row.title,
+        place
+    );
+    for user_id in targets {
+        notifications::notify(
+            conn,
+            user_id,
+            notifications::MENTION,
+            &content,
+            Some(task_id),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn members(names: &[&str]) -> Vec<(Uuid, String)> {
+        names
+            .iter()
+            .map(|name| (Uuid::new_v4(), name.to_string()))
+            .collect()
+    }
+
+    // (用户名, start, end)
+    fn found(text: &str, members: &[(Uuid, String)]) -> Vec<(String, usize, usize)> {
+        find(text, members)
+            .into_iter()
+            .map(|m| (m.username, m.start, m.end))
+            .collect()
+    }
+
+    #[test]
+    fn mention_next_to_cjk() {
+        let m = members(&["小明", "bob"]);
+        assert_eq!(found("请@小明看一下", &m), vec![("小明".to_string(), 1, 4)]);
+        assert_eq!(found("你好@bob谢谢", &m), vec![("bob".to_string(), 2, 6)]);
+    }
+
+    #[test]
+    fn email_is_not_a_mention() {
+        let m = members(&["b", "example"]);
+        assert!(found("a@b", &m).is_empty());
+        assert!(found("联系 me@example 即可", &m).is_empty());
+    }
+
+    #[test]
+    fn longest_username_wins() {
+        let m = members(&["bo", "bob"]);
+        assert_eq!(found("@bob", &m), vec![("bob".to_string(), 0, 4)]);
+        assert_eq!(found("@bo 你好", &m), vec![("bo".to_string(), 0, 3)]);
+        assert_eq!(
+            found("@bo @bob", &m),
+            vec![("bo".to_string(), 0, 3), ("bob".to_string(), 4, 8)]
+        );
+    }
+
+    #[test]
+    fn username_must_end_at_word_boundary() {
+        let m = members(&["bob"]);
+        assert!(found("@bob_x", &m).is_empty());
+        assert!(found("@bob2", &m).is_empty());
+        assert!(found("@bobby", &m).is_empty());
+        assert_eq!(found("@bob, hi", &m), vec![("bob".to_string(), 0, 4)]);
+        // bob 后紧跟字母时退回到更短的 bo 也不行
+        let m = members(&["bo", "bob"]);
+        assert!(found("@bobx", &m).is_empty());
+    }
+
+    #[test]
+    fn offsets_are_utf16_after_astral_characters() {
+        let m = members(&["bob"]);
+        // 😀 在 UTF-16 中占两个单元
+        assert_eq!(found("😀@bob", &m), vec![("bob".to_string(), 2, 6)]);
+        assert_eq!(found("😀😀 中 @bob", &m), vec![("bob".to_string(), 7, 11)]);
+    }
+
+    #[test]
+    fn only_members_are_mentioned() {
+        let m = members(&["bob"]);
+        assert!(found("@alice", &m).is_empty());
+        assert!(found("@", &m).is_empty());
+        assert!(found("", &m).is_empty());
+    }
+}
@@ -1,20 +1,11 @@
+use crate::blueprint::TaskBlueprint;
+use crate::mentions::Mention;
+use crate::privacy::Contact;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
-pub struct User {
-    pub id: Uuid,
-    pub username: String,
-    pub password_hash: String,
-    pub name: String,
-    pub avatar_url: Option<String>,
-    pub phone: Option<String>,
-    pub student_id: Option<String>,
-    pub email: Option<String>,
-    pub created_at: DateTime<Utc>,
-}
-
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Task {
     pub id: Uuid,
@@ -24,6 +15,12 @@ pub struct Task {
     pub created_at: DateTime<Utc>,
     pub team_size: i32,
     pub status: String,
+    pub weight_by_estimate: bool,
+    pub deadline: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub tags: Vec<String>,
+    pub approval_required: bool,
+    pub course: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
@@ -33,22 +30,27 @@ pub struct Progress {
     pub content: String,
     pub percent: i32,
     pub created_at: DateTime<Utc>,
+    pub source: String,
+    pub user_id: Option<Uuid>,
+    pub mentions: Json<Vec<Mention>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
-pub struct Evaluation {
+pub struct EvaluationCriterion {
     pub id: Uuid,
     pub task_id: Uuid,
-    pub username: String,
-    pub content: String,
-    pub rate: i32,
-    pub created_at: DateTime<Utc>,
+    pub name: String,
+    pub description: Option<String>,
+    pub min_score: i32,
+    pub max_score: i32,
+    pub position: i32,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
-pub struct Message {
+pub struct DirectMessage {
     pub id: Uuid,
-    pub username: String,
+    pub thread_id: Uuid,
+    pub sender_id: Uuid,
     pub content: String,
     pub created_at: DateTime<Utc>,
 }
@@ -59,6 +61,18 @@ pub struct TaskRole {
     pub task_id: Uuid,
     pub role_name: String,
     pub user_id: Option<Uuid>,
+    pub closed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TaskStatusHistory {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub from_status: Option<String>,
+    pub to_status: String,
+    pub changed_by: Option<Uuid>,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -80,16 +94,113 @@ pub struct SubTask {
     pub status: String,
     pub created_at: DateTime<Utc>,
     pub due_date: Option<DateTime<Utc>>,
+    pub priority: i16,
+    pub labels: Vec<String>,
+    pub template_id: Option<Uuid>,
+    pub occurrence_at: Option<DateTime<Utc>>,
+    pub estimate_hours: Option<f32>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub milestone_id: Option<Uuid>,
 }
 
-#[derive(Debug, Serialize, sqlx::FromRow)]
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Milestone {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub target_date: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ChecklistItem {
+    pub id: Uuid,
+    pub sub_task_id: Uuid,
+    pub content: String,
+    pub done: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SubTaskTemplate {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub title: String,
+    pub description: Option<String>,
+    pub assignee_id: Option<Uuid>,
+    pub priority: i16,
+    pub labels: Vec<String>,
+    pub frequency: String,
+    pub interval_days: i32,
+    pub due_offset_hours: i32,
+    pub next_run_at: DateTime<Utc>,
+    pub until_at: Option<DateTime<Utc>>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
 pub struct RoleInfo {
     pub role_id: Uuid,
     pub role_name: String,
-    pub user_id: Option<Uuid>,
+    pub capacity: i32,
+    pub closed: bool,
+    pub members: Vec<RoleMember>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoleMember {
+    pub user_id: Uuid,
     pub name: Option<String>,
-    pub username: Option<String>,
-    pub phone: Option<String>,
-    pub student_id: Option<String>,
-    pub email: Option<String>,
+    pub username: String,
+    // 按查看人和隐私设置过滤
+    #[serde(flatten)]
+    pub contact: Contact,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TimeLog {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub sub_task_id: Option<Uuid>,
+    pub user_id: Uuid,
+    pub hours: f32,
+    pub note: Option<String>,
+    pub logged_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TaskAttachment {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub file_name: String,
+    pub url: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub uploaded_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TaskTemplate {
+    pub id: Uuid,
+    pub creator_id: Uuid,
+    pub name: String,
+    pub source_task_id: Option<Uuid>,
+    pub structure: Json<TaskBlueprint>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Notification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub kind: String,
+    pub content: String,
+    pub task_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub read_at: Option<DateTime<Utc>>,
 }
@@ -0,0 +1,42 @@
+use chrono::Utc;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+// 通知类型
+pub const JOIN_REQUEST: &str = "join_request";
+pub const JOIN_APPROVED: &str = "join_approved";
+pub const JOIN_REJECTED: &str = "join_rejected";
+pub const TASK_INVITATION: &str = "task_invitation";
+pub const INVITATION_ACCEPTED: &str = "invitation_accepted";
+pub const INVITATION_DECLINED: &str = "invitation_declined";
+pub const MEMBER_LEFT: &str = "member_left";
+pub const MEMBER_REMOVED: &str = "member_removed";
+pub const OWNERSHIP_TRANSFER: &str = "ownership_transfer";
+pub const TRANSFER_ACCEPTED: &str = "transfer_accepted";
+pub const TRANSFER_DECLINED: &str = "transfer_declined";
+pub const MENTION: &str = "mention";
+
+// 写入一条站内通知。与触发通知的修改放在同一事务中，回滚时通知一并撤销。
+pub async fn notify(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    kind: &str,
+    content: &str,
+    task_id: Option<Uuid>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO notifications (id, user_id, kind, content, task_id, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        kind,
+        content,
+        task_id,
+        Utc::now()
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,179 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgConnection;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// 联系方式的可见范围
+pub const VISIBILITY_PUBLIC: &str = "public";
+pub const VISIBILITY_TEAMMATES: &str = "teammates";
+pub const VISIBILITY_PRIVATE: &str = "private";
+pub const VISIBILITIES: [&str; 3] = [VISIBILITY_PUBLIC, VISIBILITY_TEAMMATES, VISIBILITY_PRIVATE];
+
+// 查看人与资料所属用户的关系
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Relation {
+    // 本人或管理员，可以看到全部字段
+    Myself,
+    // 与该用户同在某个任务中（成员或发布者）
+    Teammate,
+    Public,
+}
+
+impl Relation {
+    fn can_see(self, visibility: &str) -> bool {
+        match self {
+            Relation::Myself => true,
+            Relation::Teammate => visibility != VISIBILITY_PRIVATE,
+            Relation::Public => visibility == VISIBILITY_PUBLIC,
+        }
+    }
+}
+
+// 按隐私设置过滤后的联系方式，不可见的字段为空
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Contact {
+    pub phone: Option<String>,
+    pub student_id: Option<String>,
+    pub email: Option<String>,
+}
+
+// 本人查看自己的资料
+#[derive(Debug, Serialize)]
+pub struct SelfUser {
+    pub id: Uuid,
+    pub username: String,
+    pub name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub phone: Option<String>,
+    pub student_id: Option<String>,
+    pub email: Option<String>,
+    pub phone_visibility: String,
+    pub student_id_visibility: String,
+    pub email_visibility: String,
+    pub is_admin: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+// 他人查看的资料，联系方式按关系和隐私设置过滤
+#[derive(Debug, Serialize)]
+pub struct PublicUser {
+    pub id: Uuid,
+    pub username: String,
+    pub name: Option<String>,
+    pub avatar_url: Option<String>,
+    #[serde(flatten)]
+    pub contact: Contact,
+    // 是否为队友，前端据此提示部分信息仅队友可见
+    pub teammate: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+pub enum UserView {
+    Myself(SelfUser),
+    Other(PublicUser),
+}
+
+// 查看人与一组用户的关系及其可见的联系方式；未登录查看人按公开处理
+pub async fn visible_contacts(
+    conn: &mut PgConnection,
+    viewer: Option<Uuid>,
+    user_ids: &[Uuid],
+) -> Result<HashMap<Uuid, (Relation, Contact)>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        WITH teams AS (
+            SELECT tr.task_id, m.user_id
+            FROM task_role_members m
+            JOIN task_roles tr ON tr.id = m.role_id
+            UNION
+            SELECT id, creator_id FROM tasks WHERE creator_id IS NOT NULL AND deleted_at IS NULL
+        )
+        SELECT u.id, u.phone, u.student_id, u.email,
+            u.phone_visibility, u.student_id_visibility, u.email_visibility,
+            (COALESCE(u.id = $2, FALSE) OR COALESCE((SELECT is_admin FROM users WHERE id = $2), FALSE)) as "myself!",
+            EXISTS (
+                SELECT 1 FROM teams a JOIN teams b ON a.task_id = b.task_id
+                WHERE a.user_id = u.id AND b.user_id = $2
+            ) as "teammate!"
+        FROM users u
+        WHERE u.id = ANY($1)
+        "#,
+        user_ids,
+        viewer
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            let relation = if r.myself {
+                Relation::Myself
+            } else if r.teammate {
+                Relation::Teammate
+            } else {
+                Relation::Public
+            };
+            let contact = Contact {
+                phone: r.phone.filter(|_| relation.can_see(&r.phone_visibility)),
+                student_id: r
+                    .student_id
+                    .filter(|_| relation.can_see(&r.student_id_visibility)),
+                email: r.email.filter(|_| relation.can_see(&r.email_visibility)),
+            };
+            (r.id, (relation, contact))
+        })
+        .collect())
+}
+
+// 本人看到完整资料（不含密码），其他人看到按隐私设置过滤后的资料
+pub async fn user_view(
+    conn: &mut PgConnection,
+    viewer: Option<Uuid>,
+    user_id: Uuid,
+) -> Result<Option<UserView>, sqlx::Error> {
+    let Some(user) = sqlx::query!(
+        r#"
+        SELECT id, username, name, avatar_url, phone, student_id, email,
+            phone_visibility, student_id_visibility, email_visibility, is_admin, created_at
+        FROM users WHERE id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    if viewer == Some(user_id) {
+        return Ok(Some(UserView::Myself(SelfUser {
+            id: user.id,
+            username: user.username,
+            name: user.name,
+            avatar_url: user.avatar_url,
+            phone: user.phone,
+            student_id: user.student_id,
+            email: user.email,
+            phone_visibility: user.phone_visibility,
+            student_id_visibility: user.student_id_visibility,
+            email_visibility: user.email_visibility,
+            is_admin: user.is_admin,
+            created_at: user.created_at,
+        })));
+    }
+    let (relation, contact) = visible_contacts(conn, viewer, &[user_id])
+        .await?
+        .remove(&user_id)
+        .unwrap_or((Relation::Public, Contact::default()));
+    Ok(Some(UserView::Other(PublicUser {
+        id: user.id,
+        username: user.username,
+        name: user.name,
+        avatar_url: user.avatar_url,
+        contact,
+        teammate: relation != Relation::Public,
+        created_at: user.created_at,
+    })))
+}
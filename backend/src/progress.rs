@@ -0,0 +1,42 @@
+use chrono::Utc;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+// 重新计算任务进度；与最近一次自动快照不同时写入新的 progress 记录。
+// 需要在修改子任务的同一事务中调用，任务行加锁保证并发修改时快照不重复。
+pub async fn sync_task_progress(conn: &mut PgConnection, task_id: Uuid) -> Result<i32, sqlx::Error> {
+    sqlx::query!("SELECT id FROM tasks WHERE id = $1 FOR UPDATE", task_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    let percent = sqlx::query_scalar!(
+        "SELECT percent FROM task_computed_progress WHERE task_id = $1",
+        task_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .flatten()
+    .unwrap_or(0);
+
+    let last = sqlx::query_scalar!(
+        "SELECT percent FROM progress WHERE task_id = $1 AND source = 'auto' ORDER BY created_at DESC LIMIT 1",
+        task_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if last != Some(percent) {
+        sqlx::query!(
+            "INSERT INTO progress (id, task_id, content, percent, created_at, source) VALUES ($1, $2, $3, $4, $5, 'auto')",
+            Uuid::new_v4(),
+            task_id,
+            format!("子任务完成度更新为 {}%", percent),
+            percent,
+            Utc::now()
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(percent)
+}
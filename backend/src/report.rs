@@ -0,0 +1,321 @@
+use chrono::{DateTime, Duration, Utc};
+use printpdf::{IndirectFontRef, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+// 即将到期的子任务：报告结束后多少天内的 DDL
+const UPCOMING_DAYS: i64 = 7;
+
+// A4 版面（毫米）
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 20.0;
+const PT_TO_MM: f32 = 0.3528;
+
+// 报告内容按块组织，Markdown 与 PDF 共用同一份结构
+pub enum Block {
+    Heading(u8, String),
+    Paragraph(String),
+    Item(String),
+}
+
+pub struct WeeklyReport {
+    pub title: String,
+    pub blocks: Vec<Block>,
+}
+
+pub async fn build_weekly_report(
+    pool: &PgPool,
+    task_id: Uuid,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Option<WeeklyReport>, sqlx::Error> {
+    let task = match sqlx::query!("SELECT title FROM tasks WHERE id = $1", task_id)
+        .fetch_optional(pool)
+        .await?
+    {
+        Some(task) => task,
+        None => return Ok(None),
+    };
+
+    let progress = sqlx::query!(
+        r#"
+        SELECT content, percent, created_at FROM progress
+        WHERE task_id = $1 AND created_at >= $2 AND created_at < $3
+        ORDER BY created_at ASC
+        "#,
+        task_id,
+        from,
+        to
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let added = sqlx::query!(
+        r#"
+        SELECT st.title, st.created_at, COALESCE(NULLIF(u.name, ''), u.username) as assignee
+        FROM sub_tasks st
+        LEFT JOIN users u ON st.assignee_id = u.id
+        WHERE st.task_id = $1 AND st.created_at >= $2 AND st.created_at < $3
+        ORDER BY st.created_at ASC
+        "#,
+        task_id,
+        from,
+        to
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let completed = sqlx::query!(
+        r#"
+        SELECT st.title, st.completed_at as "completed_at!", COALESCE(NULLIF(u.name, ''), u.username) as assignee
+        FROM sub_tasks st
+        LEFT JOIN users u ON st.assignee_id = u.id
+        WHERE st.task_id = $1 AND st.completed_at >= $2 AND st.completed_at < $3
+        ORDER BY st.completed_at ASC
+        "#,
+        task_id,
+        from,
+        to
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let hours = sqlx::query!(
+        r#"
+        SELECT COALESCE(NULLIF(u.name, ''), u.username) as "member!", SUM(tl.hours)::FLOAT8 as "hours!"
+        FROM time_logs tl
+        JOIN users u ON tl.user_id = u.id
+        WHERE tl.task_id = $1 AND tl.logged_at >= $2 AND tl.logged_at < $3
+        GROUP BY u.id, u.name, u.username
+        ORDER BY 2 DESC
+        "#,
+        task_id,
+        from,
+        to
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let upcoming = sqlx::query!(
+        r#"
+        SELECT st.title, st.due_date as "due_date!", COALESCE(NULLIF(u.name, ''), u.username) as assignee
+        FROM sub_tasks st
+        LEFT JOIN users u ON st.assignee_id = u.id
+        WHERE st.task_id = $1 AND st.status <> '已完成' AND st.due_date < $2
+        ORDER BY st.due_date ASC
+        "#,
+        task_id,
+        to + Duration::days(UPCOMING_DAYS)
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let date = |d: DateTime<Utc>| d.format("%Y-%m-%d").to_string();
+    let who = |name: &Option<String>| name.clone().unwrap_or_else(|| "未分配".to_string());
+
+    let mut blocks = vec![
+        Block::Heading(1, format!("{} 周报", task.title)),
+        Block::Paragraph(format!("统计区间：{} 至 {}", date(from), date(to))),
+    ];
+
+    blocks.push(Block::Heading(2, "进度记录".to_string()));
+    if progress.is_empty() {
+        blocks.push(Block::Paragraph("本周无进度记录".to_string()));
+    }
+    for p in &progress {
+        blocks.push(Block::Item(format!(
+            "{} {}%：{}",
+            date(p.created_at),
+            p.percent,
+            p.content
+        )));
+    }
+
+    blocks.push(Block::Heading(2, "完成的子任务".to_string()));
+    if completed.is_empty() {
+        blocks.push(Block::Paragraph("本周没有完成的子任务".to_string()));
+    }
+    for st in &completed {
+        blocks.push(Block::Item(format!(
+            "{} {}（{}）",
+            date(st.completed_at),
+            st.title,
+            who(&st.assignee)
+        )));
+    }
+
+    blocks.push(Block::Heading(2, "新增的子任务".to_string()));
+    if added.is_empty() {
+        blocks.push(Block::Paragraph("本周没有新增子任务".to_string()));
+    }
+    for st in &added {
+        blocks.push(Block::Item(format!(
+            "{} {}（{}）",
+            date(st.created_at),
+            st.title,
+            who(&st.assignee)
+        )));
+    }
+
+    // 成员分工：完成的子任务与工时按成员汇总
+    blocks.push(Block::Heading(2, "成员分工".to_string()));
+    let mut members: Vec<String> = hours.iter().map(|h| h.member.clone()).collect();
+    for st in &completed {
+        let name = who(&st.assignee);
+        if !members.contains(&name) {
+            members.push(name);
+        }
+    }
+    if members.is_empty() {
+        blocks.push(Block::Paragraph("本周暂无成员记录".to_string()));
+    }
+    for member in &members {
+        let logged = hours
+            .iter()
+            .find(|h| &h.member == member)
+            .map_or(0.0, |h| h.hours);
+        let done: Vec<&str> = completed
+            .iter()
+            .filter(|st| &who(&st.assignee) == member)
+            .map(|st| st.title.as_str())
+            .collect();
+        let done = if done.is_empty() {
+            "无".to_string()
+        } else {
+            done.join("、")
+        };
+        blocks.push(Block::Item(format!(
+            "{}：工时 {:.1} 小时，完成 {}",
+            member, logged, done
+        )));
+    }
+    let total_hours: f64 = hours.iter().map(|h| h.hours).sum();
+    blocks.push(Block::Paragraph(format!("本周合计工时 {:.1} 小时", total_hours)));
+
+    blocks.push(Block::Heading(2, "即将到期".to_string()));
+    if upcoming.is_empty() {
+        blocks.push(Block::Paragraph(format!(
+            "未来 {} 天内没有到期的子任务",
+            UPCOMING_DAYS
+        )));
+    }
+    for st in &upcoming {
+        let overdue = if st.due_date < to { "（已逾期）" } else { "" };
+        blocks.push(Block::Item(format!(
+            "{} {}（{}）{}",
+            date(st.due_date),
+            st.title,
+            who(&st.assignee),
+            overdue
+        )));
+    }
+
+    Ok(Some(WeeklyReport {
+        title: format!("{} 周报", task.title),
+        blocks,
+    }))
+}
+
+impl WeeklyReport {
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let mut in_list = false;
+        for block in &self.blocks {
+            // 列表结束后空一行
+            if in_list && !matches!(block, Block::Item(_)) {
+                out.push('\n');
+            }
+            match block {
+                Block::Heading(level, text) => {
+                    writeln!(out, "{} {}\n", "#".repeat(*level as usize), text).ok();
+                }
+                Block::Paragraph(text) => {
+                    writeln!(out, "{}\n", text).ok();
+                }
+                Block::Item(text) => {
+                    writeln!(out, "- {}", text).ok();
+                }
+            }
+            in_list = matches!(block, Block::Item(_));
+        }
+        out
+    }
+
+    // 中文需要嵌入支持 CJK 的 TrueType 字体，由调用方读取字体文件后传入
+    pub fn to_pdf(&self, font_data: &[u8]) -> Result<Vec<u8>, String> {
+        let (doc, page, layer) =
+            PdfDocument::new(&self.title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+        let font = doc
+            .add_external_font(font_data)
+            .map_err(|e| format!("字体加载失败: {}", e))?;
+
+        let mut writer = PdfWriter {
+            layer: doc.get_page(page).get_layer(layer),
+            doc: &doc,
+            font,
+            y: PAGE_HEIGHT - MARGIN,
+        };
+        for block in &self.blocks {
+            match block {
+                Block::Heading(1, text) => writer.write(text, 18.0, 0.0, 4.0),
+                Block::Heading(_, text) => writer.write(text, 14.0, 0.0, 4.0),
+                Block::Paragraph(text) => writer.write(text, 11.0, 0.0, 1.5),
+                Block::Item(text) => writer.write(&format!("· {}", text), 11.0, 4.0, 0.5),
+            }
+        }
+        drop(writer);
+        doc.save_to_bytes()
+            .map_err(|e| format!("PDF 生成失败: {}", e))
+    }
+}
+
+struct PdfWriter<'a> {
+    doc: &'a PdfDocumentReference,
+    layer: PdfLayerReference,
+    font: IndirectFontRef,
+    // 下一行文字顶部的纵坐标
+    y: f32,
+}
+
+impl PdfWriter<'_> {
+    // 按估算宽度折行写入，写不下时换页
+    fn write(&mut self, text: &str, size: f32, indent: f32, space_before: f32) {
+        let line_height = size * PT_TO_MM * 1.5;
+        let max_width = PAGE_WIDTH - 2.0 * MARGIN - indent;
+        self.y -= space_before;
+        for line in wrap(text, size, max_width) {
+            if self.y - line_height < MARGIN {
+                let (page, layer) = self
+                    .doc
+                    .add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+                self.layer = self.doc.get_page(page).get_layer(layer);
+                self.y = PAGE_HEIGHT - MARGIN;
+            }
+            self.y -= line_height;
+            self.layer
+                .use_text(line, size, Mm(MARGIN + indent), Mm(self.y), &self.font);
+        }
+    }
+}
+
+// 估算字符宽度：ASCII 约半个字号宽，中文等全角字符约一个字号宽
+fn wrap(text: &str, size: f32, max_width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut width = 0.0;
+    for c in text.chars() {
+        let w = if c.is_ascii() { 0.55 } else { 1.0 } * size * PT_TO_MM;
+        if width + w > max_width && !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+            width = 0.0;
+        }
+        line.push(c);
+        width += w;
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
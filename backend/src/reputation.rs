@@ -0,0 +1,138 @@
+use crate::evaluation;
+use crate::lifecycle;
+use crate::membership;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+// 公开资料只包含展示信息和履历，不含联系方式
+#[derive(Debug, Serialize)]
+pub struct PublicProfile {
+    pub user_id: Uuid,
+    pub username: String,
+    // 未设置姓名时为用户名
+    pub display_name: String,
+    pub avatar_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub completed_tasks: Vec<ProfileTask>,
+    pub roles: Vec<ProfileRole>,
+    // 互评平均分（百分制），没有可公开的评价时为空
+    pub average_rating: Option<f64>,
+    pub rating_count: i64,
+    // 有截止时间的子任务中按时完成的比例（百分制），没有记录时为空
+    pub reliability: Option<f64>,
+    pub on_time_sub_tasks: i64,
+    pub due_sub_tasks: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileTask {
+    pub task_id: Uuid,
+    pub title: String,
+    pub status: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileRole {
+    pub task_id: Uuid,
+    pub task_title: String,
+    pub role_name: String,
+    // 是否仍担任该职责
+    pub current: bool,
+}
+
+pub async fn public_profile(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<Option<PublicProfile>, sqlx::Error> {
+    let Some(user) = sqlx::query!(
+        "SELECT id, username, name, avatar_url, created_at FROM users WHERE id = $1",
+        user_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    // 参与或发布的、已结束或已归档的任务
+    let completed_tasks = sqlx::query_as!(
+        ProfileTask,
+        r#"
+        SELECT t.id as task_id, t.title, t.status as "status!"
+        FROM tasks t
+        WHERE t.deleted_at IS NULL AND t.status IN ($2, $3)
+            AND (t.creator_id = $1 OR EXISTS (
+                SELECT 1 FROM task_membership_events e
+                WHERE e.task_id = t.id AND e.user_id = $1 AND e.event = $4
+            ))
+        ORDER BY t.created_at DESC
+        "#,
+        user_id,
+        lifecycle::FINISHED,
+        lifecycle::ARCHIVED,
+        membership::EVENT_JOINED
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    // 草稿任务不公开
+    let roles = sqlx::query_as!(
+        ProfileRole,
+        r#"
+        SELECT DISTINCT ON (t.id, e.role_name)
+            t.id as task_id, t.title as task_title, e.role_name,
+            EXISTS (
+                SELECT 1 FROM task_role_members m
+                JOIN task_roles tr ON tr.id = m.role_id
+                WHERE tr.task_id = t.id AND tr.role_name = e.role_name AND m.user_id = $1
+            ) as "current!"
+        FROM task_membership_events e
+        JOIN tasks t ON t.id = e.task_id
+        WHERE e.user_id = $1 AND e.event = $2 AND t.deleted_at IS NULL AND t.status <> $3
+        ORDER BY t.id, e.role_name
+        "#,
+        user_id,
+        membership::EVENT_JOINED,
+        lifecycle::DRAFT
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let (average_rating, rating_count) = evaluation::user_rating(conn, user_id).await?;
+
+    // 已过截止时间仍未完成的子任务也计入分母
+    let due = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE st.status = '已完成' AND st.completed_at <= st.due_date) as "on_time!",
+            COUNT(*) FILTER (WHERE st.status = '已完成' OR st.due_date < NOW()) as "total!"
+        FROM sub_tasks st
+        JOIN tasks t ON t.id = st.task_id
+        WHERE st.assignee_id = $1 AND st.due_date IS NOT NULL AND t.deleted_at IS NULL
+        "#,
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(Some(PublicProfile {
+        user_id: user.id,
+        display_name: user
+            .name
+            .filter(|n| !n.trim().is_empty())
+            .unwrap_or_else(|| user.username.clone()),
+        username: user.username,
+        avatar_url: user.avatar_url,
+        created_at: user.created_at,
+        completed_tasks,
+        roles,
+        average_rating: average_rating.map(|r| (r * 10.0).round() / 10.0),
+        rating_count,
+        reliability: (due.total > 0)
+            .then(|| (due.on_time as f64 / due.total as f64 * 1000.0).round() / 10.0),
+        on_time_sub_tasks: due.on_time,
+        due_sub_tasks: due.total,
+    }))
+}
@@ -0,0 +1,147 @@
+use crate::models::SubTaskTemplate;
+use crate::progress::sync_task_progress;
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+// 调度间隔（秒）
+const TICK_SECONDS: u64 = 60;
+// 单个模板每轮最多补生成的次数，避免长时间停机后一次性生成过多
+const MAX_CATCH_UP: usize = 50;
+// 软删除任务的保留天数，期间发布者可以恢复
+pub const TASK_RETENTION_DAYS: i64 = 30;
+// 每轮最多彻底删除的任务数
+const PURGE_BATCH: i64 = 100;
+
+pub fn start(pool: PgPool) {
+    actix_web::rt::spawn(async move {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(TICK_SECONDS));
+        loop {
+            ticker.tick().await;
+            if let Err(e) = materialize_recurring_sub_tasks(&pool).await {
+                eprintln!("Failed to materialize recurring sub-tasks: {:?}", e);
+            }
+            if let Err(e) = purge_deleted_tasks(&pool).await {
+                eprintln!("Failed to purge deleted tasks: {:?}", e);
+            }
+        }
+    });
+}
+
+// 为到期的周期模板生成子任务。
+// 模板行在事务内加锁，生成的子任务与 next_run_at 的推进一起提交；
+// 再加上 sub_tasks (template_id, occurrence_at) 的唯一约束，重启或多实例都不会重复生成。
+pub async fn materialize_recurring_sub_tasks(pool: &PgPool) -> Result<(), sqlx::Error> {
+    let now = Utc::now();
+    let mut tx = pool.begin().await?;
+    let templates = sqlx::query_as::<_, SubTaskTemplate>(
+        r#"
+        SELECT * FROM sub_task_templates
+        WHERE active AND next_run_at <= $1
+            AND task_id IN (SELECT id FROM tasks WHERE deleted_at IS NULL)
+        FOR UPDATE SKIP LOCKED
+        "#,
+    )
+    .bind(now)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for template in templates {
+        let interval = Duration::days(template.interval_days as i64);
+        let mut run_at = template.next_run_at;
+        let mut generated = 0;
+        while run_at <= now
+            && generated < MAX_CATCH_UP
+            && template.until_at.is_none_or(|until| run_at <= until)
+        {
+            let due_date = run_at + Duration::hours(template.due_offset_hours as i64);
+            sqlx::query!(
+                r#"
+                INSERT INTO sub_tasks (id, task_id, title, description, assignee_id, created_at, due_date, status, priority, labels, template_id, occurrence_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, '未开始', $8, $9, $10, $11)
+                ON CONFLICT (template_id, occurrence_at) DO NOTHING
+                "#,
+                Uuid::new_v4(),
+                template.task_id,
+                template.title,
+                template.description,
+                template.assignee_id,
+                now,
+                due_date,
+                template.priority,
+                &template.labels,
+                template.id,
+                run_at
+            )
+            .execute(&mut *tx)
+            .await?;
+            run_at += interval;
+            generated += 1;
+        }
+
+        let active = template.until_at.is_none_or(|until| run_at <= until);
+        sqlx::query!(
+            "UPDATE sub_task_templates SET next_run_at = $1, active = $2 WHERE id = $3",
+            run_at,
+            active,
+            template.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if generated > 0 {
+            sync_task_progress(&mut tx, template.task_id).await?;
+        }
+    }
+
+    tx.commit().await
+}
+
+// 彻底删除超过保留期的软删除任务。
+// task_roles、progress、evaluations 的外键没有 ON DELETE CASCADE，需要先手动删除；
+// 其余子表随任务级联删除。全部在同一事务中完成。
+pub async fn purge_deleted_tasks(pool: &PgPool) -> Result<(), sqlx::Error> {
+    let cutoff = Utc::now() - Duration::days(TASK_RETENTION_DAYS);
+    let mut tx = pool.begin().await?;
+    let task_ids: Vec<Uuid> = sqlx::query_scalar!(
+        r#"
+        SELECT id FROM tasks
+        WHERE deleted_at < $1
+        ORDER BY deleted_at ASC
+        LIMIT $2
+        FOR UPDATE SKIP LOCKED
+        "#,
+        cutoff,
+        PURGE_BATCH
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    if task_ids.is_empty() {
+        return Ok(());
+    }
+
+    sqlx::query!("DELETE FROM task_roles WHERE task_id = ANY($1)", &task_ids)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM progress WHERE task_id = ANY($1)", &task_ids)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM evaluations WHERE task_id = ANY($1)", &task_ids)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM tasks WHERE id = ANY($1)", &task_ids)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    // 数据库删除成功后再清理附件文件
+    for task_id in task_ids {
+        let dir = format!("./static/attachments/{}", task_id);
+        if let Err(e) = tokio::fs::remove_dir_all(&dir).await
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            eprintln!("Failed to remove attachments of task {}: {:?}", task_id, e);
+        }
+    }
+    Ok(())
+}
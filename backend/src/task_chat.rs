@@ -0,0 +1,429 @@
+use crate::membership;
+use crate::mentions::{self, Mention};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgConnection;
+use sqlx::types::Json;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// 单条消息的最大字数
+pub const MAX_CONTENT_CHARS: usize = 2000;
+// 表情回应的最大字符数（部分表情由多个码点组成）
+pub const MAX_EMOJI_CHARS: usize = 8;
+
+pub enum ChatError {
+    TaskNotFound,
+    NotMember,
+    MessageNotFound,
+    EmptyContent,
+    TooLong,
+    // 只有发送人可以编辑
+    NotSender,
+    // 发送人或任务发布者可以删除
+    CannotDelete,
+    Deleted,
+    InvalidEmoji,
+    Db(sqlx::Error),
+}
+
+impl From<sqlx::Error> for ChatError {
+    fn from(e: sqlx::Error) -> Self {
+        ChatError::Db(e)
+    }
+}
+
+impl ChatError {
+    pub fn message(&self) -> String {
+        match self {
+            ChatError::TaskNotFound => "任务不存在".to_string(),
+            ChatError::NotMember => "只有任务成员可以参与聊天".to_string(),
+            ChatError::MessageNotFound => "消息不存在".to_string(),
+            ChatError::EmptyContent => "消息内容不能为空".to_string(),
+            ChatError::TooLong => format!("消息不能超过{}字", MAX_CONTENT_CHARS),
+            ChatError::NotSender => "只能编辑自己发送的消息".to_string(),
+            ChatError::CannotDelete => "只有发送人或任务发布者可以删除消息".to_string(),
+            ChatError::Deleted => "消息已删除".to_string(),
+            ChatError::InvalidEmoji => "表情无效".to_string(),
+            ChatError::Db(e) => e.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReactionCount {
+    pub emoji: String,
+    pub count: i64,
+    // 回应过的用户，前端据此判断自己是否已回应
+    pub user_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChatMessage {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub sender_id: Uuid,
+    pub sender_username: String,
+    pub sender_name: Option<String>,
+    // 已删除的消息不返回内容
+    pub content: Option<String>,
+    pub parent_id: Option<Uuid>,
+    pub reply_count: i64,
+    pub created_at: DateTime<Utc>,
+    // 不为空表示编辑过
+    pub edited_at: Option<DateTime<Utc>>,
+    pub deleted: bool,
+    pub reactions: Vec<ReactionCount>,
+    pub mentions: Vec<Mention>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MessageEdit {
+    // 编辑前的内容
+    pub content: String,
+    pub edited_at: DateTime<Utc>,
+}
+
+struct MessageRow {
+    task_id: Uuid,
+    sender_id: Uuid,
+    parent_id: Option<Uuid>,
+    deleted: bool,
+    mentions: Json<Vec<Mention>>,
+}
+
+fn validate_content(content: &str) -> Result<&str, ChatError> {
+    let content = content.trim();
+    if content.is_empty() {
+        return Err(ChatError::EmptyContent);
+    }
+    if content.chars().count() > MAX_CONTENT_CHARS {
+        return Err(ChatError::TooLong);
+    }
+    Ok(content)
+}
+
+// 聊天室成员：任务当前成员和发布者，推送事件也只发给他们
+pub async fn room_members(conn: &mut PgConnection, task_id: Uuid) -> Result<Vec<Uuid>, ChatError> {
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM tasks WHERE id = $1 AND deleted_at IS NULL) as "exists!""#,
+        task_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if !exists {
+        return Err(ChatError::TaskNotFound);
+    }
+    Ok(membership::current_team(conn, task_id).await?)
+}
+
+// 校验 user_id 是聊天室成员，返回全部成员
+pub async fn check_member(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    user_id: Uuid,
+) -> Result<Vec<Uuid>, ChatError> {
+    let members = room_members(conn, task_id).await?;
+    if !members.contains(&user_id) {
+        return Err(ChatError::NotMember);
+    }
+    Ok(members)
+}
+
+async fn find_message(conn: &mut PgConnection, message_id: Uuid) -> Result<MessageRow, ChatError> {
+    sqlx::query_as!(
+        MessageRow,
+        r#"
+        SELECT task_id, sender_id, parent_id, deleted_at IS NOT NULL as "deleted!",
+            mentions as "mentions: Json<Vec<Mention>>"
+        FROM task_messages WHERE id = $1
+        "#,
+        message_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(ChatError::MessageNotFound)
+}
+
+// 按 id 读取消息及其回复数、表情回应，顺序与 ids 一致
+pub async fn load(conn: &mut PgConnection, ids: &[Uuid]) -> Result<Vec<ChatMessage>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT m.id, m.task_id, m.sender_id, u.username as sender_username, u.name as sender_name,
+            m.content, m.parent_id, m.created_at, m.edited_at, m.deleted_at IS NOT NULL as "deleted!",
+            m.mentions as "mentions: Json<Vec<Mention>>",
+            (SELECT COUNT(*) FROM task_messages r
+                WHERE r.parent_id = m.id AND r.deleted_at IS NULL) as "reply_count!"
+        FROM task_messages m JOIN users u ON u.id = m.sender_id
+        WHERE m.id = ANY($1)
+        "#,
+        ids
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let reaction_rows = sqlx::query!(
+        r#"
+        SELECT message_id, emoji, COUNT(*) as "count!",
+            ARRAY_AGG(user_id ORDER BY created_at) as "user_ids!"
+        FROM task_message_reactions
+        WHERE message_id = ANY($1)
+        GROUP BY message_id, emoji
+        ORDER BY MIN(created_at)
+        "#,
+        ids
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let mut reactions: HashMap<Uuid, Vec<ReactionCount>> = HashMap::new();
+    for r in reaction_rows {
+        reactions
+            .entry(r.message_id)
+            .or_default()
+            .push(ReactionCount {
+                emoji: r.emoji,
+                count: r.count,
+                user_ids: r.user_ids,
+            });
+    }
+    let mut messages: HashMap<Uuid, ChatMessage> = rows
+        .into_iter()
+        .map(|r| {
+            let message = ChatMessage {
+                id: r.id,
+                task_id: r.task_id,
+                sender_id: r.sender_id,
+                sender_username: r.sender_username,
+                sender_name: r.sender_name,
+                content: (!r.deleted).then_some(r.content),
+                parent_id: r.parent_id,
+                reply_count: r.reply_count,
+                created_at: r.created_at,
+                edited_at: r.edited_at,
+                deleted: r.deleted,
+                reactions: if r.deleted {
+                    Vec::new()
+                } else {
+                    reactions.remove(&r.id).unwrap_or_default()
+                },
+                mentions: if r.deleted { Vec::new() } else { r.mentions.0 },
+            };
+            (r.id, message)
+        })
+        .collect();
+    Ok(ids.iter().filter_map(|id| messages.remove(id)).collect())
+}
+
+// 发送消息；回复一条回复时挂到它的根消息下，回复只有一层
+pub async fn post(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    sender_id: Uuid,
+    content: &str,
+    parent_id: Option<Uuid>,
+) -> Result<Uuid, ChatError> {
+    check_member(conn, task_id, sender_id).await?;
+    let content = validate_content(content)?;
+    let parent_id = match parent_id {
+        Some(parent_id) => {
+            let parent = find_message(conn, parent_id).await?;
+            if parent.task_id != task_id {
+                return Err(ChatError::MessageNotFound);
+            }
+            if parent.deleted {
+                return Err(ChatError::Deleted);
+            }
+            Some(parent.parent_id.unwrap_or(parent_id))
+        }
+        None => None,
+    };
+    let mentions = mentions::resolve(conn, task_id, content).await?;
+    let id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO task_messages (id, task_id, sender_id, content, parent_id, created_at, mentions)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        id,
+        task_id,
+        sender_id,
+        content,
+        parent_id,
+        Utc::now(),
+        Json(&mentions) as _
+    )
+    .execute(&mut *conn)
+    .await?;
+    mentions::notify_mentioned(conn, task_id, Some(sender_id), &mentions, &[], "聊天").await?;
+    Ok(id)
+}
+
+// 编辑消息，编辑前的内容写入编辑历史；返回消息所在任务
+pub async fn edit(
+    conn: &mut PgConnection,
+    message_id: Uuid,
+    user_id: Uuid,
+    content: &str,
+) -> Result<Uuid, ChatError> {
+    let message = find_message(conn, message_id).await?;
+    check_member(conn, message.task_id, user_id).await?;
+    if message.sender_id != user_id {
+        return Err(ChatError::NotSender);
+    }
+    if message.deleted {
+        return Err(ChatError::Deleted);
+    }
+    let content = validate_content(content)?;
+    let now = Utc::now();
+    let updated = sqlx::query!(
+        r#"
+        WITH old AS (SELECT content FROM task_messages WHERE id = $1 FOR UPDATE)
+        INSERT INTO task_message_edits (id, message_id, content, edited_at)
+        SELECT $2, $1, old.content, $3 FROM old WHERE old.content <> $4
+        "#,
+        message_id,
+        Uuid::new_v4(),
+        now,
+        content
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+    // 内容没有变化时不算编辑；编辑后新提到的成员才会收到通知
+    if updated > 0 {
+        let mentions = mentions::resolve(conn, message.task_id, content).await?;
+        sqlx::query!(
+            "UPDATE task_messages SET content = $2, edited_at = $3, mentions = $4 WHERE id = $1",
+            message_id,
+            content,
+            now,
+            Json(&mentions) as _
+        )
+        .execute(&mut *conn)
+        .await?;
+        mentions::notify_mentioned(
+            conn,
+            message.task_id,
+            Some(user_id),
+            &mentions,
+            &message.mentions,
+            "聊天",
+        )
+        .await?;
+    }
+    Ok(message.task_id)
+}
+
+// 软删除消息；返回消息所在任务
+pub async fn delete(
+    conn: &mut PgConnection,
+    message_id: Uuid,
+    user_id: Uuid,
+) -> Result<Uuid, ChatError> {
+    let message = find_message(conn, message_id).await?;
+    check_member(conn, message.task_id, user_id).await?;
+    if message.deleted {
+        return Err(ChatError::Deleted);
+    }
+    if message.sender_id != user_id {
+        let is_creator = sqlx::query_scalar!(
+            r#"SELECT EXISTS (SELECT 1 FROM tasks WHERE id = $1 AND creator_id = $2) as "is_creator!""#,
+            message.task_id,
+            user_id
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        if !is_creator {
+            return Err(ChatError::CannotDelete);
+        }
+    }
+    sqlx::query!(
+        "UPDATE task_messages SET deleted_at = $2 WHERE id = $1",
+        message_id,
+        Utc::now()
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(message.task_id)
+}
+
+// 添加或取消表情回应；返回消息所在任务
+pub async fn react(
+    conn: &mut PgConnection,
+    message_id: Uuid,
+    user_id: Uuid,
+    emoji: &str,
+    add: bool,
+) -> Result<Uuid, ChatError> {
+    let emoji = emoji.trim();
+    let chars = emoji.chars().count();
+    if chars == 0 || chars > MAX_EMOJI_CHARS || emoji.chars().any(|c| c.is_alphanumeric()) {
+        return Err(ChatError::InvalidEmoji);
+    }
+    let message = find_message(conn, message_id).await?;
+    check_member(conn, message.task_id, user_id).await?;
+    if message.deleted {
+        return Err(ChatError::Deleted);
+    }
+    if add {
+        sqlx::query!(
+            r#"
+            INSERT INTO task_message_reactions (message_id, user_id, emoji, created_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (message_id, user_id, emoji) DO NOTHING
+            "#,
+            message_id,
+            user_id,
+            emoji,
+            Utc::now()
+        )
+        .execute(&mut *conn)
+        .await?;
+    } else {
+        sqlx::query!(
+            "DELETE FROM task_message_reactions WHERE message_id = $1 AND user_id = $2 AND emoji = $3",
+            message_id,
+            user_id,
+            emoji
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(message.task_id)
+}
+
+// 消息的编辑历史，从早到晚
+pub async fn edit_history(
+    conn: &mut PgConnection,
+    message_id: Uuid,
+    user_id: Uuid,
+) -> Result<Vec<MessageEdit>, ChatError> {
+    let message = find_message(conn, message_id).await?;
+    check_member(conn, message.task_id, user_id).await?;
+    if message.deleted {
+        return Err(ChatError::Deleted);
+    }
+    Ok(sqlx::query_as!(
+        MessageEdit,
+        "SELECT content, edited_at FROM task_message_edits WHERE message_id = $1 ORDER BY edited_at",
+        message_id
+    )
+    .fetch_all(&mut *conn)
+    .await?)
+}
+
+// 某条消息的全部回复，从早到晚
+pub async fn replies(
+    conn: &mut PgConnection,
+    message_id: Uuid,
+    user_id: Uuid,
+) -> Result<Vec<ChatMessage>, ChatError> {
+    let message = find_message(conn, message_id).await?;
+    check_member(conn, message.task_id, user_id).await?;
+    let ids = sqlx::query_scalar!(
+        "SELECT id FROM task_messages WHERE parent_id = $1 ORDER BY created_at, id",
+        message_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    Ok(load(conn, &ids).await?)
+}
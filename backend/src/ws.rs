@@ -1,26 +1,177 @@
-use actix::{Actor, StreamHandler};
+use crate::auth;
+use actix::prelude::*;
 use actix_web::{Error, HttpRequest, HttpResponse, web};
 use actix_web_actors::ws;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
 
-struct MyWs;
+// 心跳间隔，超过 CLIENT_TIMEOUT 没有收到客户端消息就断开
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
 
-impl Actor for MyWs {
+// 推送给客户端的事件，内容为 JSON 文本
+#[derive(Message, Clone)]
+#[rtype(result = "()")]
+pub struct Event(pub String);
+
+#[derive(Message)]
+#[rtype(result = "()")]
+struct Connect {
+    id: Uuid,
+    user_id: Uuid,
+    addr: Recipient<Event>,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+struct Disconnect {
+    id: Uuid,
+}
+
+// 推送给指定用户的所有连接（同一用户可能同时打开多个页面）
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SendToUsers {
+    pub user_ids: Vec<Uuid>,
+    pub event: String,
+}
+
+// 记录在线连接，按用户推送事件
+#[derive(Default)]
+pub struct WsServer {
+    // 连接 id -> (用户 id, 连接)
+    sessions: HashMap<Uuid, (Uuid, Recipient<Event>)>,
+}
+
+impl Actor for WsServer {
+    type Context = Context<Self>;
+}
+
+impl Handler<Connect> for WsServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: Connect, _: &mut Context<Self>) {
+        self.sessions.insert(msg.id, (msg.user_id, msg.addr));
+    }
+}
+
+impl Handler<Disconnect> for WsServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: Disconnect, _: &mut Context<Self>) {
+        self.sessions.remove(&msg.id);
+    }
+}
+
+impl Handler<SendToUsers> for WsServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: SendToUsers, _: &mut Context<Self>) {
+        for (user_id, addr) in self.sessions.values() {
+            if msg.user_ids.contains(user_id) {
+                addr.do_send(Event(msg.event.clone()));
+            }
+        }
+    }
+}
+
+// 向用户推送事件，不在线的用户直接忽略
+pub fn push(server: &Addr<WsServer>, user_ids: Vec<Uuid>, event: serde_json::Value) {
+    server.do_send(SendToUsers {
+        user_ids,
+        event: event.to_string(),
+    });
+}
+
+struct WsSession {
+    id: Uuid,
+    user_id: Uuid,
+    hb: Instant,
+    server: Addr<WsServer>,
+}
+
+impl WsSession {
+    fn heartbeat(&self, ctx: &mut ws::WebsocketContext<Self>) {
+        ctx.run_interval(HEARTBEAT_INTERVAL, |act, ctx| {
+            if Instant::now().duration_since(act.hb) > CLIENT_TIMEOUT {
+                ctx.stop();
+                return;
+            }
+            ctx.ping(b"");
+        });
+    }
+}
+
+impl Actor for WsSession {
     type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.heartbeat(ctx);
+        self.server.do_send(Connect {
+            id: self.id,
+            user_id: self.user_id,
+            addr: ctx.address().recipient(),
+        });
+    }
+
+    fn stopping(&mut self, _: &mut Self::Context) -> Running {
+        self.server.do_send(Disconnect { id: self.id });
+        Running::Stop
+    }
+}
+
+impl Handler<Event> for WsSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: Event, ctx: &mut Self::Context) {
+        ctx.text(msg.0);
+    }
 }
 
-impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for MyWs {
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsSession {
     fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
         match msg {
-            Ok(ws::Message::Ping(msg)) => ctx.pong(&msg),
-            Ok(ws::Message::Text(text)) => ctx.text(text),
-            Ok(ws::Message::Binary(bin)) => ctx.binary(bin),
+            Ok(ws::Message::Ping(msg)) => {
+                self.hb = Instant::now();
+                ctx.pong(&msg);
+            }
+            // 消息通过 HTTP 接口发送，连接只用于接收推送
+            Ok(ws::Message::Pong(_)) | Ok(ws::Message::Text(_)) => self.hb = Instant::now(),
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            Err(_) => ctx.stop(),
             _ => (),
         }
     }
 }
 
-pub async fn ws_index(req: HttpRequest, stream: web::Payload) -> Result<HttpResponse, Error> {
-    let resp = ws::start(MyWs {}, &req, stream);
-    println!("{:?}", resp);
-    resp
+#[derive(Debug, Deserialize)]
+pub struct WsQuery {
+    // 浏览器建立 WebSocket 时不能带 Authorization 头，登录 token 放在查询参数里
+    pub token: String,
+}
+
+pub async fn ws_index(
+    req: HttpRequest,
+    stream: web::Payload,
+    query: web::Query<WsQuery>,
+    server: web::Data<Addr<WsServer>>,
+) -> Result<HttpResponse, Error> {
+    let Some(user_id) = auth::decode_user_id(&query.token) else {
+        return Ok(HttpResponse::Unauthorized().json("Invalid token"));
+    };
+    ws::start(
+        WsSession {
+            id: Uuid::new_v4(),
+            user_id,
+            hb: Instant::now(),
+            server: server.get_ref().clone(),
+        },
+        &req,
+        stream,
+    )
 }
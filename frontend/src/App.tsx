@@ -11,6 +11,10 @@ import Chat from "./pages/Chat";
 import Profile from "./pages/Profile";
 import Management from "./pages/Management";
 import TaskDashboard from './pages/TaskDashboard';
+import InviteAccept from './pages/InviteAccept';
+import Grades from './pages/Grades';
+import UserProfile from './pages/UserProfile';
+import Messages from './pages/Messages';
 
 const App: React.FC = () => (
   <Router>
@@ -26,6 +30,10 @@ const App: React.FC = () => (
       <Route path="/profile" element={<Profile />} />
       <Route path="/management" element={<Management />} />
       <Route path="/task/:id/dashboard" element={<TaskDashboard />} />
+      <Route path="/invite/:token" element={<InviteAccept />} />
+      <Route path="/grades" element={<Grades />} />
+      <Route path="/user/:id" element={<UserProfile />} />
+      <Route path="/messages" element={<Messages />} />
       <Route path="*" element={<Navigate to="/login" />} />
     </Routes>
   </Router>
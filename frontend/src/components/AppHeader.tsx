@@ -1,6 +1,6 @@
 import React, { useEffect, useState } from 'react';
-import { Layout, Menu, Avatar, Dropdown, message, Button } from 'antd';
-import { UserOutlined } from '@ant-design/icons';
+import { Layout, Menu, Avatar, Dropdown, message, Button, Badge, Popover, List } from 'antd';
+import { UserOutlined, BellOutlined } from '@ant-design/icons';
 import { useNavigate, Link, useLocation } from 'react-router-dom';
 import axios from 'axios';
 
@@ -16,6 +16,8 @@ function parseJwt(token: string) {
 
 const AppHeader: React.FC = () => {
     const [user, setUser] = useState<any>(null);
+    const [notifications, setNotifications] = useState<any[]>([]);
+    const [unreadMessages, setUnreadMessages] = useState(0);
     const navigate = useNavigate();
     const location = useLocation();
 
@@ -24,6 +26,12 @@ const AppHeader: React.FC = () => {
         if (token) {
             const payload = parseJwt(token);
             if (payload && payload.sub) {
+                axios.get(`/api/notifications/${payload.sub}`)
+                    .then(res => setNotifications(res.data))
+                    .catch(() => {});
+                axios.get("/api/dm_unread")
+                    .then(res => setUnreadMessages(res.data.unread))
+                    .catch(() => {});
                 axios.get(`/api/user_info/${payload.sub}`)
                     .then(res => {
                         setUser(res.data);
@@ -42,6 +50,38 @@ const AppHeader: React.FC = () => {
         }
     }, [navigate]);
 
+    const unreadCount = notifications.filter(n => !n.read_at).length;
+
+    const markAllRead = () => {
+        axios.post('/api/notifications/read_all', { user_id: user.id }).then(() => {
+            setNotifications(list => list.map(n => ({ ...n, read_at: n.read_at || new Date().toISOString() })));
+        });
+    };
+
+    const notificationList = (
+        <div style={{ width: 320 }}>
+            <List
+                size="small"
+                dataSource={notifications.slice(0, 10)}
+                locale={{ emptyText: '暂无通知' }}
+                renderItem={n => (
+                    <List.Item
+                        style={{ cursor: n.task_id ? 'pointer' : 'default', color: n.read_at ? '#888' : undefined }}
+                        onClick={() => n.task_id && navigate(`/task/${n.task_id}`)}
+                    >
+                        <div>
+                            <div>{n.content}</div>
+                            <div style={{ fontSize: 12, color: '#aaa' }}>{new Date(n.created_at).toLocaleString()}</div>
+                        </div>
+                    </List.Item>
+                )}
+            />
+            {unreadCount > 0 && (
+                <Button type="link" size="small" onClick={markAllRead}>全部标为已读</Button>
+            )}
+        </div>
+    );
+
     const handleLogout = () => {
         localStorage.removeItem('token');
         message.success('已退出登录');
@@ -56,6 +96,15 @@ const AppHeader: React.FC = () => {
             <Menu.Item key="management">
                 <Link to="/management">任务管理</Link>
             </Menu.Item>
+            <Menu.Item key="messages">
+                <Link to="/messages">私信</Link>
+                {unreadMessages > 0 && <Badge count={unreadMessages} size="small" style={{ marginLeft: 8 }} />}
+            </Menu.Item>
+            {user?.is_admin && (
+                <Menu.Item key="grades">
+                    <Link to="/grades">课程成绩</Link>
+                </Menu.Item>
+            )}
             <Menu.Divider />
             <Menu.Item key="logout" onClick={handleLogout}>
                 退出登录
@@ -79,7 +128,14 @@ const AppHeader: React.FC = () => {
                     ]}
                 />
             </div>
-            <div className="user-profile">
+            <div className="user-profile" style={{ display: 'flex', alignItems: 'center' }}>
+                {user && (
+                    <Popover content={notificationList} title="通知" trigger="click" placement="bottomRight">
+                        <Badge count={unreadCount} size="small" offset={[-24, 0]}>
+                            <BellOutlined style={{ fontSize: 18, marginRight: 24, cursor: 'pointer' }} />
+                        </Badge>
+                    </Popover>
+                )}
                 {user ? (
                     <Dropdown overlay={userMenu} placement="bottomRight">
                         <div style={{ cursor: 'pointer', display: 'flex', alignItems: 'center' }}>
@@ -0,0 +1,28 @@
+import React from "react";
+import { Link } from "react-router-dom";
+
+interface Props {
+    text: string;
+    // 后端返回的提及位置，start、end 为 UTF-16 偏移，与字符串下标一致
+    mentions?: { user_id: string; start: number; end: number }[];
+}
+
+// 把文本中 @ 到的成员高亮并链接到其主页
+const MentionText: React.FC<Props> = ({ text, mentions }) => {
+    const parts: React.ReactNode[] = [];
+    let last = 0;
+    [...(mentions || [])].sort((a, b) => a.start - b.start).forEach(m => {
+        if (m.start < last) return;
+        parts.push(text.slice(last, m.start));
+        parts.push(
+            <Link key={m.start} to={`/user/${m.user_id}`} style={{ background: "#e6f4ff", borderRadius: 3, padding: "0 2px" }}>
+                {text.slice(m.start, m.end)}
+            </Link>
+        );
+        last = m.end;
+    });
+    parts.push(text.slice(last));
+    return <>{parts}</>;
+};
+
+export default MentionText;
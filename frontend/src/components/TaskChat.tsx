@@ -0,0 +1,223 @@
+import React, { useCallback, useEffect, useState } from "react";
+import { Button, Input, Modal, Popconfirm, Popover, Space, Tag, message } from "antd";
+import axios from "axios";
+import dayjs from "dayjs";
+import MentionText from "./MentionText";
+
+const EMOJIS = ["👍", "❤️", "😂", "🎉", "😮", "👀"];
+
+interface Props {
+    taskId: string;
+    userId: string;
+    // 任务发布者可以删除任何人的消息
+    creatorId?: string;
+}
+
+// 任务聊天室：成员之间的消息，支持回复、编辑、删除和表情回应，变化通过 WebSocket 实时同步
+const TaskChat: React.FC<Props> = ({ taskId, userId, creatorId }) => {
+    const [items, setItems] = useState<any[]>([]);
+    const [nextCursor, setNextCursor] = useState<string | null>(null);
+    const [replies, setReplies] = useState<Record<string, any[]>>({});
+    const [content, setContent] = useState("");
+    const [replyTo, setReplyTo] = useState<any>(null);
+    const [editing, setEditing] = useState<any>(null);
+    const [editContent, setEditContent] = useState("");
+    const [edits, setEdits] = useState<any[] | null>(null);
+
+    // 新消息或变化的消息合并进列表
+    const upsert = useCallback((msg: any) => {
+        if (msg.parent_id) {
+            setReplies(map => {
+                if (!map[msg.parent_id]) return map;
+                const list = map[msg.parent_id];
+                return {
+                    ...map,
+                    [msg.parent_id]: list.some(m => m.id === msg.id)
+                        ? list.map(m => (m.id === msg.id ? msg : m))
+                        : [...list, msg],
+                };
+            });
+        } else {
+            setItems(list => (list.some(m => m.id === msg.id)
+                ? list.map(m => (m.id === msg.id ? msg : m))
+                : [msg, ...list]));
+        }
+    }, []);
+
+    useEffect(() => {
+        axios.get(`/api/tasks/${taskId}/messages`).then(res => {
+            if (res.data.success === false) return;
+            setItems(res.data.items);
+            setNextCursor(res.data.next_cursor);
+        });
+        const token = localStorage.getItem("token");
+        const protocol = window.location.protocol === "https:" ? "wss" : "ws";
+        const socket = new WebSocket(`${protocol}://${window.location.host}/ws/?token=${token}`);
+        socket.onmessage = event => {
+            const data = JSON.parse(event.data);
+            if (data.type === "chat_message" && data.chat_message.task_id === taskId) {
+                upsert(data.chat_message);
+            }
+        };
+        return () => socket.close();
+    }, [taskId, upsert]);
+
+    const loadMore = () => {
+        axios.get(`/api/tasks/${taskId}/messages`, { params: { cursor: nextCursor } }).then(res => {
+            setItems(list => [...list, ...res.data.items]);
+            setNextCursor(res.data.next_cursor);
+        });
+    };
+
+    const toggleReplies = (msg: any) => {
+        if (replies[msg.id]) {
+            setReplies(({ [msg.id]: _, ...rest }) => rest);
+            return;
+        }
+        axios.get(`/api/task_messages/${msg.id}/replies`)
+            .then(res => setReplies(map => ({ ...map, [msg.id]: res.data })));
+    };
+
+    const handleResult = (res: any) => {
+        if (!res.data.success) {
+            message.error(res.data.message);
+            return false;
+        }
+        upsert(res.data.chat_message);
+        return true;
+    };
+
+    const send = () => {
+        if (!content.trim()) return;
+        axios.post(`/api/tasks/${taskId}/messages`, { content, parent_id: replyTo?.id })
+            .then(res => {
+                if (handleResult(res)) {
+                    setContent("");
+                    setReplyTo(null);
+                }
+            });
+    };
+
+    const saveEdit = () => {
+        axios.put(`/api/task_messages/${editing.id}`, { content: editContent }).then(res => {
+            if (handleResult(res)) setEditing(null);
+        });
+    };
+
+    const remove = (msg: any) => {
+        axios.delete(`/api/task_messages/${msg.id}`).then(handleResult);
+    };
+
+    const react = (msg: any, emoji: string) => {
+        const reacted = msg.reactions.some((r: any) => r.emoji === emoji && r.user_ids.includes(userId));
+        const request = reacted
+            ? axios.delete(`/api/task_messages/${msg.id}/reactions`, { params: { emoji } })
+            : axios.post(`/api/task_messages/${msg.id}/reactions`, { emoji });
+        request.then(handleResult);
+    };
+
+    const showEdits = (msg: any) => {
+        axios.get(`/api/task_messages/${msg.id}/edits`)
+            .then(res => setEdits([...res.data, { content: msg.content, edited_at: msg.edited_at, current: true }]));
+    };
+
+    const renderMessage = (msg: any) => (
+        <div key={msg.id} style={{ padding: "8px 0", borderBottom: "1px solid #f0f0f0" }}>
+            <div>
+                <b>{msg.sender_name || msg.sender_username}</b>
+                <span style={{ fontSize: 12, color: "#aaa", marginLeft: 8 }}>{dayjs(msg.created_at).format("MM-DD HH:mm")}</span>
+                {msg.edited_at && !msg.deleted && (
+                    <span style={{ fontSize: 12, color: "#aaa", marginLeft: 8, cursor: "pointer" }} onClick={() => showEdits(msg)}>（已编辑）</span>
+                )}
+            </div>
+            {msg.deleted ? (
+                <div style={{ color: "#aaa", fontStyle: "italic" }}>消息已删除</div>
+            ) : (
+                <>
+                    <div style={{ whiteSpace: "pre-wrap" }}><MentionText text={msg.content} mentions={msg.mentions} /></div>
+                    <Space size={4} wrap style={{ marginTop: 4 }}>
+                        {msg.reactions.map((r: any) => (
+                            <Tag
+                                key={r.emoji}
+                                color={r.user_ids.includes(userId) ? "blue" : undefined}
+                                style={{ cursor: "pointer" }}
+                                onClick={() => react(msg, r.emoji)}
+                            >
+                                {r.emoji} {r.count}
+                            </Tag>
+                        ))}
+                        <Popover
+                            trigger="click"
+                            content={EMOJIS.map(e => (
+                                <Button key={e} type="text" onClick={() => react(msg, e)}>{e}</Button>
+                            ))}
+                        >
+                            <Button type="link" size="small">回应</Button>
+                        </Popover>
+                        {!msg.parent_id && <Button type="link" size="small" onClick={() => setReplyTo(msg)}>回复</Button>}
+                        {msg.sender_id === userId && (
+                            <Button type="link" size="small" onClick={() => { setEditing(msg); setEditContent(msg.content); }}>编辑</Button>
+                        )}
+                        {(msg.sender_id === userId || creatorId === userId) && (
+                            <Popconfirm title="确定删除这条消息？" onConfirm={() => remove(msg)}>
+                                <Button type="link" size="small" danger>删除</Button>
+                            </Popconfirm>
+                        )}
+                    </Space>
+                </>
+            )}
+            {!msg.parent_id && msg.reply_count > 0 && (
+                <Button type="link" size="small" onClick={() => toggleReplies(msg)}>
+                    {replies[msg.id] ? "收起回复" : `${msg.reply_count} 条回复`}
+                </Button>
+            )}
+            {replies[msg.id] && (
+                <div style={{ marginLeft: 24, borderLeft: "2px solid #f0f0f0", paddingLeft: 12 }}>
+                    {replies[msg.id].map(renderMessage)}
+                </div>
+            )}
+        </div>
+    );
+
+    return (
+        <div>
+            <div style={{ maxHeight: 480, overflowY: "auto", marginBottom: 12 }}>
+                {items.map(renderMessage)}
+                {nextCursor && <Button type="link" onClick={loadMore}>加载更早的消息</Button>}
+            </div>
+            {replyTo && (
+                <div style={{ color: "#888", marginBottom: 4 }}>
+                    回复 {replyTo.sender_name || replyTo.sender_username}：{replyTo.content}
+                    <Button type="link" size="small" onClick={() => setReplyTo(null)}>取消</Button>
+                </div>
+            )}
+            <div style={{ display: "flex" }}>
+                <Input.TextArea
+                    autoSize={{ minRows: 1, maxRows: 4 }}
+                    maxLength={2000}
+                    placeholder="输入消息，@用户名 提及成员，Enter 发送"
+                    value={content}
+                    onChange={e => setContent(e.target.value)}
+                    onPressEnter={e => { if (!e.shiftKey) { e.preventDefault(); send(); } }}
+                />
+                <Button type="primary" style={{ marginLeft: 8 }} onClick={send}>发送</Button>
+            </div>
+            <Modal title="编辑消息" open={editing !== null} onOk={saveEdit} onCancel={() => setEditing(null)}>
+                <Input.TextArea rows={4} maxLength={2000} value={editContent} onChange={e => setEditContent(e.target.value)} />
+            </Modal>
+            <Modal title="编辑历史" open={edits !== null} footer={null} onCancel={() => setEdits(null)}>
+                {edits?.map((e, i) => (
+                    <div key={i} style={{ marginBottom: 12 }}>
+                        <div style={{ fontSize: 12, color: "#aaa" }}>
+                            {i === 0 ? "原始内容" : `${dayjs(edits[i - 1].edited_at).format("MM-DD HH:mm")} 编辑`}
+                            {e.current && "（当前）"}
+                        </div>
+                        <div style={{ whiteSpace: "pre-wrap" }}>{e.content}</div>
+                    </div>
+                ))}
+            </Modal>
+        </div>
+    );
+};
+
+export default TaskChat;
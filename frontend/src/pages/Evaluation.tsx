@@ -1,60 +1,152 @@
-import React, { useState } from "react";
-import { List, Input, Button, Rate, message } from "antd";
+import React, { useEffect, useState } from "react";
+import { List, Input, Button, Select, Slider, Table, Tag, Modal, message } from "antd";
+import { useNavigate, useSearchParams } from "react-router-dom";
+import axios from "axios";
 
-// 示例评价数据
-const initialEvaluations = [
-    { id: 1, user: "张三", content: "配合很好！", rate: 5 },
-    { id: 2, user: "李四", content: "完成及时", rate: 4 },
-];
+function parseJwt(token: string) {
+    try {
+        return JSON.parse(atob(token.split('.')[1]));
+    } catch {
+        return null;
+    }
+}
 
 const Evaluation: React.FC = () => {
-    const [evaluations, setEvaluations] = useState(initialEvaluations);
-    const [user, setUser] = useState("");
-    const [content, setContent] = useState("");
-    const [rate, setRate] = useState(5);
-
-    const addEvaluation = () => {
-        if (!user || !content) return message.warning("请填写完整");
-        setEvaluations([
-            ...evaluations,
-            { id: Date.now(), user, content, rate },
-        ]);
-        setUser("");
-        setContent("");
-        setRate(5);
+    const navigate = useNavigate();
+    const [searchParams, setSearchParams] = useSearchParams();
+    const taskId = searchParams.get("task_id");
+    const [userId, setUserId] = useState<string | null>(null);
+    const [finishedTasks, setFinishedTasks] = useState<any[]>([]);
+    const [criteria, setCriteria] = useState<any[]>([]);
+    const [targets, setTargets] = useState<any[]>([]);
+    const [summary, setSummary] = useState<any[]>([]);
+    const [evaluating, setEvaluating] = useState<any>(null);
+    const [scores, setScores] = useState<Record<string, number>>({});
+    const [comment, setComment] = useState("");
+
+    useEffect(() => {
+        const token = localStorage.getItem("token");
+        const payload = token ? parseJwt(token) : null;
+        if (!payload || !payload.sub) {
+            message.warning("请先登录");
+            navigate("/login");
+            return;
+        }
+        setUserId(payload.sub);
+        axios.get(`/api/my_roles/${payload.sub}`).then(res => {
+            const finished = res.data.filter((r: any) => r.status === "已结束");
+            setFinishedTasks(finished.filter((r: any, i: number) => finished.findIndex((o: any) => o.task_id === r.task_id) === i));
+        });
+    }, [navigate]);
+
+    const fetchEvaluations = () => {
+        if (!taskId || !userId) return;
+        axios.get(`/api/tasks/${taskId}/evaluation_rubric`).then(res => setCriteria(res.data.criteria));
+        axios.get(`/api/tasks/${taskId}/evaluation_targets`).then(res => setTargets(res.data));
+        axios.get(`/api/tasks/${taskId}/evaluation_summary`).then(res => setSummary(res.data));
+    };
+
+    useEffect(fetchEvaluations, [taskId, userId]);
+
+    const openEvaluate = (target: any) => {
+        setEvaluating(target);
+        setScores(Object.fromEntries(criteria.map(c => [c.name, c.max_score])));
+        setComment("");
+    };
+
+    const submitEvaluation = async () => {
+        try {
+            const res = await axios.post("/api/evaluations", {
+                task_id: taskId,
+                target_id: evaluating.user_id,
+                scores,
+                comment,
+            });
+            if (res.data.success) {
+                message.success("评价已提交");
+                setEvaluating(null);
+                fetchEvaluations();
+            } else {
+                message.error(res.data.message || "提交失败");
+            }
+        } catch {
+            message.error("网络错误");
+        }
     };
 
+    const summaryColumns = [
+        { title: "成员", key: "user", render: (_: any, record: any) => record.name || record.username },
+        { title: "评价数", dataIndex: "rating_count", key: "rating_count" },
+        ...criteria.map(c => ({
+            title: `${c.name}（${c.min_score}-${c.max_score}）`,
+            key: c.name,
+            render: (_: any, record: any) => {
+                if (!record.revealed) return "未公开";
+                const avg = record.criteria.find((x: any) => x.name === c.name)?.average;
+                return avg == null ? "-" : avg.toFixed(1);
+            },
+        })),
+        {
+            title: "综合（百分制）",
+            dataIndex: "overall",
+            key: "overall",
+            render: (v: number | null, record: any) => (!record.revealed ? "未公开" : v == null ? "-" : v.toFixed(1)),
+        },
+    ];
+
     return (
-        <div style={{ maxWidth: 600, margin: "40px auto" }}>
-            <h2>队员评价</h2>
-            <List
-                dataSource={evaluations}
-                renderItem={item => (
-                    <List.Item>
-                        <div style={{ width: "100%" }}>
-                            <b>{item.user}</b>：{item.content} <Rate disabled value={item.rate} />
-                        </div>
-                    </List.Item>
-                )}
+        <div style={{ maxWidth: 800, margin: "40px auto" }}>
+            <h2>队员互评</h2>
+            <Select
+                style={{ width: 300, marginBottom: 24 }}
+                placeholder="选择已结束的任务"
+                value={taskId}
+                onChange={v => setSearchParams({ task_id: v })}
+                options={finishedTasks.map(t => ({ value: t.task_id, label: t.title }))}
             />
-            <div style={{ marginTop: 24 }}>
-                <Input
-                    style={{ width: 120, marginRight: 8 }}
-                    placeholder="你的名字"
-                    value={user}
-                    onChange={e => setUser(e.target.value)}
-                />
-                <Input
-                    style={{ width: 200, marginRight: 8 }}
-                    placeholder="评价内容"
-                    value={content}
-                    onChange={e => setContent(e.target.value)}
-                />
-                <Rate value={rate} onChange={setRate} style={{ marginRight: 8 }} />
-                <Button type="primary" onClick={addEvaluation}>提交评价</Button>
-            </div>
+            {taskId && (
+                <>
+                    <List
+                        header={<div>我的队友</div>}
+                        dataSource={targets}
+                        renderItem={item => (
+                            <List.Item
+                                actions={[
+                                    item.evaluated ? <Tag color="green">已评价</Tag>
+                                        : <Button type="primary" size="small" onClick={() => openEvaluate(item)}>评价</Button>,
+                                ]}
+                            >
+                                {item.name || item.username}
+                            </List.Item>
+                        )}
+                    />
+                    <h3 style={{ marginTop: 24 }}>评价汇总</h3>
+                    <Table columns={summaryColumns} dataSource={summary} rowKey="user_id" pagination={false} size="small" />
+                </>
+            )}
+            <Modal
+                title={`评价 ${evaluating ? evaluating.name || evaluating.username : ""}`}
+                open={evaluating !== null}
+                onOk={submitEvaluation}
+                onCancel={() => setEvaluating(null)}
+                destroyOnClose
+            >
+                {criteria.map(c => (
+                    <div key={c.name} style={{ marginBottom: 12 }}>
+                        <b>{c.name}</b>
+                        {c.description && <span style={{ color: "#888", marginLeft: 8 }}>{c.description}</span>}
+                        <Slider
+                            min={c.min_score}
+                            max={c.max_score}
+                            value={scores[c.name]}
+                            onChange={v => setScores({ ...scores, [c.name]: v })}
+                        />
+                    </div>
+                ))}
+                <Input.TextArea rows={3} placeholder="评语（可选）" value={comment} onChange={e => setComment(e.target.value)} />
+            </Modal>
         </div>
     );
 };
 
-export default Evaluation; 
\ No newline at end of file
+export default Evaluation;
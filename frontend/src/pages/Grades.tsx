@@ -0,0 +1,82 @@
+import React, { useEffect, useState } from "react";
+import { Select, Table, Button, Space, message } from "antd";
+import { useNavigate } from "react-router-dom";
+import axios from "axios";
+
+function parseJwt(token: string) {
+    try {
+        return JSON.parse(atob(token.split('.')[1]));
+    } catch {
+        return null;
+    }
+}
+
+// 管理员（教师）按课程查看并导出成员贡献度
+const Grades: React.FC = () => {
+    const navigate = useNavigate();
+    const [userId, setUserId] = useState<string | null>(null);
+    const [courses, setCourses] = useState<any[]>([]);
+    const [course, setCourse] = useState<string | null>(null);
+    const [rows, setRows] = useState<any[]>([]);
+
+    useEffect(() => {
+        const token = localStorage.getItem("token");
+        const payload = token ? parseJwt(token) : null;
+        if (!payload || !payload.sub) {
+            message.warning("请先登录");
+            navigate("/login");
+            return;
+        }
+        setUserId(payload.sub);
+        axios.get("/api/courses").then(res => setCourses(res.data));
+    }, [navigate]);
+
+    useEffect(() => {
+        if (!course || !userId) return;
+        axios.get(`/api/courses/${encodeURIComponent(course)}/contributions`)
+            .then(res => setRows(res.data))
+            .catch(err => message.error(err.response?.data?.message || "无法加载成绩"));
+    }, [course, userId]);
+
+    // 导出需带登录 token，不能直接 window.open，先取回文件再触发下载
+    const exportGrades = (format: string) => {
+        axios.get(`/api/courses/${encodeURIComponent(course!)}/contributions`, { params: { format }, responseType: "blob" }).then(res => {
+            const link = document.createElement("a");
+            link.href = URL.createObjectURL(res.data);
+            link.download = `${course}-contributions.${format}`;
+            link.click();
+            URL.revokeObjectURL(link.href);
+        }).catch(() => message.error("导出失败"));
+    };
+
+    const columns = [
+        { title: "任务", dataIndex: "task_title", key: "task_title" },
+        { title: "成员", key: "user", render: (_: any, record: any) => record.name || record.username },
+        { title: "学号", dataIndex: "student_id", key: "student_id" },
+        { title: "完成子任务", dataIndex: "completed_sub_tasks", key: "completed_sub_tasks" },
+        { title: "工时", dataIndex: "hours", key: "hours", render: (v: number) => v.toFixed(1) },
+        { title: "互评综合分", dataIndex: "evaluation", key: "evaluation", render: (v: number | null) => (v == null ? "-" : v.toFixed(1)) },
+        { title: "进度记录", dataIndex: "progress_entries", key: "progress_entries" },
+        { title: "贡献度", dataIndex: "score", key: "score", render: (v: number) => v.toFixed(1) },
+    ];
+
+    return (
+        <div style={{ maxWidth: 1000, margin: "40px auto" }}>
+            <h2>课程成绩</h2>
+            <Space style={{ marginBottom: 24 }}>
+                <Select
+                    style={{ width: 300 }}
+                    placeholder="选择课程"
+                    value={course}
+                    onChange={setCourse}
+                    options={courses.map(c => ({ value: c.course, label: `${c.course}（${c.task_count} 个任务）` }))}
+                />
+                <Button disabled={!course} onClick={() => exportGrades("csv")}>导出 CSV</Button>
+                <Button disabled={!course} onClick={() => exportGrades("xlsx")}>导出 Excel</Button>
+            </Space>
+            <Table columns={columns} dataSource={rows} rowKey={r => `${r.task_id}-${r.user_id}`} size="small" />
+        </div>
+    );
+};
+
+export default Grades;
@@ -0,0 +1,67 @@
+import React, { useEffect, useState } from "react";
+import { useParams, useNavigate } from "react-router-dom";
+import { Card, Button, Spin, Radio, Space, message } from "antd";
+import axios from "axios";
+
+const InviteAccept: React.FC = () => {
+    const { token } = useParams();
+    const navigate = useNavigate();
+    const [invite, setInvite] = useState<any>(null);
+    const [loading, setLoading] = useState(true);
+    const [roleId, setRoleId] = useState<string | null>(null);
+    const [joining, setJoining] = useState(false);
+
+    useEffect(() => {
+        const loginToken = localStorage.getItem("token");
+        if (!loginToken) {
+            message.warning("请先登录");
+            navigate("/login");
+            return;
+        }
+        axios.get(`/api/invite/${token}`).then(res => {
+            setInvite(res.data);
+            if (res.data.role_id) setRoleId(res.data.role_id);
+        }).catch(() => setInvite(null)).finally(() => setLoading(false));
+    }, [token, navigate]);
+
+    const accept = async () => {
+        setJoining(true);
+        try {
+            const res = await axios.post(`/api/invite/${token}/accept`, { role_id: roleId });
+            if (res.data.success) {
+                message.success("已加入任务");
+                navigate(`/task/${res.data.task_id}`);
+            } else {
+                message.error(res.data.message || "加入失败");
+            }
+        } catch {
+            message.error("网络错误");
+        }
+        setJoining(false);
+    };
+
+    if (loading) return <Spin style={{ margin: 40 }} />;
+    if (!invite || !invite.success) return <div style={{ margin: 40 }}>{invite?.message || "邀请链接无效或已过期"}</div>;
+
+    return (
+        <div style={{ maxWidth: 600, margin: "40px auto" }}>
+            <Card title={`邀请你加入：${invite.title}`}>
+                <p>{invite.description}</p>
+                <Radio.Group value={roleId} onChange={e => setRoleId(e.target.value)}>
+                    <Space direction="vertical">
+                        {invite.roles.map((r: any) => (
+                            <Radio key={r.role_id} value={r.role_id} disabled={r.closed || r.filled >= r.capacity}>
+                                {r.role_name}（{r.filled}/{r.capacity}）
+                            </Radio>
+                        ))}
+                    </Space>
+                </Radio.Group>
+                <div style={{ marginTop: 16 }}>
+                    <Button type="primary" disabled={!roleId} loading={joining} onClick={accept}>接受邀请</Button>
+                </div>
+            </Card>
+        </div>
+    );
+};
+
+export default InviteAccept;
@@ -1,6 +1,6 @@
 import React, { useEffect, useState } from "react";
-import { Input, Button, message, List, Modal, Layout, Menu, Table, Avatar, Popconfirm, Tooltip } from "antd";
-import { UserOutlined, LaptopOutlined } from '@ant-design/icons';
+import { Input, Button, message, List, Modal, Layout, Menu, Table, Avatar, Popconfirm, Tooltip, Select } from "antd";
+import { UserOutlined, LaptopOutlined, MailOutlined } from '@ant-design/icons';
 import axios from "axios";
 import { useNavigate } from "react-router-dom";
 import AppHeader from "../components/AppHeader";
@@ -18,11 +18,15 @@ function parseJwt(token: string) {
 const Management: React.FC = () => {
     const [roles, setRoles] = useState<any[]>([]);
     const [published, setPublished] = useState<any[]>([]);
+    const [invitations, setInvitations] = useState<any[]>([]);
+    const [transfers, setTransfers] = useState<any[]>([]);
+    const [removeReason, setRemoveReason] = useState("");
     const [isEditModalVisible, setIsEditModalVisible] = useState(false);
     const [editingTask, setEditingTask] = useState<any>(null);
-    const [formValues, setFormValues] = useState({ title: "", description: "" });
+    const [formValues, setFormValues] = useState<{ title: string; description: string; tags: string[] }>({ title: "", description: "", tags: [] });
     const [saving, setSaving] = useState(false);
     const [selectedKey, setSelectedKey] = useState('claimed');
+    const [userId, setUserId] = useState<string | null>(null);
     const navigate = useNavigate();
 
     useEffect(() => {
@@ -34,15 +38,41 @@ const Management: React.FC = () => {
         }
         const payload = parseJwt(token);
         if (payload && payload.sub) {
+            setUserId(payload.sub);
             axios.get(`/api/my_roles/${payload.sub}`).then(res => {
                 setRoles(res.data);
             });
             axios.get(`/api/my_published_tasks/${payload.sub}`).then(res => {
                 setPublished(res.data);
             });
+            axios.get("/api/my_invitations").then(res => {
+                setInvitations(res.data);
+            });
+            axios.get("/api/my_ownership_transfers").then(res => {
+                setTransfers(res.data);
+            });
         }
     }, [navigate]);
 
+    const respondInvitation = async (invitationId: string, action: "accept" | "decline") => {
+        try {
+            const res = await axios.post(`/api/invitations/${invitationId}/${action}`);
+            if (res.data.success) {
+                message.success(action === "accept" ? "已加入任务" : "已拒绝邀请");
+                const [res2, res3] = await Promise.all([
+                    axios.get("/api/my_invitations"),
+                    axios.get(`/api/my_roles/${userId}`),
+                ]);
+                setInvitations(res2.data);
+                setRoles(res3.data);
+            } else {
+                message.error(res.data.message || "操作失败");
+            }
+        } catch {
+            message.error("网络错误");
+        }
+    };
+
     const finishTask = async (taskId: string) => {
         try {
             const res = await axios.post("/api/finish_task", { task_id: taskId });
@@ -57,9 +87,37 @@ const Management: React.FC = () => {
         }
     };
 
+    const cloneTask = async (taskId: string) => {
+        try {
+            const res = await axios.post(`/api/tasks/${taskId}/clone`, {});
+            if (res.data.success) {
+                message.success("已复制为草稿");
+                const res2 = await axios.get(`/api/my_published_tasks/${userId}`);
+                setPublished(res2.data);
+            } else {
+                message.error(res.data.message || "复制失败");
+            }
+        } catch {
+            message.error("网络错误");
+        }
+    };
+
+    const saveAsTemplate = async (taskId: string) => {
+        try {
+            const res = await axios.post(`/api/tasks/${taskId}/save_as_template`, {});
+            if (res.data.success) {
+                message.success("已保存为模板");
+            } else {
+                message.error(res.data.message || "保存失败");
+            }
+        } catch {
+            message.error("网络错误");
+        }
+    };
+
     const showEditModal = (task: any) => {
         setEditingTask(task);
-        setFormValues({ title: task.title, description: task.description });
+        setFormValues({ title: task.title, description: task.description, tags: task.tags || [] });
         setIsEditModalVisible(true);
     };
 
@@ -71,11 +129,12 @@ const Management: React.FC = () => {
                 task_id: editingTask.id,
                 title: formValues.title,
                 description: formValues.description,
+                tags: formValues.tags,
             });
             if (res.data.success) {
                 message.success("任务更新成功");
                 setPublished(published.map(t =>
-                    t.id === editingTask.id ? { ...t, title: formValues.title, description: formValues.description } : t
+                    t.id === editingTask.id ? { ...t, title: formValues.title, description: formValues.description, tags: formValues.tags } : t
                 ));
                 setIsEditModalVisible(false);
                 setEditingTask(null);
@@ -125,7 +184,14 @@ const Management: React.FC = () => {
             title: "操作",
             key: "action",
             render: (_: any, record: any) => (
-                <Popconfirm title="确定要移除该成员吗？" onConfirm={() => handleRemoveMember(record)} okText="是" cancelText="否">
+                <Popconfirm
+                    title="确定要移除该成员吗？"
+                    description={<Input placeholder="移除原因（可选）" value={removeReason} onChange={e => setRemoveReason(e.target.value)} />}
+                    onOpenChange={() => setRemoveReason("")}
+                    onConfirm={() => handleRemoveMember(record)}
+                    okText="是"
+                    cancelText="否"
+                >
                     <Button danger size="small">移除</Button>
                 </Popconfirm>
             ),
@@ -134,7 +200,13 @@ const Management: React.FC = () => {
 
     const handleRemoveMember = async (member: any) => {
         try {
-            await axios.post(`/api/task_roles/${member.role_id}/remove_member`);
+            const res = await axios.post(`/api/task_roles/${member.role_id}/remove_member`, null, {
+                params: { user_id: member.user_id, reason: removeReason || undefined },
+            });
+            if (!res.data.success) {
+                message.error(res.data.message || "移除失败");
+                return;
+            }
             message.success("成员已移除");
             // 刷新数据
             const token = localStorage.getItem("token");
@@ -154,7 +226,11 @@ const Management: React.FC = () => {
             header={<div>我认领的任务</div>}
             dataSource={roles.sort((a, b) => (a.status === "已结束" ? 1 : 0) - (b.status === "已结束" ? 1 : 0))}
             renderItem={item => (
-                <List.Item>
+                <List.Item
+                    actions={item.status === "已结束" ? [
+                        <Button size="small" onClick={() => navigate(`/evaluation?task_id=${item.task_id}`)}>互评</Button>,
+                    ] : []}
+                >
                     <div>
                         <b>{item.title}</b> - {item.role_name} <span style={{ color: item.status === "已结束" ? "#888" : "#52c41a" }}>{item.status}</span>
                     </div>
@@ -170,15 +246,19 @@ const Management: React.FC = () => {
             renderItem={item => (
                 <List.Item
                     actions={[
-                        item.status === "进行中" ? (
+                        !["已结束", "已归档"].includes(item.status) ? (
                             <>
                                 <Button size="small" type="primary" style={{ marginRight: 8 }} onClick={() => navigate(`/task/${item.id}/dashboard`)}>管理</Button>
                                 <Button size="small" style={{ marginRight: 8 }} onClick={() => showEditModal(item)}>编辑</Button>
                                 <Button size="small" danger onClick={() => finishTask(item.id)}>结束任务</Button>
                             </>
                         ) : (
-                            <span style={{ color: "#888" }}>已结束</span>
-                        )
+                            <span style={{ color: "#888" }}>{item.status}</span>
+                        ),
+                        <>
+                            <Button size="small" style={{ marginRight: 8 }} onClick={() => cloneTask(item.id)}>复制</Button>
+                            <Button size="small" onClick={() => saveAsTemplate(item.id)}>存为模板</Button>
+                        </>
                     ]}
                 >
                     <div>
@@ -189,6 +269,68 @@ const Management: React.FC = () => {
         />
     );
 
+    const respondTransfer = async (transferId: string, action: "accept" | "decline" | "cancel") => {
+        try {
+            const res = await axios.post(`/api/ownership_transfers/${transferId}/${action}`);
+            if (res.data.success) {
+                message.success(action === "accept" ? "你已成为该任务的发布者" : "操作成功");
+            } else {
+                message.error(res.data.message || "操作失败");
+            }
+            const [res2, res3] = await Promise.all([
+                axios.get("/api/my_ownership_transfers"),
+                axios.get(`/api/my_published_tasks/${userId}`),
+            ]);
+            setTransfers(res2.data);
+            setPublished(res3.data);
+        } catch {
+            message.error("网络错误");
+        }
+    };
+
+    const renderInvitations = () => (
+        <>
+            <List
+                header={<div>收到的邀请</div>}
+                dataSource={invitations}
+                renderItem={item => (
+                    <List.Item
+                        actions={item.status === "待回复" ? [
+                            <Button size="small" type="primary" onClick={() => respondInvitation(item.id, "accept")}>接受</Button>,
+                            <Button size="small" danger onClick={() => respondInvitation(item.id, "decline")}>拒绝</Button>,
+                        ] : [<span style={{ color: "#888" }}>{item.status}</span>]}
+                    >
+                        <div>
+                            <b>{item.task_title}</b> - {item.role_name}
+                            <span style={{ color: "#888", marginLeft: 8 }}>来自 {item.inviter_name}</span>
+                            {item.message && <div style={{ color: "#888" }}>{item.message}</div>}
+                        </div>
+                    </List.Item>
+                )}
+            />
+            <List
+                header={<div>任务转让</div>}
+                dataSource={transfers}
+                renderItem={item => (
+                    <List.Item
+                        actions={item.status !== "待接受" ? [<span style={{ color: "#888" }}>{item.status}</span>]
+                            : item.to_user_id === userId ? [
+                                <Button size="small" type="primary" onClick={() => respondTransfer(item.id, "accept")}>接受</Button>,
+                                <Button size="small" danger onClick={() => respondTransfer(item.id, "decline")}>拒绝</Button>,
+                            ] : [<Button size="small" onClick={() => respondTransfer(item.id, "cancel")}>取消</Button>]}
+                    >
+                        <div>
+                            <b>{item.task_title}</b>
+                            <span style={{ color: "#888", marginLeft: 8 }}>
+                                {item.to_user_id === userId ? `${item.from_name} 转让给你` : `转让给 ${item.to_name}`}
+                            </span>
+                        </div>
+                    </List.Item>
+                )}
+            />
+        </>
+    );
+
     return (
         <Layout style={{ minHeight: '100vh' }}>
             <AppHeader />
@@ -203,11 +345,14 @@ const Management: React.FC = () => {
                             items={[
                                 { key: 'claimed', icon: <UserOutlined />, label: '我认领的任务' },
                                 { key: 'published', icon: <LaptopOutlined />, label: '我发布的任务' },
+                                { key: 'invitations', icon: <MailOutlined />, label: '邀请与转让' },
                             ]}
                         />
                     </Sider>
                     <Content style={{ padding: '0 24px', minHeight: 280, background: '#fff' }}>
-                        {selectedKey === 'claimed' ? renderClaimedTasks() : renderPublishedTasks()}
+                        {selectedKey === 'claimed' ? renderClaimedTasks()
+                            : selectedKey === 'invitations' ? renderInvitations()
+                                : renderPublishedTasks()}
                     </Content>
                 </Layout>
             </Content>
@@ -230,6 +375,14 @@ const Management: React.FC = () => {
                     onChange={(e) => setFormValues({ ...formValues, description: e.target.value })}
                     rows={4}
                     placeholder="任务描述"
+                    style={{ marginBottom: 16 }}
+                />
+                <Select
+                    mode="tags"
+                    value={formValues.tags}
+                    onChange={(tags) => setFormValues({ ...formValues, tags })}
+                    style={{ width: "100%" }}
+                    placeholder="分类标签"
                 />
             </Modal>
         </Layout>
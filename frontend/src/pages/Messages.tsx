@@ -0,0 +1,193 @@
+import React, { useCallback, useEffect, useRef, useState } from "react";
+import { List, Avatar, Badge, Input, Button, Empty, Popconfirm, message } from "antd";
+import { UserOutlined } from "@ant-design/icons";
+import { useNavigate, useSearchParams, Link } from "react-router-dom";
+import axios from "axios";
+import dayjs from "dayjs";
+
+function parseJwt(token: string) {
+    try {
+        return JSON.parse(atob(token.split('.')[1]));
+    } catch {
+        return null;
+    }
+}
+
+// 私信：左侧会话列表，右侧历史消息，新私信通过 WebSocket 实时推送
+const Messages: React.FC = () => {
+    const navigate = useNavigate();
+    const [searchParams] = useSearchParams();
+    const [userId, setUserId] = useState<string | null>(null);
+    const [threads, setThreads] = useState<any[]>([]);
+    const [active, setActive] = useState<any>(null);
+    const [items, setItems] = useState<any[]>([]);
+    const [nextCursor, setNextCursor] = useState<string | null>(null);
+    const [content, setContent] = useState("");
+    // 从用户主页点“发私信”进入时还没有会话，先记下对方
+    const [draftPeer, setDraftPeer] = useState<string | null>(searchParams.get("to"));
+    const activeRef = useRef<any>(null);
+    activeRef.current = active;
+
+    const loadThreads = useCallback(() => {
+        return axios.get("/api/dm_threads").then(res => {
+            setThreads(res.data);
+            return res.data as any[];
+        });
+    }, []);
+
+    const markRead = useCallback((threadId: string) => {
+        axios.post(`/api/dm_threads/${threadId}/read`).then(() => {
+            setThreads(list => list.map(t => (t.id === threadId ? { ...t, unread_count: 0 } : t)));
+        });
+    }, []);
+
+    useEffect(() => {
+        const token = localStorage.getItem("token");
+        const payload = token ? parseJwt(token) : null;
+        if (!payload || !payload.sub) {
+            message.warning("请先登录");
+            navigate("/login");
+            return;
+        }
+        const uid = payload.sub;
+        setUserId(uid);
+        loadThreads().then(list => {
+            const to = searchParams.get("to");
+            const existing = to && list.find(t => t.peer_id === to);
+            if (existing) {
+                setActive(existing);
+                setDraftPeer(null);
+            }
+        });
+
+        const protocol = window.location.protocol === "https:" ? "wss" : "ws";
+        const socket = new WebSocket(`${protocol}://${window.location.host}/ws/?token=${token}`);
+        socket.onmessage = event => {
+            const data = JSON.parse(event.data);
+            if (data.type !== "direct_message") return;
+            const dm = data.direct_message;
+            if (activeRef.current?.id === dm.thread_id) {
+                setItems(list => (list.some(m => m.id === dm.id) ? list : [dm, ...list]));
+                if (dm.sender_id !== uid) markRead(dm.thread_id);
+            }
+            loadThreads();
+        };
+        return () => socket.close();
+    }, [navigate, searchParams, loadThreads, markRead]);
+
+    useEffect(() => {
+        if (!active || !userId) {
+            setItems([]);
+            setNextCursor(null);
+            return;
+        }
+        axios.get(`/api/dm_threads/${active.id}/messages`).then(res => {
+            setItems(res.data.items);
+            setNextCursor(res.data.next_cursor);
+        });
+        if (active.unread_count > 0) markRead(active.id);
+    }, [active, userId, markRead]);
+
+    const loadMore = () => {
+        axios.get(`/api/dm_threads/${active.id}/messages`, { params: { cursor: nextCursor } }).then(res => {
+            setItems(list => [...list, ...res.data.items]);
+            setNextCursor(res.data.next_cursor);
+        });
+    };
+
+    const peerId = active?.peer_id || draftPeer;
+
+    const send = () => {
+        if (!content.trim() || !peerId) return;
+        axios.post("/api/direct_messages", { to_user_id: peerId, content }).then(res => {
+            if (!res.data.success) {
+                message.error(res.data.message);
+                return;
+            }
+            setContent("");
+            const dm = res.data.direct_message;
+            if (!active) {
+                loadThreads().then(list => {
+                    setActive(list.find(t => t.id === dm.thread_id) || null);
+                    setDraftPeer(null);
+                });
+            } else {
+                setItems(list => (list.some(m => m.id === dm.id) ? list : [dm, ...list]));
+            }
+        });
+    };
+
+    const toggleBlock = () => {
+        const request = active.blocked
+            ? axios.delete(`/api/blocks/${active.peer_id}`)
+            : axios.post("/api/blocks", { blocked_id: active.peer_id });
+        request.then(() => {
+            message.success(active.blocked ? "已解除屏蔽" : "已屏蔽，对方将无法给你发私信");
+            setActive({ ...active, blocked: !active.blocked });
+            loadThreads();
+        });
+    };
+
+    return (
+        <div style={{ maxWidth: 1000, margin: "40px auto", display: "flex", gap: 24 }}>
+            <List
+                style={{ width: 300, background: "#fafafa" }}
+                header={<b>私信</b>}
+                dataSource={threads}
+                locale={{ emptyText: "暂无私信" }}
+                renderItem={(t: any) => (
+                    <List.Item
+                        style={{ cursor: "pointer", padding: "8px 12px", background: active?.id === t.id ? "#e6f4ff" : undefined }}
+                        onClick={() => { setDraftPeer(null); setActive(t); }}
+                    >
+                        <List.Item.Meta
+                            avatar={<Badge count={t.unread_count} size="small"><Avatar src={t.peer_avatar_url} icon={<UserOutlined />} /></Badge>}
+                            title={t.peer_name || t.peer_username}
+                            description={<span style={{ fontSize: 12 }}>{t.last_message}</span>}
+                        />
+                    </List.Item>
+                )}
+            />
+            <div style={{ flex: 1 }}>
+                {peerId ? (
+                    <>
+                        {active && (
+                            <div style={{ display: "flex", justifyContent: "space-between", marginBottom: 12 }}>
+                                <Link to={`/user/${active.peer_id}`}><b>{active.peer_name || active.peer_username}</b></Link>
+                                <Popconfirm title={active.blocked ? "解除屏蔽？" : "屏蔽后对方不能再给你发私信，确定屏蔽？"} onConfirm={toggleBlock}>
+                                    <Button size="small" danger={!active.blocked}>{active.blocked ? "解除屏蔽" : "屏蔽"}</Button>
+                                </Popconfirm>
+                            </div>
+                        )}
+                        <div style={{ height: 420, overflowY: "auto", display: "flex", flexDirection: "column-reverse", background: "#fafafa", padding: 12, marginBottom: 12 }}>
+                            {items.map(m => (
+                                <div key={m.id} style={{ textAlign: m.sender_id === userId ? "right" : "left", margin: "4px 0" }}>
+                                    <div style={{ display: "inline-block", padding: "6px 10px", borderRadius: 6, background: m.sender_id === userId ? "#d6e4ff" : "white", whiteSpace: "pre-wrap" }}>
+                                        {m.content}
+                                    </div>
+                                    <div style={{ fontSize: 12, color: "#aaa" }}>{dayjs(m.created_at).format("MM-DD HH:mm")}</div>
+                                </div>
+                            ))}
+                            {nextCursor && <Button type="link" onClick={loadMore}>加载更早的消息</Button>}
+                        </div>
+                        <div style={{ display: "flex" }}>
+                            <Input.TextArea
+                                autoSize={{ minRows: 1, maxRows: 4 }}
+                                maxLength={2000}
+                                placeholder="输入私信，Enter 发送"
+                                value={content}
+                                onChange={e => setContent(e.target.value)}
+                                onPressEnter={e => { if (!e.shiftKey) { e.preventDefault(); send(); } }}
+                            />
+                            <Button type="primary" style={{ marginLeft: 8 }} onClick={send}>发送</Button>
+                        </div>
+                    </>
+                ) : (
+                    <Empty description="选择一个会话" style={{ marginTop: 120 }} />
+                )}
+            </div>
+        </div>
+    );
+};
+
+export default Messages;
@@ -1,5 +1,6 @@
-import React, { useState } from "react";
-import { Form, Input, Button, message, InputNumber, Space } from "antd";
+import React, { useEffect, useState } from "react";
+import { Form, Input, Button, message, InputNumber, Space, Select, DatePicker, Divider, Checkbox } from "antd";
+import type { Dayjs } from "dayjs";
 import { useNavigate } from "react-router-dom";
 import axios from "axios";
 
@@ -14,16 +15,42 @@ function parseJwt(token: string) {
 const NewTask: React.FC = () => {
     const navigate = useNavigate();
     const [teamSize, setTeamSize] = useState(1);
-    const [roles, setRoles] = useState<string[]>([""]);
+    const [roles, setRoles] = useState<{ name: string; capacity: number }[]>([{ name: "", capacity: 1 }]);
+    const [templates, setTemplates] = useState<any[]>([]);
+    const [templateId, setTemplateId] = useState<string | null>(null);
+    const [templateStart, setTemplateStart] = useState<Dayjs | null>(null);
 
-    const onTeamSizeChange = (value: number | null) => {
-        const v = value || 1;
-        setTeamSize(v);
-        setRoles(Array(v).fill("").map((val, i) => roles[i] || ""));
+    const currentUserId = () => {
+        const token = localStorage.getItem("token");
+        const payload = token ? parseJwt(token) : null;
+        return payload && payload.sub ? payload.sub : null;
     };
 
-    const onRoleChange = (idx: number, value: string) => {
-        setRoles(r => r.map((item, i) => (i === idx ? value : item)));
+    useEffect(() => {
+        if (currentUserId()) {
+            axios.get("/api/my_task_templates").then(res => setTemplates(res.data));
+        }
+    }, []);
+
+    const createFromTemplate = async () => {
+        if (!templateId) return;
+        try {
+            const res = await axios.post(`/api/task_templates/${templateId}/create_task`, {
+                start_date: templateStart ? templateStart.toISOString() : undefined,
+            });
+            if (res.data.success) {
+                message.success("已从模板创建草稿任务");
+                navigate("/management");
+            } else {
+                message.error(res.data.message || "创建失败");
+            }
+        } catch (err) {
+            message.error("网络错误");
+        }
+    };
+
+    const onRoleChange = (idx: number, value: Partial<{ name: string; capacity: number }>) => {
+        setRoles(r => r.map((item, i) => (i === idx ? { ...item, ...value } : item)));
     };
 
     const onFinish = async (values: any) => {
@@ -55,6 +82,22 @@ const NewTask: React.FC = () => {
     return (
         <div style={{ maxWidth: 500, margin: "40px auto" }}>
             <h2>发布新任务</h2>
+            {templates.length > 0 && (
+                <>
+                    <Space style={{ marginBottom: 8 }} wrap>
+                        <Select
+                            placeholder="选择模板"
+                            style={{ width: 200 }}
+                            value={templateId}
+                            onChange={setTemplateId}
+                            options={templates.map(t => ({ value: t.id, label: t.name }))}
+                        />
+                        <DatePicker placeholder="开始日期" value={templateStart} onChange={setTemplateStart} />
+                        <Button disabled={!templateId} onClick={createFromTemplate}>从模板创建</Button>
+                    </Space>
+                    <Divider />
+                </>
+            )}
             <Form onFinish={onFinish}>
                 <Form.Item name="title" label="任务标题" rules={[{ required: true, message: "请输入任务标题" }]}>
                     <Input />
@@ -62,20 +105,47 @@ const NewTask: React.FC = () => {
                 <Form.Item name="description" label="任务描述" rules={[{ required: true, message: "请输入任务描述" }]}>
                     <Input.TextArea rows={4} />
                 </Form.Item>
+                <Form.Item name="tags" label="分类标签">
+                    <Select
+                        mode="tags"
+                        placeholder="如 编程、设计、竞赛、论文"
+                        options={["编程", "设计", "竞赛", "论文"].map(t => ({ value: t, label: t }))}
+                    />
+                </Form.Item>
+                <Form.Item name="course" label="所属课程">
+                    <Input placeholder="如 软件工程（选填）" />
+                </Form.Item>
+                <Form.Item name="approval_required" valuePropName="checked">
+                    <Checkbox>认领职责需要我审核</Checkbox>
+                </Form.Item>
                 <Form.Item label="组队人数">
-                    <InputNumber min={1} max={10} value={teamSize} onChange={onTeamSizeChange} />
+                    <InputNumber min={1} max={20} value={teamSize} onChange={v => setTeamSize(v || 1)} />
                 </Form.Item>
-                <Form.Item label="职责名称">
+                <Form.Item label="职责与名额">
                     <Space direction="vertical" style={{ width: "100%" }}>
-                        {Array.from({ length: teamSize }).map((_, idx) => (
-                            <Input
-                                key={idx}
-                                placeholder={`职责${idx + 1}`}
-                                value={roles[idx] || ""}
-                                onChange={e => onRoleChange(idx, e.target.value)}
-                                required
-                            />
+                        {roles.map((role, idx) => (
+                            <Space key={idx}>
+                                <Input
+                                    placeholder={`职责${idx + 1}`}
+                                    value={role.name}
+                                    onChange={e => onRoleChange(idx, { name: e.target.value })}
+                                    required
+                                />
+                                <InputNumber
+                                    min={1}
+                                    max={teamSize}
+                                    value={role.capacity}
+                                    onChange={v => onRoleChange(idx, { capacity: v || 1 })}
+                                    addonAfter="人"
+                                />
+                                {roles.length > 1 && (
+                                    <Button onClick={() => setRoles(r => r.filter((_, i) => i !== idx))}>删除</Button>
+                                )}
+                            </Space>
                         ))}
+                        <Button type="dashed" onClick={() => setRoles(r => [...r, { name: "", capacity: 1 }])}>
+                            添加职责
+                        </Button>
                     </Space>
                 </Form.Item>
                 <Form.Item>
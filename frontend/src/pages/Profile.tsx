@@ -1,5 +1,5 @@
 import React, { useEffect, useState } from "react";
-import { Card, Input, Button, message, Avatar, Upload } from "antd";
+import { Card, Input, Button, message, Avatar, Upload, Select } from "antd";
 import { UserOutlined, UploadOutlined } from '@ant-design/icons';
 import axios from "axios";
 import { useNavigate } from "react-router-dom";
@@ -22,12 +22,20 @@ const beforeUpload = (file: RcFile) => {
     return isJpgOrPng && isLt2M;
 };
 
+// 联系方式的可见范围
+const visibilityOptions = [
+    { value: "public", label: "所有人可见" },
+    { value: "teammates", label: "仅队友可见" },
+    { value: "private", label: "仅自己可见" },
+];
+
 const Profile: React.FC = () => {
     const [user, setUser] = useState<{ id: string; username: string; name: string; avatar_url?: string; phone: string; student_id: string; email: string } | null>(null);
     const [name, setName] = useState("");
     const [phone, setPhone] = useState("");
     const [studentId, setStudentId] = useState("");
     const [email, setEmail] = useState("");
+    const [visibility, setVisibility] = useState({ phone: "teammates", student_id: "teammates", email: "public" });
     const [saving, setSaving] = useState(false);
     const navigate = useNavigate();
 
@@ -46,6 +54,11 @@ const Profile: React.FC = () => {
                 setPhone(res.data.phone || "");
                 setStudentId(res.data.student_id || "");
                 setEmail(res.data.email || "");
+                setVisibility({
+                    phone: res.data.phone_visibility,
+                    student_id: res.data.student_id_visibility,
+                    email: res.data.email_visibility,
+                });
             });
         }
     }, [navigate]);
@@ -67,11 +80,13 @@ const Profile: React.FC = () => {
         setSaving(true);
         try {
             const res = await axios.post("/api/update_profile", {
-                user_id: user.id,
                 name,
                 phone,
                 student_id: studentId,
                 email,
+                phone_visibility: visibility.phone,
+                student_id_visibility: visibility.student_id,
+                email_visibility: visibility.email,
             });
             if (res.data.success) {
                 message.success("保存成功");
@@ -138,14 +153,32 @@ const Profile: React.FC = () => {
                         <div style={{ marginBottom: 16 }}>
                             <b>手机号：</b>
                             <Input style={{ width: 200 }} value={phone} onChange={e => setPhone(e.target.value)} />
+                            <Select
+                                style={{ width: 120, marginLeft: 8 }}
+                                value={visibility.phone}
+                                onChange={v => setVisibility({ ...visibility, phone: v })}
+                                options={visibilityOptions}
+                            />
                         </div>
                         <div style={{ marginBottom: 16 }}>
                             <b>学号：</b>
                             <Input style={{ width: 200 }} value={studentId} onChange={e => setStudentId(e.target.value)} />
+                            <Select
+                                style={{ width: 120, marginLeft: 8 }}
+                                value={visibility.student_id}
+                                onChange={v => setVisibility({ ...visibility, student_id: v })}
+                                options={visibilityOptions}
+                            />
                         </div>
                         <div style={{ marginBottom: 16 }}>
                             <b>邮箱：</b>
                             <Input style={{ width: 200 }} value={email} onChange={e => setEmail(e.target.value)} />
+                            <Select
+                                style={{ width: 120, marginLeft: 8 }}
+                                value={visibility.email}
+                                onChange={v => setVisibility({ ...visibility, email: v })}
+                                options={visibilityOptions}
+                            />
                         </div>
                         <Button type="primary" onClick={onSave} loading={saving} style={{ marginLeft: 8 }}>保存</Button>
                         <div style={{ marginBottom: 24 }}>
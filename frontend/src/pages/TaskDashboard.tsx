@@ -1,9 +1,11 @@
 import React, { useEffect, useState } from 'react';
-import { useParams, useNavigate } from 'react-router-dom';
-import { Layout, Typography, Progress, Tabs, Button, Spin, Descriptions, message, Table, Modal, Form, Input, DatePicker, Popconfirm, List, Avatar, Select } from 'antd';
+import { useParams, useNavigate, Link } from 'react-router-dom';
+import { Layout, Typography, Progress, Tabs, Button, Spin, Descriptions, message, Table, Modal, Form, Input, DatePicker, Popconfirm, List, Avatar, Select, InputNumber, Space, Tag, Checkbox } from 'antd';
 import { PlusOutlined, EditOutlined, DeleteOutlined, UserOutlined } from '@ant-design/icons';
 import axios from 'axios';
 import AppHeader from '../components/AppHeader';
+import TaskChat from '../components/TaskChat';
+import MentionText from '../components/MentionText';
 import dayjs from 'dayjs';
 
 const { Content } = Layout;
@@ -24,6 +26,19 @@ const TaskDashboard: React.FC = () => {
     const [assignModal, setAssignModal] = useState<{ visible: boolean, subTask: any | null }>({ visible: false, subTask: null });
     const [assigning, setAssigning] = useState(false);
     const [selectedMember, setSelectedMember] = useState<string | null>(null);
+    const [joinRequests, setJoinRequests] = useState<any[]>([]);
+    const [inviteLinks, setInviteLinks] = useState<any[]>([]);
+    const [invitations, setInvitations] = useState<any[]>([]);
+    const [linkRoleId, setLinkRoleId] = useState<string | null>(null);
+    const [linkHours, setLinkHours] = useState(72);
+    const [inviteUsername, setInviteUsername] = useState('');
+    const [inviteRoleId, setInviteRoleId] = useState<string | null>(null);
+    const [transferTo, setTransferTo] = useState<string | null>(null);
+    const [removeReason, setRemoveReason] = useState('');
+    const [history, setHistory] = useState<any[]>([]);
+    const [weights, setWeights] = useState<any>({ sub_tasks: 40, hours: 20, evaluation: 30, progress: 10 });
+    const [contributions, setContributions] = useState<any[]>([]);
+    const [rubric, setRubric] = useState<{ locked: boolean; anonymous: boolean; min_ratings: number; criteria: any[] }>({ locked: false, anonymous: false, min_ratings: 3, criteria: [] });
 
     const fetchSubTasks = () => {
         if (taskId) {
@@ -35,17 +50,16 @@ const TaskDashboard: React.FC = () => {
 
     useEffect(() => {
         if (taskId) {
-            axios.get('/api/tasks').then(res => {
-                const foundTask = res.data.find((t: any) => t.id === taskId);
-                setTask(foundTask || null);
-            }).finally(() => setLoading(false));
+            axios.get(`/api/tasks/${taskId}`).then(res => {
+                setTask(res.data);
+            }).catch(() => setTask(null)).finally(() => setLoading(false));
             fetchSubTasks();
         }
     }, [taskId]);
 
     useEffect(() => {
         if (taskId) {
-            axios.get(`/api/task_roles/${taskId}`).then(res => setMembers(res.data));
+            fetchMembers();
         }
     }, [taskId, subTasks]);
 
@@ -55,6 +69,266 @@ const TaskDashboard: React.FC = () => {
         }
     }, [taskId, members, subTasks]);
 
+    const fetchJoinRequests = () => {
+        axios.get(`/api/tasks/${taskId}/join_requests`, { params: { status: '待审核' } })
+            .then(res => setJoinRequests(res.data.success === false ? [] : res.data));
+    };
+
+    useEffect(() => {
+        if (taskId) {
+            fetchJoinRequests();
+        }
+    }, [taskId]);
+
+    // 审核由发布者操作，任务管理页只对发布者开放
+    const decideJoinRequest = (requestId: string, action: 'approve' | 'reject') => {
+        axios.post(`/api/join_requests/${requestId}/${action}`, {}).then(res => {
+            if (res.data.success) {
+                message.success(action === 'approve' ? '已通过申请' : '已拒绝申请');
+                fetchJoinRequests();
+                fetchMembers();
+            } else {
+                message.error(res.data.message || '操作失败');
+            }
+        });
+    };
+
+    const joinRequestColumns = [
+        { title: '申请人', key: 'user', render: (_: any, record: any) => <Link to={`/user/${record.user_id}`}>{record.name || record.username}</Link> },
+        { title: '职责', dataIndex: 'role_name', key: 'role_name' },
+        { title: '留言', dataIndex: 'message', key: 'message' },
+        { title: '申请时间', dataIndex: 'created_at', key: 'created_at', render: (d: string) => dayjs(d).format('YYYY-MM-DD HH:mm') },
+        {
+            title: '操作',
+            key: 'action',
+            render: (_: any, record: any) => (
+                <>
+                    <Button type="primary" size="small" style={{ marginRight: 8 }} onClick={() => decideJoinRequest(record.id, 'approve')}>通过</Button>
+                    <Button danger size="small" onClick={() => decideJoinRequest(record.id, 'reject')}>拒绝</Button>
+                </>
+            ),
+        },
+    ];
+
+    const fetchInvites = () => {
+        axios.get(`/api/tasks/${taskId}/invite_links`)
+            .then(res => setInviteLinks(res.data.success === false ? [] : res.data));
+        axios.get(`/api/tasks/${taskId}/invitations`)
+            .then(res => setInvitations(res.data.success === false ? [] : res.data));
+    };
+
+    useEffect(() => {
+        if (task) {
+            fetchInvites();
+        }
+    }, [task]);
+
+    const inviteUrl = (token: string) => `${window.location.origin}/invite/${token}`;
+
+    const copyInviteLink = (token: string) => {
+        navigator.clipboard.writeText(inviteUrl(token)).then(() => message.success('邀请链接已复制'));
+    };
+
+    const createInviteLink = () => {
+        axios.post(`/api/tasks/${taskId}/invite_links`, {
+            role_id: linkRoleId || undefined,
+            expires_in_hours: linkHours,
+        }).then(res => {
+            if (res.data.success) {
+                copyInviteLink(res.data.token);
+                fetchInvites();
+            } else {
+                message.error(res.data.message || '生成失败');
+            }
+        });
+    };
+
+    const revokeInviteLink = (linkId: string) => {
+        axios.post(`/api/invite_links/${linkId}/revoke`).then(res => {
+            if (res.data.success) {
+                message.success('已撤销');
+                fetchInvites();
+            } else {
+                message.error(res.data.message || '撤销失败');
+            }
+        });
+    };
+
+    const inviteUser = () => {
+        if (!inviteUsername.trim() || !inviteRoleId) {
+            message.warning('请输入用户名并选择职责');
+            return;
+        }
+        axios.post(`/api/tasks/${taskId}/invitations`, {
+            username: inviteUsername.trim(),
+            role_id: inviteRoleId,
+        }).then(res => {
+            if (res.data.success) {
+                message.success('邀请已发送');
+                setInviteUsername('');
+                fetchInvites();
+            } else {
+                message.error(res.data.message || '邀请失败');
+            }
+        });
+    };
+
+    const requestTransfer = () => {
+        if (!transferTo) return;
+        axios.post(`/api/tasks/${taskId}/transfer`, { to_user_id: transferTo }).then(res => {
+            if (res.data.success) {
+                message.success('已发起转让，等待对方接受');
+                setTransferTo(null);
+            } else {
+                message.error(res.data.message || '转让失败');
+            }
+        });
+    };
+
+    const inviteLinkColumns = [
+        { title: '职责', dataIndex: 'role_name', key: 'role_name', render: (name: string) => name || '任意职责' },
+        { title: '过期时间', dataIndex: 'expires_at', key: 'expires_at', render: (d: string) => dayjs(d).format('YYYY-MM-DD HH:mm') },
+        {
+            title: '状态',
+            key: 'state',
+            render: (_: any, record: any) =>
+                record.revoked_at ? <Tag>已撤销</Tag>
+                    : dayjs(record.expires_at).isBefore(dayjs()) ? <Tag>已过期</Tag>
+                        : <Tag color="green">有效</Tag>,
+        },
+        {
+            title: '操作',
+            key: 'action',
+            render: (_: any, record: any) => !record.revoked_at && dayjs(record.expires_at).isAfter(dayjs()) && (
+                <>
+                    <Button size="small" style={{ marginRight: 8 }} onClick={() => copyInviteLink(record.token)}>复制链接</Button>
+                    <Button danger size="small" onClick={() => revokeInviteLink(record.id)}>撤销</Button>
+                </>
+            ),
+        },
+    ];
+
+    const invitationColumns = [
+        { title: '受邀人', dataIndex: 'invitee_name', key: 'invitee_name' },
+        { title: '职责', dataIndex: 'role_name', key: 'role_name' },
+        { title: '状态', dataIndex: 'status', key: 'status' },
+        { title: '邀请时间', dataIndex: 'created_at', key: 'created_at', render: (d: string) => dayjs(d).format('YYYY-MM-DD HH:mm') },
+    ];
+
+    const fetchHistory = () => {
+        axios.get(`/api/tasks/${taskId}/membership_history`).then(res => setHistory(res.data));
+    };
+
+    useEffect(() => {
+        if (taskId) {
+            fetchHistory();
+        }
+    }, [taskId, members]);
+
+    const historyColumns = [
+        { title: '时间', dataIndex: 'created_at', key: 'created_at', render: (d: string) => dayjs(d).format('YYYY-MM-DD HH:mm') },
+        { title: '成员', key: 'user', render: (_: any, record: any) => record.name || record.username },
+        { title: '职责', dataIndex: 'role_name', key: 'role_name' },
+        { title: '变动', dataIndex: 'event', key: 'event' },
+        { title: '操作人', dataIndex: 'actor_name', key: 'actor_name' },
+        { title: '原因', dataIndex: 'reason', key: 'reason' },
+    ];
+
+    const fetchRubric = () => {
+        axios.get(`/api/tasks/${taskId}/evaluation_rubric`).then(res => setRubric(res.data));
+    };
+
+    useEffect(() => {
+        if (taskId) {
+            fetchRubric();
+        }
+    }, [taskId]);
+
+    const onCriterionChange = (idx: number, value: any) => {
+        setRubric(r => ({ ...r, criteria: r.criteria.map((c, i) => (i === idx ? { ...c, ...value } : c)) }));
+    };
+
+    const saveRubric = () => {
+        axios.post(`/api/tasks/${taskId}/evaluation_rubric`, {
+            criteria: rubric.criteria,
+        }).then(res => {
+            if (res.data.success) {
+                message.success('评分标准已保存');
+                fetchRubric();
+            } else {
+                message.error(res.data.message || '保存失败');
+            }
+        });
+    };
+
+    const saveEvaluationSettings = () => {
+        axios.post(`/api/tasks/${taskId}/evaluation_settings`, {
+            anonymous: rubric.anonymous,
+            min_ratings: rubric.min_ratings,
+        }).then(res => {
+            if (res.data.success) {
+                message.success('匿名设置已保存');
+                fetchRubric();
+            } else {
+                message.error(res.data.message || '保存失败');
+            }
+        });
+    };
+
+    const fetchContributions = () => {
+        axios.get(`/api/tasks/${taskId}/contribution_weights`).then(res => setWeights(res.data.weights));
+        axios.get(`/api/tasks/${taskId}/contributions`)
+            .then(res => setContributions(res.data))
+            .catch(() => message.error('无法加载贡献度'));
+    };
+
+    useEffect(() => {
+        if (task) {
+            fetchContributions();
+        }
+    }, [task]);
+
+    const saveWeights = () => {
+        axios.post(`/api/tasks/${taskId}/contribution_weights`, weights).then(res => {
+            if (res.data.success) {
+                message.success('权重已保存');
+                fetchContributions();
+            } else {
+                message.error(res.data.message || '保存失败');
+            }
+        });
+    };
+
+    // 导出需带登录 token，不能直接 window.open，先取回文件再触发下载
+    const exportContributions = (format: string) => {
+        axios.get(`/api/tasks/${taskId}/contributions`, { params: { format }, responseType: 'blob' }).then(res => {
+            const link = document.createElement('a');
+            link.href = URL.createObjectURL(res.data);
+            link.download = `contributions.${format}`;
+            link.click();
+            URL.revokeObjectURL(link.href);
+        }).catch(() => message.error('导出失败'));
+    };
+
+    const contributionColumns = [
+        { title: '成员', key: 'user', render: (_: any, record: any) => record.name || record.username },
+        { title: '学号', dataIndex: 'student_id', key: 'student_id' },
+        { title: '完成子任务', dataIndex: 'completed_sub_tasks', key: 'completed_sub_tasks' },
+        { title: '工时', dataIndex: 'hours', key: 'hours', render: (v: number) => v.toFixed(1) },
+        { title: '互评综合分', dataIndex: 'evaluation', key: 'evaluation', render: (v: number | null) => (v == null ? '-' : v.toFixed(1)) },
+        { title: '进度记录', dataIndex: 'progress_entries', key: 'progress_entries' },
+        { title: '贡献度', dataIndex: 'score', key: 'score', render: (v: number) => v.toFixed(1) },
+    ];
+
+    // 每个职责的每名成员占一行
+    const fetchMembers = () => {
+        axios.get(`/api/task_roles/${taskId}`).then(res => {
+            setMembers(res.data.flatMap((r: any) =>
+                r.members.map((m: any) => ({ ...m, role_id: r.role_id, role_name: r.role_name }))
+            ));
+        });
+    };
+
     const handleAddSubTask = () => {
         setEditingSubTask(null);
         form.resetFields();
@@ -81,9 +355,10 @@ const TaskDashboard: React.FC = () => {
 
     const handleModalOk = () => {
         form.validateFields().then(values => {
+            const payload = { ...values, user_id: task.creator_id };
             const request = editingSubTask
-                ? axios.put(`/api/tasks/${taskId}/sub_tasks/${editingSubTask.id}`, values)
-                : axios.post(`/api/tasks/${taskId}/sub_tasks`, values);
+                ? axios.put(`/api/tasks/${taskId}/sub_tasks/${editingSubTask.id}`, payload)
+                : axios.post(`/api/tasks/${taskId}/sub_tasks`, payload);
 
             request.then(() => {
                 message.success(`子任务${editingSubTask ? '更新' : '创建'}成功`);
@@ -117,7 +392,7 @@ const TaskDashboard: React.FC = () => {
 
     const columns = [
         { title: '任务名', dataIndex: 'title', key: 'title' },
-        { title: '详情', dataIndex: 'description', key: 'description' },
+        { title: '详情', dataIndex: 'description', key: 'description', render: (text: string, record: any) => text && <MentionText text={text} mentions={record.mentions} /> },
         { title: 'DDL', dataIndex: 'due_date', key: 'due_date', render: (date: string) => date ? new Date(date).toLocaleDateString() : 'N/A' },
         { title: '完成状态', dataIndex: 'status', key: 'status' },
         { title: '所属组员', dataIndex: 'assignee_name', key: 'assignee_name', render: (name: string) => name && name.trim() ? name : '未分配' },
@@ -180,11 +455,20 @@ const TaskDashboard: React.FC = () => {
             render: (_: any, record: any) => (
                 <Popconfirm
                     title="确定要移除该成员吗？"
+                    description={<Input placeholder="移除原因（可选）" value={removeReason} onChange={e => setRemoveReason(e.target.value)} />}
+                    onOpenChange={() => setRemoveReason('')}
                     onConfirm={() => {
-                        axios.post(`/api/task_roles/${record.role_id}/remove_member`).then(() => {
-                            message.success('成员已移除');
-                            axios.get(`/api/task_roles/${taskId}`).then(res => setMembers(res.data));
-                            fetchSubTasks();
+                        axios.post(`/api/task_roles/${record.role_id}/remove_member`, null, {
+                            params: { user_id: record.user_id, operator_id: task.creator_id, reason: removeReason || undefined },
+                        }).then(res => {
+                            if (res.data.success) {
+                                message.success('成员已移除');
+                                fetchMembers();
+                                fetchSubTasks();
+                                fetchHistory();
+                            } else {
+                                message.error(res.data.message || '移除失败');
+                            }
                         });
                     }}
                     okText="是"
@@ -198,7 +482,13 @@ const TaskDashboard: React.FC = () => {
 
     const rolesColumns = [
         { title: '职责', dataIndex: 'role_name', key: 'role_name' },
-        { title: '认领人', key: 'user', render: (_: any, record: any) => record.name || record.username || '未认领' },
+        { title: '名额', key: 'capacity', render: (_: any, record: any) => `${record.members.length}/${record.capacity}` },
+        {
+            title: '认领人',
+            key: 'user',
+            render: (_: any, record: any) =>
+                record.members.length > 0 ? record.members.map((m: any) => m.name || m.username).join('、') : '未认领',
+        },
     ];
 
     if (loading || !task) {
@@ -227,7 +517,7 @@ const TaskDashboard: React.FC = () => {
                             </Descriptions>
                         </div>
                         <div style={{ textAlign: 'center' }}>
-                            <Progress type="circle" percent={0} />
+                            <Progress type="circle" percent={task.progress ?? 0} />
                             <Text style={{ display: 'block', marginTop: 8 }}>0 项紧急任务</Text>
                         </div>
                     </div>
@@ -242,17 +532,132 @@ const TaskDashboard: React.FC = () => {
                         <TabPane tab="成员列表" key="2">
                             <Table
                                 columns={memberColumns}
-                                dataSource={members.map((m: any) => ({ ...m, key: m.role_id }))}
+                                dataSource={members.map((m: any) => ({ ...m, key: `${m.role_id}-${m.user_id}` }))}
                                 pagination={false}
                                 size="small"
                             />
                         </TabPane>
                         <TabPane tab="申请审核" key="3">
-                            <p>这里将是申请审核列表...</p>
+                            <Table columns={joinRequestColumns} dataSource={joinRequests} rowKey="id" />
                         </TabPane>
                         <TabPane tab="职责分配" key="4">
                             <Table columns={rolesColumns} dataSource={roles} rowKey="role_id" />
                         </TabPane>
+                        <TabPane tab="邀请成员" key="5">
+                            <Space style={{ marginBottom: 16 }} wrap>
+                                <Select
+                                    style={{ width: 160 }}
+                                    placeholder="任意职责"
+                                    value={linkRoleId}
+                                    onChange={setLinkRoleId}
+                                    allowClear
+                                    options={roles.map(r => ({ value: r.role_id, label: r.role_name }))}
+                                />
+                                <InputNumber min={1} max={720} value={linkHours} onChange={v => setLinkHours(v || 72)} addonAfter="小时" />
+                                <Button type="primary" onClick={createInviteLink}>生成邀请链接</Button>
+                            </Space>
+                            <Table columns={inviteLinkColumns} dataSource={inviteLinks} rowKey="id" size="small" pagination={false} />
+                            <Space style={{ margin: '24px 0 16px' }} wrap>
+                                <Input placeholder="用户名" value={inviteUsername} onChange={e => setInviteUsername(e.target.value)} style={{ width: 160 }} />
+                                <Select
+                                    style={{ width: 160 }}
+                                    placeholder="选择职责"
+                                    value={inviteRoleId}
+                                    onChange={setInviteRoleId}
+                                    options={roles.map(r => ({ value: r.role_id, label: r.role_name }))}
+                                />
+                                <Button type="primary" onClick={inviteUser}>发送邀请</Button>
+                            </Space>
+                            <Table columns={invitationColumns} dataSource={invitations} rowKey="id" size="small" pagination={false} />
+                        </TabPane>
+                        <TabPane tab="成员变动" key="7">
+                            <Table columns={historyColumns} dataSource={history} rowKey="id" size="small" />
+                        </TabPane>
+                        <TabPane tab="互评标准" key="8">
+                            <Space direction="vertical">
+                                {rubric.criteria.map((c, idx) => (
+                                    <Space key={idx}>
+                                        <Input
+                                            placeholder="标准名称"
+                                            value={c.name}
+                                            disabled={rubric.locked}
+                                            onChange={e => onCriterionChange(idx, { name: e.target.value })}
+                                        />
+                                        <Input
+                                            placeholder="说明"
+                                            value={c.description}
+                                            disabled={rubric.locked}
+                                            onChange={e => onCriterionChange(idx, { description: e.target.value })}
+                                        />
+                                        <InputNumber min={0} max={100} value={c.min_score} disabled={rubric.locked} onChange={v => onCriterionChange(idx, { min_score: v })} />
+                                        <span>至</span>
+                                        <InputNumber min={0} max={100} value={c.max_score} disabled={rubric.locked} onChange={v => onCriterionChange(idx, { max_score: v })} />
+                                        {!rubric.locked && rubric.criteria.length > 1 && (
+                                            <Button onClick={() => setRubric(r => ({ ...r, criteria: r.criteria.filter((_, i) => i !== idx) }))}>删除</Button>
+                                        )}
+                                    </Space>
+                                ))}
+                                {rubric.locked ? (
+                                    <Text type="secondary">已有成员提交评价，评分标准不能再修改</Text>
+                                ) : (
+                                    <Space>
+                                        <Button
+                                            type="dashed"
+                                            onClick={() => setRubric(r => ({ ...r, criteria: [...r.criteria, { name: '', description: '', min_score: 1, max_score: 5 }] }))}
+                                        >
+                                            添加标准
+                                        </Button>
+                                        <Button type="primary" onClick={saveRubric}>保存评分标准</Button>
+                                    </Space>
+                                )}
+                                <Space>
+                                    <Checkbox checked={rubric.anonymous} onChange={e => setRubric(r => ({ ...r, anonymous: e.target.checked }))}>
+                                        匿名互评
+                                    </Checkbox>
+                                    <span>收到至少</span>
+                                    <InputNumber min={2} max={20} value={rubric.min_ratings} onChange={v => setRubric(r => ({ ...r, min_ratings: v || 2 }))} />
+                                    <span>条评价后公开结果</span>
+                                    <Button onClick={saveEvaluationSettings}>保存匿名设置</Button>
+                                </Space>
+                            </Space>
+                        </TabPane>
+                        <TabPane tab="贡献度" key="9">
+                            <Space wrap style={{ marginBottom: 16 }}>
+                                {[['sub_tasks', '完成子任务'], ['hours', '工时'], ['evaluation', '互评'], ['progress', '进度记录']].map(([key, label]) => (
+                                    <InputNumber
+                                        key={key}
+                                        min={0}
+                                        max={100}
+                                        addonBefore={label}
+                                        value={weights[key]}
+                                        onChange={v => setWeights({ ...weights, [key]: v ?? 0 })}
+                                    />
+                                ))}
+                                <Button type="primary" onClick={saveWeights}>保存权重</Button>
+                                <Button onClick={() => exportContributions('csv')}>导出 CSV</Button>
+                                <Button onClick={() => exportContributions('xlsx')}>导出 Excel</Button>
+                            </Space>
+                            <Table columns={contributionColumns} dataSource={contributions} rowKey="user_id" size="small" pagination={false} />
+                        </TabPane>
+                        <TabPane tab="任务聊天" key="10">
+                            <TaskChat taskId={taskId!} userId={task.creator_id} creatorId={task.creator_id} />
+                        </TabPane>
+                        <TabPane tab="转让任务" key="6">
+                            <Space wrap>
+                                <Select
+                                    style={{ width: 200 }}
+                                    placeholder="选择新的发布者"
+                                    value={transferTo}
+                                    onChange={setTransferTo}
+                                    options={members
+                                        .filter((m, i) => m.user_id !== task.creator_id && members.findIndex(o => o.user_id === m.user_id) === i)
+                                        .map(m => ({ value: m.user_id, label: m.name || m.username }))}
+                                />
+                                <Popconfirm title="对方接受后你将不再是该任务的发布者，确定转让吗？" onConfirm={requestTransfer} okText="是" cancelText="否">
+                                    <Button type="primary" disabled={!transferTo}>发起转让</Button>
+                                </Popconfirm>
+                            </Space>
+                        </TabPane>
                     </Tabs>
                 </div>
             </Content>
@@ -267,7 +672,7 @@ const TaskDashboard: React.FC = () => {
                     <Form.Item name="title" label="任务名" rules={[{ required: true, message: '请输入任务名' }]}>
                         <Input />
                     </Form.Item>
-                    <Form.Item name="description" label="详情">
+                    <Form.Item name="description" label="详情" extra="可以用 @用户名 提及任务成员，对方会收到通知">
                         <Input.TextArea rows={4} />
                     </Form.Item>
                     <Form.Item name="due_date" label="DDL">
@@ -290,7 +695,7 @@ const TaskDashboard: React.FC = () => {
                     onChange={setSelectedMember}
                     allowClear
                 >
-                    {members.filter(m => m.user_id).map(m => (
+                    {members.filter((m, i) => members.findIndex(o => o.user_id === m.user_id) === i).map(m => (
                         <Select.Option key={m.user_id} value={m.user_id}>{m.name || m.username}</Select.Option>
                     ))}
                 </Select>
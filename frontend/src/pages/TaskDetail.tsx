@@ -1,7 +1,8 @@
 import React, { useEffect, useState } from "react";
 import { useParams, Link, useNavigate } from "react-router-dom";
-import { Card, Button, Spin, List, message } from "antd";
+import { Card, Button, Spin, List, message, Modal, Input, Popconfirm } from "antd";
 import axios from "axios";
+import TaskChat from "../components/TaskChat";
 
 function parseJwt(token: string) {
     try {
@@ -19,7 +20,8 @@ const TaskDetail: React.FC = () => {
     const [roles, setRoles] = useState<any[]>([]);
     const [claiming, setClaiming] = useState<string | null>(null);
     const [userId, setUserId] = useState<string | null>(null);
-    const [creator, setCreator] = useState<any>(null);
+    const [applyingRole, setApplyingRole] = useState<string | null>(null);
+    const [applyMessage, setApplyMessage] = useState("");
 
     useEffect(() => {
         const token = localStorage.getItem("token");
@@ -30,19 +32,10 @@ const TaskDetail: React.FC = () => {
         }
 
         if (id) {
-            axios.get(`/api/tasks`).then(res => {
-                const found = res.data.find((t: any) => t.id === id);
-                setTask(found);
-                setLoading(false);
-                if (found && found.creator_id) {
-                    axios.get(`/api/user_info/${found.creator_id}`).then(res2 => {
-                        setCreator(res2.data);
-                    });
-                }
-            });
-            axios.get(`/api/task_roles/${id}`).then(res => {
-                setRoles(res.data);
-            });
+            axios.get(`/api/tasks/${id}`).then(res => {
+                setTask(res.data);
+                setRoles(res.data.roles);
+            }).catch(() => setTask(null)).finally(() => setLoading(false));
             // 获取当前用户id
             if (token) {
                 const payload = parseJwt(token);
@@ -51,11 +44,13 @@ const TaskDetail: React.FC = () => {
         }
     }, [id, navigate]);
 
-    const claimRole = async (roleId: string) => {
+    const claimRole = async (roleId: string, note?: string) => {
         setClaiming(roleId);
         try {
-            const res = await axios.post(`/api/claim_role`, { role_id: roleId, user_id: userId });
-            if (res.data.success) {
+            const res = await axios.post(`/api/claim_role`, { role_id: roleId, user_id: userId, message: note });
+            if (res.data.pending) {
+                message.success("已提交申请，等待发布者审核");
+            } else if (res.data.success) {
                 message.success("认领成功");
                 // 刷新职责
                 const res2 = await axios.get(`/api/task_roles/${id}`);
@@ -70,6 +65,23 @@ const TaskDetail: React.FC = () => {
         setClaiming(null);
     };
 
+    const leaveTask = async () => {
+        try {
+            const res = await axios.post(`/api/tasks/${id}/leave`);
+            if (res.data.success) {
+                message.success("已退出任务");
+                const res2 = await axios.get(`/api/task_roles/${id}`);
+                setRoles(res2.data);
+            } else {
+                message.error(res.data.message || "退出失败");
+            }
+        } catch {
+            message.error("网络错误");
+        }
+    };
+
+    const isMember = roles.some(r => r.members.some((m: any) => m.user_id === userId));
+
     if (loading) return <Spin style={{ margin: 40 }} />;
     if (!task) return <div>任务不存在</div>;
 
@@ -77,22 +89,34 @@ const TaskDetail: React.FC = () => {
         <div style={{ maxWidth: 600, margin: "40px auto" }}>
             <Card title={task.title}>
                 <p>{task.description}</p>
-                {creator && (
-                    <div style={{ marginBottom: 12, color: '#888' }}>
-                        <span>发布人：{creator.name || '(未设置昵称)'}（账号：{creator.username}）</span>
-                    </div>
-                )}
+                <div style={{ marginBottom: 12, color: '#888' }}>
+                    <span>发布人：{task.creator_name || '(未设置昵称)'}（账号：{task.creator_username}）</span>
+                </div>
                 <h3>职责分配</h3>
                 <List
                     dataSource={roles}
                     renderItem={item => (
                         <List.Item>
-                            <span>{item.role_name}</span>
-                            {item.user_id ? (
-                                <span style={{ color: "#888", marginLeft: 16 }}>已被认领</span>
+                            <span>
+                                {item.role_name}（{item.members.length}/{item.capacity}）
+                                {item.members.length > 0 && (
+                                    <span style={{ color: "#888", marginLeft: 8 }}>
+                                        {item.members.map((m: any) => m.name || m.username).join("、")}
+                                    </span>
+                                )}
+                            </span>
+                            {item.members.some((m: any) => m.user_id === userId) ? (
+                                <span style={{ color: "#888", marginLeft: 16 }}>已认领</span>
+                            ) : item.closed || item.members.length >= item.capacity ? (
+                                <span style={{ color: "#888", marginLeft: 16 }}>名额已满</span>
                             ) : userId ? (
-                                <Button type="primary" size="small" loading={claiming === item.role_id} onClick={() => claimRole(item.role_id)}>
-                                    认领
+                                <Button
+                                    type="primary"
+                                    size="small"
+                                    loading={claiming === item.role_id}
+                                    onClick={() => task.approval_required ? setApplyingRole(item.role_id) : claimRole(item.role_id)}
+                                >
+                                    {task.approval_required ? "申请" : "认领"}
                                 </Button>
                             ) : (
                                 <span style={{ color: "#888", marginLeft: 16 }}>请登录后认领</span>
@@ -103,7 +127,30 @@ const TaskDetail: React.FC = () => {
                 <Button style={{ marginTop: 16 }}>
                     <Link to="/tasks">返回任务大厅</Link>
                 </Button>
+                {isMember && task.creator_id !== userId && (
+                    <Popconfirm title="退出后将释放你的职责和子任务分配，确定退出吗？" onConfirm={leaveTask} okText="是" cancelText="否">
+                        <Button danger style={{ marginTop: 16, marginLeft: 8 }}>退出任务</Button>
+                    </Popconfirm>
+                )}
             </Card>
+            {userId && (isMember || task.creator_id === userId) && (
+                <Card title="任务聊天" style={{ marginTop: 24 }}>
+                    <TaskChat taskId={id!} userId={userId} creatorId={task.creator_id} />
+                </Card>
+            )}
+            <Modal
+                title="申请加入"
+                open={applyingRole !== null}
+                onOk={async () => {
+                    await claimRole(applyingRole!, applyMessage);
+                    setApplyingRole(null);
+                    setApplyMessage("");
+                }}
+                onCancel={() => setApplyingRole(null)}
+            >
+                <p>该任务需要发布者审核，可以附上留言介绍自己。</p>
+                <Input.TextArea rows={4} value={applyMessage} onChange={e => setApplyMessage(e.target.value)} />
+            </Modal>
         </div>
     );
 };
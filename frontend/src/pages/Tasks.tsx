@@ -1,5 +1,5 @@
 import React, { useEffect, useState } from "react";
-import { List, Input, Layout, Button } from "antd";
+import { List, Input, Layout, Button, Checkbox, Tag } from "antd";
 import { Link, useNavigate } from "react-router-dom";
 import axios from "axios";
 import AppHeader from "../components/AppHeader";
@@ -8,26 +8,36 @@ const { Content } = Layout;
 
 const Tasks: React.FC = () => {
     const [tasks, setTasks] = useState<any[]>([]);
-    const [filteredTasks, setFilteredTasks] = useState<any[]>([]);
+    const [nextCursor, setNextCursor] = useState<string | null>(null);
     const [searchTerm, setSearchTerm] = useState("");
+    const [openRolesOnly, setOpenRolesOnly] = useState(false);
+    const [tagFacets, setTagFacets] = useState<{ tag: string; count: number }[]>([]);
+    const [selectedTag, setSelectedTag] = useState<string | null>(null);
+    const [loading, setLoading] = useState(false);
     const navigate = useNavigate();
 
+    const fetchTasks = (cursor?: string) => {
+        setLoading(true);
+        axios.get("/api/tasks", {
+            params: {
+                q: searchTerm || undefined,
+                has_open_roles: openRolesOnly || undefined,
+                tag: selectedTag || undefined,
+                cursor,
+            },
+        }).then(res => {
+            setTasks(prev => cursor ? [...prev, ...res.data.items] : res.data.items);
+            setNextCursor(res.data.next_cursor);
+        }).finally(() => setLoading(false));
+    };
+
     useEffect(() => {
-        axios.get("/api/tasks").then(res => {
-            setTasks(res.data);
-            setFilteredTasks(res.data);
-        });
+        axios.get("/api/task_tags").then(res => setTagFacets(res.data));
     }, []);
 
     useEffect(() => {
-        const lowercasedValue = searchTerm.toLowerCase();
-        const filtered = tasks.filter(task =>
-            task.title.toLowerCase().includes(lowercasedValue) ||
-            (task.creator_name && task.creator_name.toLowerCase().includes(lowercasedValue)) ||
-            task.creator_username.toLowerCase().includes(lowercasedValue)
-        );
-        setFilteredTasks(filtered);
-    }, [searchTerm, tasks]);
+        fetchTasks();
+    }, [searchTerm, openRolesOnly, selectedTag]);
 
     return (
         <Layout style={{ minHeight: '100vh' }}>
@@ -35,25 +45,59 @@ const Tasks: React.FC = () => {
             <Content style={{ padding: '24px 50px' }}>
                 <div style={{ background: '#fff', padding: 24, borderRadius: 8 }}>
                     <div style={{ display: 'flex', justifyContent: 'space-between', alignItems: 'center', marginBottom: 24 }}>
-                        <Input.Search
-                            placeholder="查找任务发布人或任务名"
-                            onChange={e => setSearchTerm(e.target.value)}
-                            style={{ width: 400 }}
-                            enterButton
-                        />
+                        <div>
+                            <Input.Search
+                                placeholder="搜索任务标题或描述"
+                                onSearch={value => setSearchTerm(value.trim())}
+                                style={{ width: 400 }}
+                                allowClear
+                                enterButton
+                            />
+                            <Checkbox
+                                checked={openRolesOnly}
+                                onChange={e => setOpenRolesOnly(e.target.checked)}
+                                style={{ marginLeft: 16 }}
+                            >
+                                只看有空缺职责
+                            </Checkbox>
+                        </div>
                         <Button type="primary" size="large" onClick={() => navigate("/new_task")}>
                             发布新任务
                         </Button>
                     </div>
+                    {tagFacets.length > 0 && (
+                        <div style={{ marginBottom: 16 }}>
+                            {tagFacets.map(f => (
+                                <Tag.CheckableTag
+                                    key={f.tag}
+                                    checked={selectedTag === f.tag}
+                                    onChange={checked => setSelectedTag(checked ? f.tag : null)}
+                                >
+                                    {f.tag} ({f.count})
+                                </Tag.CheckableTag>
+                            ))}
+                        </div>
+                    )}
                     <List
                         itemLayout="horizontal"
-                        dataSource={filteredTasks}
+                        dataSource={tasks}
+                        loading={loading}
+                        loadMore={nextCursor && (
+                            <div style={{ textAlign: 'center', marginTop: 12 }}>
+                                <Button onClick={() => fetchTasks(nextCursor)} loading={loading}>加载更多</Button>
+                            </div>
+                        )}
                         renderItem={task => (
                             <List.Item
                                 actions={[<Link to={`/task/${task.id}`}>查看详情</Link>]}
                             >
                                 <List.Item.Meta
-                                    title={task.title}
+                                    title={
+                                        <>
+                                            {task.title}
+                                            {task.tags?.map((t: string) => <Tag key={t} style={{ marginLeft: 8 }}>{t}</Tag>)}
+                                        </>
+                                    }
                                     description={
                                         <>
                                             <div>{task.description}</div>
@@ -0,0 +1,73 @@
+import React, { useEffect, useState } from "react";
+import { Avatar, Button, Descriptions, List, Tag, Spin, message } from "antd";
+import { UserOutlined } from "@ant-design/icons";
+import { useParams, Link, useNavigate } from "react-router-dom";
+import axios from "axios";
+import dayjs from "dayjs";
+
+// 用户公开资料：履历、互评平均分与按时完成率
+const UserProfile: React.FC = () => {
+    const { id } = useParams<{ id: string }>();
+    const navigate = useNavigate();
+    const [profile, setProfile] = useState<any>(null);
+
+    useEffect(() => {
+        axios.get(`/api/users/${id}/profile`)
+            .then(res => setProfile(res.data))
+            .catch(() => message.error("无法加载用户资料"));
+    }, [id]);
+
+    if (!profile) {
+        return <Spin style={{ display: "block", margin: "80px auto" }} />;
+    }
+
+    return (
+        <div style={{ maxWidth: 800, margin: "40px auto" }}>
+            <div style={{ display: "flex", alignItems: "center", marginBottom: 24 }}>
+                <Avatar size={64} src={profile.avatar_url} icon={<UserOutlined />} />
+                <h2 style={{ margin: "0 0 0 16px" }}>{profile.display_name}</h2>
+                <span style={{ color: "#888", marginLeft: 8 }}>@{profile.username}</span>
+                {localStorage.getItem("token") && (
+                    <Button style={{ marginLeft: "auto" }} onClick={() => navigate(`/messages?to=${profile.user_id}`)}>发私信</Button>
+                )}
+            </div>
+            <Descriptions bordered column={2} size="small">
+                <Descriptions.Item label="注册时间">{dayjs(profile.created_at).format("YYYY-MM-DD")}</Descriptions.Item>
+                <Descriptions.Item label="完成任务">{profile.completed_tasks.length}</Descriptions.Item>
+                <Descriptions.Item label="互评平均分">
+                    {profile.average_rating == null ? "暂无" : `${profile.average_rating.toFixed(1)}（${profile.rating_count} 条评价）`}
+                </Descriptions.Item>
+                <Descriptions.Item label="按时完成率">
+                    {profile.reliability == null ? "暂无" : `${profile.reliability.toFixed(1)}%（${profile.on_time_sub_tasks}/${profile.due_sub_tasks}）`}
+                </Descriptions.Item>
+            </Descriptions>
+            <List
+                style={{ marginTop: 24 }}
+                header={<div>担任过的职责</div>}
+                dataSource={profile.roles}
+                renderItem={(item: any) => (
+                    <List.Item>
+                        <Link to={`/task/${item.task_id}`}>{item.task_title}</Link>
+                        <span>
+                            {item.role_name}
+                            {item.current && <Tag color="blue" style={{ marginLeft: 8 }}>在任</Tag>}
+                        </span>
+                    </List.Item>
+                )}
+            />
+            <List
+                style={{ marginTop: 24 }}
+                header={<div>已完成的任务</div>}
+                dataSource={profile.completed_tasks}
+                renderItem={(item: any) => (
+                    <List.Item>
+                        <Link to={`/task/${item.task_id}`}>{item.title}</Link>
+                        <Tag>{item.status}</Tag>
+                    </List.Item>
+                )}
+            />
+        </div>
+    );
+};
+
+export default UserProfile;